@@ -0,0 +1,169 @@
+use crate::client::ai::solver::{SolveError, Solver};
+use crate::game::{
+    board::GamePiece,
+    notation,
+    record::{self, GameRecord, HEADER_DATE},
+    state::{GameState, Move},
+};
+
+use serde_json::json;
+use std::io::BufRead;
+
+/// The solved result of a position.
+struct Analysis {
+    to_move: GamePiece,
+    /// Score for the player to move, as described by [`Solver`].
+    score: i32,
+    /// Column, numbered from 1, of a move that achieves the score.
+    best_move: usize,
+}
+
+impl Analysis {
+    /// Who wins with perfect play.
+    fn outcome(&self) -> String {
+        match self.score {
+            s if s > 0 => format!("{} wins", self.to_move.name()),
+            s if s < 0 => format!("{} wins", self.to_move.opponent().name()),
+            _ => "draw".to_string(),
+        }
+    }
+}
+
+/// Solves positions, keeping one [`Solver`] for as long as the board size stays the same so that its results can be reused.
+#[derive(Default)]
+struct Analyst {
+    solver: Option<Solver>,
+}
+
+impl Analyst {
+    fn solver(&mut self, state: &GameState) -> &mut Solver {
+        let board = state.get_board();
+        if !self
+            .solver
+            .as_ref()
+            .is_some_and(|solver| solver.fits(board))
+        {
+            self.solver = None;
+        }
+        self.solver.get_or_insert_with(|| Solver::new(board))
+    }
+
+    fn analyze(&mut self, state: &GameState) -> Result<Analysis, SolveError> {
+        let (best_move, score) = self.solver(state).best_move(state)?;
+        Ok(Analysis {
+            to_move: *state.get_next_player(),
+            score,
+            best_move,
+        })
+    }
+}
+
+/// Reads a position given either as the columns played so far with Red moving first, e.g. `4453`, or in position notation.
+///
+/// Columns are one digit each and played on the standard board, so positions on boards with ten columns must be written in notation.
+pub fn parse_position(text: &str) -> Result<GameState, String> {
+    let text = text.trim();
+    if !text.chars().all(|c| c.is_ascii_digit()) {
+        return notation::from_notation(text).map_err(|e| e.to_string());
+    }
+    if text.contains('0') {
+        return Err(
+            "Columns are numbered from 1; write positions on boards with ten columns in position notation"
+                .to_string(),
+        );
+    }
+    let mut record = GameRecord::new(GamePiece::Red);
+    for column in text.chars().filter_map(|c| c.to_digit(10)) {
+        record.push_move(Move::Drop(column as usize));
+    }
+    record.replay().map_err(|e| e.to_string())
+}
+
+/// Prints the exact score of a position and its best move.
+pub fn solve(position: &str) -> Result<(), String> {
+    let state = parse_position(position)?;
+    let analysis = Analyst::default()
+        .analyze(&state)
+        .map_err(|e| e.to_string())?;
+
+    println!("{} to move", analysis.to_move.name());
+    println!("Score: {} ({})", analysis.score, analysis.outcome());
+    println!("Best move: {}", analysis.best_move);
+    Ok(())
+}
+
+/// Solves each position read from `input`, one per line, printing a line of JSON for each.
+///
+/// Positions that cannot be solved are reported with an `error` field rather than stopping the batch.
+pub fn solve_batch(input: impl BufRead) -> Result<(), String> {
+    let mut analyst = Analyst::default();
+    for line in input.lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        let position = line.trim();
+        if position.is_empty() {
+            continue;
+        }
+        let result = parse_position(position)
+            .and_then(|state| analyst.analyze(&state).map_err(|e| e.to_string()));
+        let output = match result {
+            Ok(analysis) => json!({
+                "position": position,
+                "to_move": analysis.to_move.name(),
+                "score": analysis.score,
+                "best_move": analysis.best_move,
+            }),
+            Err(e) => json!({ "position": position, "error": e }),
+        };
+        println!("{output}");
+    }
+    Ok(())
+}
+
+/// Prints a table for each game in a record file, scoring every move against the best move available.
+pub fn analyze(path: &str) -> Result<(), String> {
+    let records = record::load_records(path)?;
+    if records.is_empty() {
+        return Err(format!("{path} has no games in it"));
+    }
+
+    let mut analyst = Analyst::default();
+    for (i, record) in records.iter().enumerate() {
+        let header = |key: &str| record.get_header(key).unwrap_or(record::UNKNOWN_VALUE);
+        if i > 0 {
+            println!();
+        }
+        println!(
+            "Game {}: {} ({})",
+            i + 1,
+            record.matchup(),
+            header(HEADER_DATE)
+        );
+        let positions = record.positions().map_err(|e| e.to_string())?;
+
+        println!("Move  Player  Played  Score  Best  Best score  Verdict");
+        for (n, recorded) in record.get_moves().iter().enumerate() {
+            let position = &positions[n];
+            let error = |e: SolveError| format!("Move {}: {e}", n + 1);
+            let analysis = analyst.analyze(position).map_err(error)?;
+            let played = analyst
+                .solver(position)
+                .score_move(position, recorded.game_move.column())
+                .map_err(error)?;
+            let verdict = match (played, analysis.score) {
+                (played, best) if played == best => "",
+                (played, best) if played.signum() < best.signum() => "blunder",
+                _ => "inaccurate",
+            };
+            println!(
+                "{:>4}  {:<6}  {:>6}  {:>5}  {:>4}  {:>10}  {verdict}",
+                n + 1,
+                analysis.to_move.name(),
+                recorded.game_move.to_string(),
+                played,
+                analysis.best_move,
+                analysis.score,
+            );
+        }
+    }
+    Ok(())
+}
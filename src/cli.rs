@@ -0,0 +1,230 @@
+#[cfg(feature = "online")]
+use fourstack::client::online::DEFAULT_PORT;
+use fourstack::game::{FreeForAll, GameMode, Variant};
+use fourstack::settings::{AiLevel, FirstPlayer, Settings};
+
+use clap::{Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+
+/// A four-in-a-row game for your terminal.
+///
+/// Without a command, FourStack opens the main menu.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Use the plain, line-based interface instead of the full-screen one
+    #[arg(long, global = true)]
+    pub plain: bool,
+    /// Use the plain interface and describe the board in words, for screen readers
+    #[arg(long, global = true)]
+    pub narrate: bool,
+    /// Seed for random choices, such as who moves first, so that games can be repeated
+    #[arg(long, global = true)]
+    pub seed: Option<u64>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Start a game straight away, skipping the main menu
+    Play {
+        /// Who to play against [default: the mode chosen in settings]
+        #[arg(long, value_enum)]
+        mode: Option<ModeArg>,
+        /// How strongly the AI plays
+        #[arg(long, value_enum)]
+        level: Option<LevelArg>,
+        /// Who moves first; `human` and `ai` pick a side against the AI, the rest pick a colour
+        #[arg(long, value_enum)]
+        first: Option<FirstArg>,
+        /// Play against this engine command instead of the built-in AI, e.g. "fourstack engine"
+        #[arg(long)]
+        engine: Option<String>,
+        /// Rules to play by
+        #[arg(long, value_enum)]
+        variant: Option<VariantArg>,
+        /// Number of neutral spaces to block at random, which pieces stack on and lines can't pass through
+        #[arg(long, conflicts_with = "layout")]
+        blockers: Option<usize>,
+        /// File marking the spaces to block, one row per line from the top, with `#` for blocked and `.` for open spaces
+        #[arg(long)]
+        layout: Option<PathBuf>,
+        /// Number of players taking turns, from 2 to 4; the AI plays every player but one
+        #[arg(long)]
+        players: Option<usize>,
+        /// How games between more than two players are decided
+        #[arg(long, value_enum)]
+        free_for_all: Option<FreeForAllArg>,
+    },
+    /// Host an online game and wait for an opponent to connect
+    #[cfg(feature = "online")]
+    Host {
+        /// Port to listen on
+        #[arg(long, default_value_t = DEFAULT_PORT)]
+        port: u16,
+    },
+    /// Join an online game, e.g. `fourstack connect example.com:4040`
+    #[cfg(feature = "online")]
+    Connect {
+        /// Host name or IP address of the host, and the port they are listening on
+        address: String,
+    },
+    /// Watch the games saved in a record file
+    Replay {
+        /// File of game records, such as one written by exporting a game
+        file: String,
+    },
+    /// Print the exact score of a position with perfect play, and its best move
+    #[cfg(feature = "ai")]
+    Solve {
+        /// Columns played so far with Red moving first, e.g. `4453`, or a position in notation
+        #[arg(required_unless_present = "batch")]
+        position: Option<String>,
+        /// Read positions from stdin, one per line, and print a line of JSON for each
+        #[arg(long, conflicts_with = "position")]
+        batch: bool,
+    },
+    /// Score every move of the games in a record file against the best move
+    #[cfg(feature = "ai")]
+    Analyze {
+        /// File of game records, such as one written by exporting a game
+        file: String,
+    },
+    /// Answer commands from another program on stdin, such as `position startpos moves 4 4 3` and `go movetime 1000`
+    #[cfg(feature = "ai")]
+    Engine,
+}
+
+/// Game modes that can be started with `play`; online games are started with `host` or `connect`.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum ModeArg {
+    Ai,
+    Local,
+}
+
+impl From<ModeArg> for GameMode {
+    fn from(mode: ModeArg) -> Self {
+        match mode {
+            ModeArg::Ai => Self::Ai,
+            ModeArg::Local => Self::Local,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum LevelArg {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl From<LevelArg> for AiLevel {
+    fn from(level: LevelArg) -> Self {
+        match level {
+            LevelArg::Easy => Self::Easy,
+            LevelArg::Medium => Self::Medium,
+            LevelArg::Hard => Self::Hard,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum FirstArg {
+    Human,
+    Ai,
+    Red,
+    Yellow,
+    Green,
+    Blue,
+    Random,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum VariantArg {
+    Classic,
+    #[value(name = "popout")]
+    PopOut,
+    Cylinder,
+    Misere,
+}
+
+impl From<VariantArg> for Variant {
+    fn from(variant: VariantArg) -> Self {
+        match variant {
+            VariantArg::Classic => Self::Classic,
+            VariantArg::PopOut => Self::PopOut,
+            VariantArg::Cylinder => Self::Cylinder,
+            VariantArg::Misere => Self::Misere,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum FreeForAllArg {
+    /// The first player to complete a line wins
+    FirstToConnect,
+    /// Last player standing: completing a line knocks a player out instead of winning, and the last player left wins
+    Elimination,
+}
+
+impl From<FreeForAllArg> for FreeForAll {
+    fn from(free_for_all: FreeForAllArg) -> Self {
+        match free_for_all {
+            FreeForAllArg::FirstToConnect => Self::FirstToConnect,
+            FreeForAllArg::Elimination => Self::Elimination,
+        }
+    }
+}
+
+impl Command {
+    /// Applies any options that override the saved settings for this run only.
+    pub fn apply_to(&self, settings: &mut Settings) {
+        let Self::Play {
+            level,
+            first,
+            engine,
+            variant,
+            blockers,
+            layout,
+            players,
+            free_for_all,
+            ..
+        } = self
+        else {
+            return;
+        };
+        if let Some(engine) = engine {
+            settings.ai.engine = engine.clone();
+        }
+        if let Some(level) = level {
+            settings.ai.level = (*level).into();
+        }
+        if let Some(variant) = variant {
+            settings.game.variant = (*variant).into();
+        }
+        if let Some(blockers) = blockers {
+            settings.game.blockers = *blockers;
+            settings.game.layout = None;
+        }
+        if let Some(layout) = layout {
+            settings.game.layout = Some(layout.clone());
+        }
+        if let Some(players) = players {
+            settings.game.players = *players;
+        }
+        if let Some(free_for_all) = free_for_all {
+            settings.game.free_for_all = (*free_for_all).into();
+        }
+        match first {
+            Some(FirstArg::Human) => settings.ai.human_first = true,
+            Some(FirstArg::Ai) => settings.ai.human_first = false,
+            Some(FirstArg::Red) => settings.game.first_player = FirstPlayer::Red,
+            Some(FirstArg::Yellow) => settings.game.first_player = FirstPlayer::Yellow,
+            Some(FirstArg::Green) => settings.game.first_player = FirstPlayer::Green,
+            Some(FirstArg::Blue) => settings.game.first_player = FirstPlayer::Blue,
+            Some(FirstArg::Random) => settings.game.first_player = FirstPlayer::Random,
+            None => (),
+        }
+    }
+}
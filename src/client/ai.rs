@@ -1,87 +1,562 @@
+/// Search for games between more than two players.
+pub mod paranoid;
+/// Exact scoring of positions with perfect play.
+pub mod solver;
+
 use super::{ClientError, GameClient};
 use crate::game::{
-    board::GRID_COLS,
-    state::{EndgameType, GameState},
+    board::{GamePiece, MIN_PLAYERS},
+    random,
+    record::{player_header, GameRecord, Repetitions, HEADER_LEVEL},
+    state::{EndgameType, GameState, Move},
+    transposition::{Bound, TranspositionTable},
+    GameMode,
 };
+use crate::settings::{AiLevel, Settings};
+
+use minimax::{Evaluation, Evaluator, Game, BEST_EVAL, WORST_EVAL};
+use std::time::{Duration, Instant};
 
-use minimax::Strategy;
+/// Name recorded for the AI player in game records.
+const AI_NAME: &str = "FourStack AI";
+/// Number of moves the AI remembers choosing, which is plenty for the positions of one game.
+const TABLE_SIZE: usize = 4093;
+/// Number of positions a search remembers the scores of, chosen to be prime so that keys spread evenly.
+const SEARCH_TABLE_SIZE: usize = 65_521;
+/// Scores this close to a certain win or loss are wins or losses some moves away.
+const DECISIVE_MARGIN: Evaluation = 100;
 
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Debug)]
 pub struct AiGameClient {
     game_state: GameState,
+    record: GameRecord,
+    /// How often each position has been reached, to find draws by repetition.
+    repetitions: Repetitions,
+    /// The piece the human plays; the AI plays every other piece.
+    human_piece: GamePiece,
+    level: AiLevel,
+    think_time: Duration,
+    /// Moves already chosen, so that positions reached again, such as after an undo, are answered straight away.
+    table: TranspositionTable<Move>,
+}
+
+impl AiGameClient {
+    pub fn new(settings: &Settings) -> Result<Self, ClientError> {
+        let players = settings.game.players;
+        let first_player = settings.game.first_player.pick(players);
+        let human_piece = match settings.ai.human_first {
+            true => first_player,
+            false => first_player.next(players),
+        };
+        let (game_state, mut record) = super::new_game(settings, first_player, GameMode::Ai)?;
+        for piece in game_state.all_players() {
+            if *piece != human_piece {
+                record.set_header(player_header(*piece), AI_NAME);
+            }
+        }
+        record.set_header(HEADER_LEVEL, settings.ai.level.to_string());
+
+        let repetitions = Repetitions::new(&game_state);
+
+        let mut client = Self {
+            game_state,
+            record,
+            repetitions,
+            human_piece,
+            level: settings.ai.level,
+            think_time: settings.ai.think_time(),
+            table: TranspositionTable::new(TABLE_SIZE),
+        };
+        client.play_ai_move();
+        Ok(client)
+    }
+
+    /// Makes the AI's moves until it is the human's turn or the game is over.
+    fn play_ai_move(&mut self) {
+        while *self.game_state.get_next_player() != self.human_piece
+            && self.get_endgame() == EndgameType::None
+        {
+            let Some(ai_move) = self.find_move() else {
+                return;
+            };
+            super::process_move(
+                &mut self.game_state,
+                &mut self.record,
+                &mut self.repetitions,
+                ai_move,
+            )
+            .expect("AI should only choose legal moves");
+        }
+    }
+
+    /// Looks up the move chosen the last time the position, or its reflection, was reached, or searches for one.
+    fn find_move(&mut self) -> Option<Move> {
+        let max_depth = self.level.depth();
+        if let Some(hit) = self.table.get(&self.game_state) {
+            if hit.depth >= max_depth {
+                return Some(match hit.mirrored {
+                    true => hit.value.mirrored(self.game_state.get_board()),
+                    false => hit.value,
+                });
+            }
+        }
+        let mut depth = 0;
+        let ai_move = search(&self.game_state, max_depth, self.think_time, |info| {
+            depth = info.depth
+        })?;
+        self.table.insert(&self.game_state, depth, ai_move);
+        Some(ai_move)
+    }
 }
 
 impl GameClient for AiGameClient {
     fn get_current_state(&self) -> GameState {
         self.game_state
     }
-    fn handle_input_move(&mut self, column_num: usize) -> Result<GameState, ClientError> {
-        let state_ref = &mut self.game_state;
+    fn handle_input_move(&mut self, game_move: Move) -> Result<GameState, ClientError> {
         // First handle the user's move
-        super::process_move(state_ref, column_num)?;
+        super::process_move(
+            &mut self.game_state,
+            &mut self.record,
+            &mut self.repetitions,
+            game_move,
+        )?;
         // Now we generate and handle the AI's move
-        let mut strategy = minimax::Negamax::new(NaiveEvaluator, 7);
-        if let Some(ai_move) = strategy.choose_move(state_ref) {
-            super::process_move(&mut self.game_state, ai_move)
-            // Simulate thinking
-            // TODO uncomment when UI threading behavior is reworked
-            // std::thread::sleep(std::time::Duration::from_secs(1));
-        } else {
-            // Endgame, pass
-            Ok(*state_ref)
+        self.play_ai_move();
+        Ok(self.game_state)
+    }
+
+    fn undo_move(&mut self) -> Result<GameState, ClientError> {
+        let human_piece = self.human_piece;
+        super::undo_human_move(
+            &mut self.game_state,
+            &mut self.record,
+            &mut self.repetitions,
+            |piece| piece == human_piece,
+        )
+    }
+
+    fn get_record(&self) -> GameRecord {
+        super::finish_record(&self.game_state, &self.record, &self.repetitions)
+    }
+
+    fn get_endgame(&self) -> EndgameType {
+        super::endgame(&self.game_state, &self.repetitions)
+    }
+}
+
+/// What the AI found when searching a position to some depth.
+#[derive(Clone, Copy, Debug)]
+pub struct SearchInfo {
+    /// How many moves ahead were searched.
+    pub depth: u8,
+    /// Score of the best move for the player to move, from [`minimax::WORST_EVAL`] for a certain loss to [`minimax::BEST_EVAL`] for a certain win.
+    pub score: minimax::Evaluation,
+    pub best_move: Move,
+    /// Time spent searching so far.
+    pub elapsed: Duration,
+}
+
+/// Chooses a move for the player to move, searching up to `max_depth` moves ahead.
+///
+/// The search deepens one move at a time, and stops early rather than start a deeper search that is unlikely to finish within `think_time`.
+///
+/// Repeated positions are not foreseen, as the search only sees the position and not how it was reached.
+pub fn choose_move(state: &GameState, max_depth: u8, think_time: Duration) -> Option<Move> {
+    search(state, max_depth, think_time, |_| ())
+}
+
+/// Chooses a move like [`choose_move`], reporting the result of each depth as it is searched.
+pub fn search(
+    state: &GameState,
+    max_depth: u8,
+    think_time: Duration,
+    mut report: impl FnMut(&SearchInfo),
+) -> Option<Move> {
+    let started = Instant::now();
+    // Lines are scored as threats or as traps, depending on whether completing one wins
+    let mut search_depth: Box<dyn FnMut(u8) -> Option<(Move, Evaluation)>> = match (
+        state.player_count() > MIN_PLAYERS,
+        state.rules().lines_lose(),
+    ) {
+        (true, _) => Box::new(|depth| paranoid::search_depth(state, depth)),
+        (false, true) => {
+            let mut searcher = Searcher::new(AvoidanceEvaluator);
+            Box::new(move |depth| searcher.search_depth(state, depth))
+        }
+        (false, false) => {
+            let mut searcher = Searcher::new(WindowEvaluator);
+            Box::new(move |depth| searcher.search_depth(state, depth))
+        }
+    };
+    let mut best = None;
+    let mut last_search = Duration::ZERO;
+    for depth in 1..=max_depth {
+        // Each extra move multiplies the work by up to the number of moves available
+        let estimate = last_search * state.legal_moves().len() as u32;
+        if best.is_some() && estimate > think_time.saturating_sub(started.elapsed()) {
+            break;
+        }
+        let search_started = Instant::now();
+        let Some((best_move, score)) = search_depth(depth) else {
+            break;
+        };
+        best = Some(best_move);
+        last_search = search_started.elapsed();
+        report(&SearchInfo {
+            depth,
+            score,
+            best_move,
+            elapsed: started.elapsed(),
+        });
+    }
+    best
+}
+
+/// A score found for a position by a [`Searcher`].
+#[derive(Clone, Copy, Debug)]
+struct Scored {
+    score: Evaluation,
+    bound: Bound,
+    /// The best move found, which is searched first if the position is searched again.
+    best_move: Option<Move>,
+}
+
+/// An alpha-beta search for games between two players, which remembers the score of every position it searches.
+///
+/// Positions reached by more than one order of moves are looked up in its table instead of searched again.
+/// The table is kept between depths, so each deeper search starts with the best moves found by the last one.
+struct Searcher<E> {
+    evaluator: E,
+    table: TranspositionTable<Scored>,
+    /// Number of positions whose scores were taken from the table instead of searched.
+    table_hits: u64,
+}
+
+impl<E: Evaluator<G = FourStackRules>> Searcher<E> {
+    fn new(evaluator: E) -> Self {
+        Self {
+            evaluator,
+            table: TranspositionTable::new(SEARCH_TABLE_SIZE),
+            table_hits: 0,
+        }
+    }
+
+    /// Searches a position to a fixed depth, returning the best move and its score.
+    ///
+    /// Moves are tried in a random order, so the AI picks at random between equally good ones.
+    fn search_depth(&mut self, state: &GameState, depth: u8) -> Option<(Move, Evaluation)> {
+        if depth == 0 || FourStackRules::get_winner(state).is_some() {
+            return None;
+        }
+        let mut moves = state.legal_moves();
+        random::shuffle(&mut moves);
+        try_first(&mut moves, self.table_move(state));
+
+        let mut best: Option<(Move, Evaluation)> = None;
+        for game_move in moves {
+            let alpha = best.map_or(WORST_EVAL, |(_, score)| score);
+            let score = -self.negamax(&play(state, game_move), depth - 1, -BEST_EVAL, -alpha);
+            if best.is_none_or(|(_, best_score)| score > best_score) {
+                best = Some((game_move, score));
+            }
+        }
+        let (best_move, score) = best?;
+        self.store(state, depth, decay(score), Bound::Exact, Some(best_move));
+        Some((best_move, undo_decay(score)))
+    }
+
+    /// Scores a position for the player to move within the window `alpha` to `beta`.
+    ///
+    /// Scores outside the window are only bounds: a score of at most `alpha` is an upper bound, and one of at least `beta` a lower bound.
+    fn negamax(
+        &mut self,
+        state: &GameState,
+        depth: u8,
+        mut alpha: Evaluation,
+        mut beta: Evaluation,
+    ) -> Evaluation {
+        if let Some(winner) = FourStackRules::get_winner(state) {
+            return winner.evaluate();
+        }
+        if depth == 0 {
+            return self.evaluator.evaluate(state);
+        }
+
+        if let Some(hit) = self.table.get(state).filter(|hit| hit.depth >= depth) {
+            self.table_hits += 1;
+            let stored = hit.value;
+            match stored.bound {
+                Bound::Exact => return stored.score,
+                Bound::Lower => alpha = alpha.max(stored.score),
+                Bound::Upper => beta = beta.min(stored.score),
+            }
+            if alpha >= beta {
+                return stored.score;
+            }
+        }
+
+        let mut moves = ordered_moves(state);
+        try_first(&mut moves, self.table_move(state));
+        let (original_alpha, mut best, mut best_move) = (alpha, WORST_EVAL, None);
+        for game_move in moves {
+            let score = -self.negamax(&play(state, game_move), depth - 1, -beta, -alpha);
+            if score > best {
+                (best, best_move) = (score, Some(game_move));
+            }
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
         }
+        let bound = match best {
+            score if score <= original_alpha => Bound::Upper,
+            score if score >= beta => Bound::Lower,
+            _ => Bound::Exact,
+        };
+        let score = decay(best);
+        self.store(state, depth, score, bound, best_move);
+        score
+    }
+
+    /// Finds the best move stored for the position, reflected if it was stored for the reflection.
+    fn table_move(&self, state: &GameState) -> Option<Move> {
+        let hit = self.table.get(state)?;
+        let best_move = hit.value.best_move?;
+        Some(match hit.mirrored {
+            true => best_move.mirrored(state.get_board()),
+            false => best_move,
+        })
+    }
+
+    fn store(
+        &mut self,
+        state: &GameState,
+        depth: u8,
+        score: Evaluation,
+        bound: Bound,
+        best_move: Option<Move>,
+    ) {
+        // Moves are stored as played in the canonical form of the position, which is the one looked up
+        let best_move = best_move.map(|m| match state.get_board().is_canonical() {
+            true => m,
+            false => m.mirrored(state.get_board()),
+        });
+        let scored = Scored {
+            score,
+            bound,
+            best_move,
+        };
+        self.table.insert(state, depth, scored);
     }
 }
 
+/// Moves wins and losses a step closer to even for each move they are away, so the quickest win and the slowest loss are preferred.
+///
+/// The score of a position only depends on the position and not the path to it, so it can be stored and looked up wherever it is reached.
+fn decay(score: Evaluation) -> Evaluation {
+    match score {
+        s if s > BEST_EVAL - DECISIVE_MARGIN => s - 1,
+        s if s < WORST_EVAL + DECISIVE_MARGIN => s + 1,
+        s => s,
+    }
+}
+
+/// Turns a score made by [`decay`] back into a certain win or loss.
+fn undo_decay(score: Evaluation) -> Evaluation {
+    match score {
+        s if s > BEST_EVAL - DECISIVE_MARGIN => BEST_EVAL,
+        s if s < WORST_EVAL + DECISIVE_MARGIN => WORST_EVAL,
+        s => s,
+    }
+}
+
+/// Legal moves from the middle of the board outwards, as central moves tend to be better and so cut off more of the search.
+fn ordered_moves(state: &GameState) -> Vec<Move> {
+    let middle = state.get_board().cols().div_ceil(2);
+    let mut moves = state.legal_moves();
+    moves.sort_by_key(|game_move| game_move.column().abs_diff(middle));
+    moves
+}
+
+/// Moves a move, if it is one of the given moves, to the front while keeping the others in order.
+fn try_first(moves: &mut [Move], first: Option<Move>) {
+    if let Some(i) = moves.iter().position(|m| Some(*m) == first) {
+        moves[..=i].rotate_right(1);
+    }
+}
+
+fn play(state: &GameState, game_move: Move) -> GameState {
+    let mut next = *state;
+    next.play(game_move)
+        .expect("Only legal moves should be searched");
+    next
+}
+
 pub struct FourStackRules;
 impl minimax::Game for FourStackRules {
     type S = GameState;
-    type M = usize;
+    type M = Move;
 
     fn generate_moves(state: &Self::S, moves: &mut Vec<Self::M>) {
-        for i in 1..GRID_COLS {
-            let mut board = *state.get_board();
-            if board.insert_piece(*state.get_next_player(), i).is_ok() {
-                moves.push(i);
-            }
-        }
+        moves.extend(state.legal_moves());
     }
 
     fn apply(state: &mut Self::S, m: Self::M) -> Option<Self::S> {
-        let player = *state.get_next_player();
-        let board = state.get_board_mut();
-        board.insert_piece(player, m).ok()?;
-        Some(*state)
+        // The state is small, so apply moves to a copy rather than implementing undo
+        let mut next = *state;
+        next.play(m)
+            .expect("Only generated moves should be applied");
+        Some(next)
+    }
+
+    fn zobrist_hash(state: &Self::S) -> u64 {
+        state.zobrist()
     }
 
+    /// Draws by repetition are out of scope: a position does not know how it was reached, so one that repeats the game's history is scored like any other.
+    ///
+    /// In PopOut games, where positions can repeat, the AI may therefore steer into a draw by repetition while expecting to win.
     fn get_winner(state: &Self::S) -> Option<minimax::Winner> {
         use minimax::Winner;
         match state.check_endgame() {
-            EndgameType::Full => Some(Winner::Draw),
+            EndgameType::Full | EndgameType::Repetition => Some(Winner::Draw),
             EndgameType::None => None,
             EndgameType::Win(p) => Some({
                 if p.eq(state.get_next_player()) {
-                    Winner::PlayerJustMoved
-                } else {
                     Winner::PlayerToMove
+                } else {
+                    Winner::PlayerJustMoved
                 }
             }),
         }
     }
 }
 
-// TODO need to improve this
-pub struct NaiveEvaluator;
-impl minimax::Evaluator for NaiveEvaluator {
+/// Scores positions by the lines each player could still complete.
+///
+/// Every window of spaces as long as the win length that holds only one player's pieces counts towards that player, more so the fuller it is.
+/// Windows that wrap around the sides of a board that wraps count like any other.
+pub struct WindowEvaluator;
+impl minimax::Evaluator for WindowEvaluator {
+    type G = FourStackRules;
+
+    fn evaluate(&self, s: &<Self::G as minimax::Game>::S) -> minimax::Evaluation {
+        let (board, player) = (s.get_board(), *s.get_next_player());
+        board
+            .windows()
+            .map(|window| {
+                let (mut own, mut theirs) = (0, 0);
+                for (row, col) in window {
+                    match board.get_piece(row, col) {
+                        Some(piece) if piece == player => own += 1,
+                        Some(_) => theirs += 1,
+                        None => (),
+                    }
+                }
+                match (own, theirs) {
+                    (n, 0) => n * n,
+                    (0, n) => -(n * n),
+                    _ => 0,
+                }
+            })
+            .sum()
+    }
+}
+
+/// Weight of each safe move the player to move has over their opponent, in [`AvoidanceEvaluator`].
+const SAFE_MOVE_WEIGHT: minimax::Evaluation = 8;
+
+/// Scores positions for rules where completing a line loses, by how well each player can keep from completing one.
+///
+/// A player whose every move completes a line of their own is forced to lose, so each move that doesn't counts in their favour.
+/// Windows filling up with a player's pieces count against them, as they leave fewer safe places to play later.
+pub struct AvoidanceEvaluator;
+impl minimax::Evaluator for AvoidanceEvaluator {
     type G = FourStackRules;
 
     fn evaluate(&self, s: &<Self::G as minimax::Game>::S) -> minimax::Evaluation {
-        match s.check_endgame() {
-            EndgameType::Win(player) => match player.eq(s.get_next_player()) {
-                true => -1,
-                false => 1,
-            },
-            _ => 0,
+        let (board, player) = (s.get_board(), *s.get_next_player());
+        let moves = s.legal_moves().len();
+        let safe_moves =
+            |piece| (moves - board.winning_columns(piece).len()) as minimax::Evaluation;
+        let safe_lead = safe_moves(player) - safe_moves(player.opponent());
+        SAFE_MOVE_WEIGHT * safe_lead - WindowEvaluator.evaluate(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{board::GameBoard, Variant};
+    use minimax::Strategy;
+
+    fn play_moves(variant: Variant, moves: &[usize]) -> GameState {
+        let mut state =
+            GameState::with_board(GameBoard::default(), GamePiece::Red).with_variant(variant);
+        for col in moves {
+            state.play_move(*col).unwrap();
+        }
+        state
+    }
+
+    #[test]
+    fn looks_up_transpositions() {
+        let state = play_moves(Variant::Classic, &[4, 3]);
+        let mut searcher = Searcher::new(WindowEvaluator);
+        searcher.search_depth(&state, 1).unwrap();
+        assert_eq!(0, searcher.table_hits);
+
+        // Red dropping into two columns in either order reaches the same position, which is then searched one move further
+        searcher.search_depth(&state, 4).unwrap();
+        assert!(searcher.table_hits > 0);
+        let hit = searcher.table.get(&state).unwrap();
+        assert_eq!(4, hit.depth);
+        assert_eq!(Bound::Exact, hit.value.bound);
+    }
+
+    #[test]
+    fn one_sided_layout_is_not_mirrored() {
+        // Column 1 is blocked all the way up, so the reflection of a position is not on the same board
+        let mut board = GameBoard::default();
+        for row in 0..board.rows() {
+            board.block_space(row, 0);
+        }
+        let mut left = GameState::with_board(board, GamePiece::Red);
+        left.play_move(2).unwrap();
+        let mut right = GameState::with_board(board, GamePiece::Red);
+        right.play_move(6).unwrap();
+        assert_ne!(left.canonical_zobrist(), right.canonical_zobrist());
+
+        // A move into column 7 chosen on one side must not be reflected into the blocked column on the other
+        let mut table = TranspositionTable::new(TABLE_SIZE);
+        table.insert(&left, 4, Move::Drop(7));
+        assert_eq!(None, table.get(&right));
+        assert_eq!(Some(Move::Drop(7)), table.get(&left).map(|hit| hit.value));
+
+        let mut searcher = Searcher::new(WindowEvaluator);
+        for state in [left, right] {
+            let (best_move, _) = searcher.search_depth(&state, 5).unwrap();
+            assert!(state.legal_moves().contains(&best_move));
+        }
+    }
+
+    #[test]
+    fn matches_plain_negamax() {
+        for (variant, moves) in [
+            (Variant::Classic, &[4, 4, 3][..]),
+            (Variant::Classic, &[4, 4, 5, 5, 3]),
+            (Variant::Classic, &[1, 2, 1, 2, 1]),
+            (Variant::PopOut, &[4, 3, 4, 3]),
+        ] {
+            let state = play_moves(variant, moves);
+            let mut searcher = Searcher::new(WindowEvaluator);
+            for depth in 1..=5 {
+                let mut negamax = minimax::Negamax::new(WindowEvaluator, depth);
+                negamax.choose_move(&state).unwrap();
+                let (_, score) = searcher.search_depth(&state, depth).unwrap();
+                assert_eq!(negamax.root_value(), score, "{moves:?} at depth {depth}");
+            }
         }
     }
 }
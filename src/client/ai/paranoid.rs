@@ -0,0 +1,149 @@
+use super::{ordered_moves, play};
+use crate::game::{
+    board::GamePiece,
+    state::{EndgameType, GameState, Move},
+    FreeForAll,
+};
+
+use minimax::{Evaluation, BEST_EVAL, WORST_EVAL};
+
+/// Searches a position between more than two players to a fixed depth, returning the best move for the player to move and its score.
+///
+/// The search is paranoid: it assumes every other player chooses the move that is worst for the player to move, as if they had all teamed up.
+/// This makes the game two-sided again, so branches can be cut off as in a two-player alpha-beta search, where a max-n search could cut off almost none.
+pub fn search_depth(state: &GameState, depth: u8) -> Option<(Move, Evaluation)> {
+    let player = *state.get_next_player();
+    let mut best: Option<(Move, Evaluation)> = None;
+    for game_move in ordered_moves(state) {
+        let alpha = best.map_or(WORST_EVAL, |(_, score)| score);
+        let score = paranoid(
+            &play(state, game_move),
+            player,
+            depth - 1,
+            1,
+            alpha,
+            BEST_EVAL,
+        );
+        if best.is_none_or(|(_, best_score)| score > best_score) {
+            best = Some((game_move, score));
+        }
+    }
+    best
+}
+
+/// Scores a position for `player`, who picks the best move on their turns while every other player picks the worst one for them.
+fn paranoid(
+    state: &GameState,
+    player: GamePiece,
+    depth: u8,
+    ply: Evaluation,
+    mut alpha: Evaluation,
+    mut beta: Evaluation,
+) -> Evaluation {
+    // Wins and losses further away score closer to even, so the quickest win and the slowest loss are preferred
+    if state.is_eliminated(player) {
+        return WORST_EVAL + ply;
+    }
+    match state.check_endgame() {
+        EndgameType::Win(winner) if winner == player => return BEST_EVAL - ply,
+        EndgameType::Win(_) => return WORST_EVAL + ply,
+        EndgameType::Full | EndgameType::Repetition => return 0,
+        EndgameType::None if depth == 0 => return evaluate(state, player),
+        EndgameType::None => (),
+    }
+
+    let maximising = *state.get_next_player() == player;
+    let mut value = match maximising {
+        true => WORST_EVAL,
+        false => BEST_EVAL,
+    };
+    for game_move in ordered_moves(state) {
+        let score = paranoid(
+            &play(state, game_move),
+            player,
+            depth - 1,
+            ply + 1,
+            alpha,
+            beta,
+        );
+        if maximising {
+            value = value.max(score);
+            alpha = alpha.max(value);
+        } else {
+            value = value.min(score);
+            beta = beta.min(value);
+        }
+        if alpha >= beta {
+            break;
+        }
+    }
+    value
+}
+
+/// Scores a position for `player` by the lines each player could still complete, like the two-player window evaluator.
+///
+/// A window holding only the player's pieces counts for them, and one holding only a single opponent's pieces counts against them, more so the fuller it is.
+/// Under elimination, completing a line knocks a player out, so the scores are turned around.
+fn evaluate(state: &GameState, player: GamePiece) -> Evaluation {
+    let board = state.get_board();
+    let score: Evaluation = board
+        .windows()
+        .map(|window| {
+            let (mut owner, mut count) = (None, 0);
+            for (row, col) in window {
+                match (board.get_piece(row, col), owner) {
+                    (None, _) => (),
+                    (Some(piece), None) => (owner, count) = (Some(piece), 1),
+                    (Some(piece), Some(owner)) if piece == owner => count += 1,
+                    // Windows shared by more than one player can't be completed by anyone
+                    (Some(_), Some(_)) => return 0,
+                }
+            }
+            match owner {
+                Some(owner) if owner == player => count * count,
+                Some(owner) if !state.is_eliminated(owner) => -(count * count),
+                _ => 0,
+            }
+        })
+        .sum();
+    match state.get_free_for_all() {
+        FreeForAll::FirstToConnect => score,
+        FreeForAll::Elimination => -score,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::board::GameBoard;
+
+    fn play_moves(free_for_all: FreeForAll, moves: &[usize]) -> GameState {
+        let mut state =
+            GameState::with_board(GameBoard::with_size(6, 7, 4).unwrap(), GamePiece::Red)
+                .with_players(3, free_for_all);
+        for col in moves {
+            state.play_move(*col).unwrap();
+        }
+        state
+    }
+
+    #[test]
+    fn completes_and_blocks_lines() {
+        // Red can complete a line in column 1
+        let state = play_moves(FreeForAll::FirstToConnect, &[1, 2, 3, 1, 2, 3, 1, 2, 3]);
+        let (best_move, score) = search_depth(&state, 2).unwrap();
+        assert_eq!(Move::Drop(1), best_move);
+        assert_eq!(BEST_EVAL - 1, score);
+
+        // Yellow must stop Red from completing that line, as Green will not
+        let state = play_moves(FreeForAll::FirstToConnect, &[1, 2, 3, 1, 2, 3, 1]);
+        assert_eq!(Move::Drop(1), search_depth(&state, 3).unwrap().0);
+    }
+
+    #[test]
+    fn avoids_elimination() {
+        // Completing the line in column 1 would knock Red out
+        let state = play_moves(FreeForAll::Elimination, &[1, 2, 3, 1, 2, 3, 1, 2, 3]);
+        assert_ne!(Move::Drop(1), search_depth(&state, 2).unwrap().0);
+    }
+}
@@ -0,0 +1,524 @@
+use crate::game::{
+    board::{GameBoard, MAX_COLS, MAX_ROWS, MIN_PLAYERS},
+    state::{EndgameType, GameState},
+    transposition::TranspositionTable,
+    Variant,
+};
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// One bit per space, column by column from the bottom, with a spare bit above each column.
+///
+/// The spare bits are never set, so lines that would wrap from one column into the next are always broken.
+type Bitboard = u128;
+
+/// Numbers of entries the transposition table can have, from smallest to largest.
+///
+/// Each is the largest prime below a power of two, so that keys spread evenly.
+const TABLE_SIZES: [usize; 4] = [4_093, 65_521, 1_048_573, 4_194_301];
+/// Longest line that fits on any board, and so the longest win length.
+const MAX_LINE: usize = if MAX_ROWS > MAX_COLS {
+    MAX_ROWS
+} else {
+    MAX_COLS
+};
+
+/// Occurs when a position cannot be solved.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SolveError {
+    /// The game is already over, so there are no moves to choose between.
+    GameOver,
+    /// The column, numbered from 1, is full or not on the board.
+    InvalidMove(usize),
+    /// The game is played by rules other than the classic ones, which the solver does not know.
+    UnsupportedVariant(Variant),
+    /// The board has blocked spaces, which the solver does not know about.
+    BlockedSpaces,
+    /// The game has more than two players, so it can't be scored as a win for one side or the other.
+    TooManyPlayers,
+}
+
+impl Display for SolveError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::GameOver => write!(f, "The game is already over"),
+            Self::InvalidMove(col) => write!(f, "Column {col} cannot be played"),
+            Self::UnsupportedVariant(variant) => {
+                write!(f, "Only classic games can be solved, not {variant}")
+            }
+            Self::BlockedSpaces => write!(f, "Boards with blocked spaces cannot be solved"),
+            Self::TooManyPlayers => write!(f, "Only games between two players can be solved"),
+        }
+    }
+}
+
+/// A position as seen by the player to move.
+#[derive(Clone, Copy)]
+struct Position {
+    /// Spaces holding the pieces of the player to move.
+    current: Bitboard,
+    /// Every occupied space.
+    mask: Bitboard,
+    moves: usize,
+}
+
+/// Finds the exact result of positions with perfect play from both sides.
+///
+/// Scores are from the point of view of the player to move: positive if they can force a win, negative if their opponent can, and zero for a draw.
+/// The further a score is from zero, the sooner the game is won; a score of `n` means the winner still has `n - 1` pieces left to play when they win.
+pub struct Solver {
+    rows: usize,
+    cols: usize,
+    win_length: usize,
+    /// The bottom space of every column.
+    bottom: Bitboard,
+    /// Every playable space.
+    board: Bitboard,
+    /// Columns to try first, from the middle outwards, as wins through the middle are more likely.
+    order: Vec<usize>,
+    /// Upper bounds of scores already found, offset to be positive.
+    table: TranspositionTable<i8>,
+}
+
+impl Solver {
+    /// Creates a solver for boards with the same size and win length as the given one.
+    pub fn new(board: &GameBoard) -> Self {
+        let (rows, cols) = (board.rows(), board.cols());
+        let height = rows + 1;
+        let bottom = (0..cols).fold(0, |b, col| b | 1 << (col * height));
+        let column = (1 << rows) - 1;
+        let mut order = (0..cols).collect::<Vec<_>>();
+        order.sort_by_key(|col| (2 * col).abs_diff(cols - 1));
+        Self {
+            rows,
+            cols,
+            win_length: board.win_length(),
+            bottom,
+            board: bottom * column,
+            order,
+            table: TranspositionTable::new(table_size(rows * cols)),
+        }
+    }
+
+    /// Checks whether the solver can be used for positions on the given board.
+    pub fn fits(&self, board: &GameBoard) -> bool {
+        (self.rows, self.cols, self.win_length) == (board.rows(), board.cols(), board.win_length())
+    }
+
+    /// Scores the position for the player to move.
+    pub fn solve(&mut self, state: &GameState) -> Result<i32, SolveError> {
+        let position = self.position(state)?;
+        Ok(self.solve_position(&position))
+    }
+
+    /// Scores the position for the player to move, and finds the column, numbered from 1, of a move that achieves that score.
+    ///
+    /// When several moves are equally good, the one nearest the middle of the board is chosen.
+    /// The canonical form of the position is solved, so a position and its reflection are given reflected moves.
+    pub fn best_move(&mut self, state: &GameState) -> Result<(usize, i32), SolveError> {
+        let (canonical, mirrored) = state.canonical();
+        let position = self.position(&canonical)?;
+        let score = self.solve_position(&position);
+        let wins = self.winning_spaces(position.current, position.mask);
+        for col in self.order.clone() {
+            let space = self.possible(&position) & self.column_mask(col);
+            if space == 0 {
+                continue;
+            }
+            let achieves = if wins & space != 0 {
+                self.win_score(position.moves) >= score
+            } else {
+                // Only need to know whether the opponent's score is at most the negated score, not what it is
+                let next = self.play(&position, space);
+                -self.solve_within(&next, -score, -score + 1) >= score
+            };
+            if achieves {
+                let col = match mirrored {
+                    true => state.get_board().mirror_column(col + 1),
+                    false => col + 1,
+                };
+                return Ok((col, score));
+            }
+        }
+        unreachable!("The score of a position is achieved by one of its moves")
+    }
+
+    /// Scores the move in the given column, numbered from 1, for the player to move.
+    pub fn score_move(&mut self, state: &GameState, column: usize) -> Result<i32, SolveError> {
+        let position = self.position(state)?;
+        let space = match column {
+            1.. if column <= self.cols => self.possible(&position) & self.column_mask(column - 1),
+            _ => 0,
+        };
+        if space == 0 {
+            return Err(SolveError::InvalidMove(column));
+        }
+        if self.winning_spaces(position.current, position.mask) & space != 0 {
+            return Ok(self.win_score(position.moves));
+        }
+        Ok(-self.solve_position(&self.play(&position, space)))
+    }
+
+    fn position(&self, state: &GameState) -> Result<Position, SolveError> {
+        if state.get_variant() != Variant::Classic {
+            return Err(SolveError::UnsupportedVariant(state.get_variant()));
+        }
+        if state.player_count() > MIN_PLAYERS {
+            return Err(SolveError::TooManyPlayers);
+        }
+        if state.check_endgame() != EndgameType::None {
+            return Err(SolveError::GameOver);
+        }
+        let board = state.get_board();
+        if board.has_blocked_spaces() {
+            return Err(SolveError::BlockedSpaces);
+        }
+        let mut position = Position {
+            current: 0,
+            mask: 0,
+            moves: 0,
+        };
+        for col in 0..self.cols {
+            for row in 0..self.rows {
+                if let Some(piece) = board.get_piece(row, col) {
+                    let space = 1 << (col * (self.rows + 1) + self.rows - 1 - row);
+                    position.mask |= space;
+                    position.moves += 1;
+                    if piece == *state.get_next_player() {
+                        position.current |= space;
+                    }
+                }
+            }
+        }
+        Ok(position)
+    }
+
+    /// Finds the exact score by repeatedly asking whether it is above or below a guess, narrowing the range each time.
+    fn solve_position(&mut self, position: &Position) -> i32 {
+        if self.winning_spaces(position.current, position.mask) & self.possible(position) != 0 {
+            return self.win_score(position.moves);
+        }
+        let spaces = (self.rows * self.cols) as i32;
+        let moves = position.moves as i32;
+        let mut min = -(spaces - moves) / 2;
+        let mut max = (spaces + 1 - moves) / 2;
+        while min < max {
+            // Guesses near zero are quicker to check, so try those first
+            let mut guess = min + (max - min) / 2;
+            if guess <= 0 && min / 2 < guess {
+                guess = min / 2;
+            } else if guess >= 0 && max / 2 > guess {
+                guess = max / 2;
+            }
+            let score = self.negamax(position, guess, guess + 1);
+            if score <= guess {
+                max = score;
+            } else {
+                min = score;
+            }
+        }
+        min
+    }
+
+    /// Scores a position within the window `alpha` to `beta`, allowing for the player to move winning straight away.
+    fn solve_within(&mut self, position: &Position, alpha: i32, beta: i32) -> i32 {
+        if self.winning_spaces(position.current, position.mask) & self.possible(position) != 0 {
+            return self.win_score(position.moves);
+        }
+        self.negamax(position, alpha, beta)
+    }
+
+    /// Scores a position in which the player to move cannot win straight away, within the window `alpha` to `beta`.
+    fn negamax(&mut self, position: &Position, mut alpha: i32, mut beta: i32) -> i32 {
+        let spaces = (self.rows * self.cols) as i32;
+        let moves = position.moves as i32;
+        let possible = self.non_losing_moves(position);
+        if possible == 0 {
+            return -(spaces - moves) / 2;
+        }
+        if moves >= spaces - 2 {
+            return 0;
+        }
+
+        // The opponent cannot win on their next move, so the earliest they can win is the move after
+        let min = -(spaces - 2 - moves) / 2;
+        if alpha < min {
+            alpha = min;
+            if alpha >= beta {
+                return alpha;
+            }
+        }
+        let key = table_key(position);
+        let max = match self.table.get_key(key) {
+            Some((bound, _)) => bound as i32 + self.min_score() - 1,
+            None => (spaces - 1 - moves) / 2,
+        };
+        if beta > max {
+            beta = max;
+            if alpha >= beta {
+                return beta;
+            }
+        }
+
+        // Try the moves that set up the most threats first, keeping the middle first among equals
+        let mut candidates = [(0, 0); MAX_COLS];
+        let mut count = 0;
+        for col in &self.order {
+            let space = possible & self.column_mask(*col);
+            if space == 0 {
+                continue;
+            }
+            let threats = self
+                .winning_spaces(position.current | space, position.mask)
+                .count_ones();
+            let mut i = count;
+            while i > 0 && candidates[i - 1].1 < threats {
+                candidates[i] = candidates[i - 1];
+                i -= 1;
+            }
+            candidates[i] = (space, threats);
+            count += 1;
+        }
+
+        for (space, _) in candidates.into_iter().take(count) {
+            let score = -self.negamax(&self.play(position, space), -beta, -alpha);
+            if score >= beta {
+                return score;
+            }
+            alpha = alpha.max(score);
+        }
+        // Every bound is as useful as any other, so the newest always takes the entry
+        let bound = (alpha - self.min_score() + 1) as i8;
+        self.table.insert_key(key, 0, bound);
+        alpha
+    }
+
+    /// The position after the player to move drops a piece into the given space, seen by their opponent.
+    fn play(&self, position: &Position, space: Bitboard) -> Position {
+        Position {
+            current: position.current ^ position.mask,
+            mask: position.mask | space,
+            moves: position.moves + 1,
+        }
+    }
+
+    /// The space that a piece dropped into each column would land in.
+    fn possible(&self, position: &Position) -> Bitboard {
+        (position.mask + self.bottom) & self.board
+    }
+
+    /// Moves that do not let the opponent win straight away, or no moves if every move loses.
+    fn non_losing_moves(&self, position: &Position) -> Bitboard {
+        let mut possible = self.possible(position);
+        let opponent = position.current ^ position.mask;
+        let opponent_wins = self.winning_spaces(opponent, position.mask);
+        let forced = possible & opponent_wins;
+        if forced != 0 {
+            // Two threats can't both be blocked
+            if forced & (forced - 1) != 0 {
+                return 0;
+            }
+            possible = forced;
+        }
+        // Don't play underneath a space where the opponent would win
+        possible & !(opponent_wins >> 1)
+    }
+
+    /// Empty spaces that would complete a line for the player with the given pieces.
+    fn winning_spaces(&self, pieces: Bitboard, mask: Bitboard) -> Bitboard {
+        let height = self.rows as isize + 1;
+        let span = self.win_length - 1;
+        let mut spaces = 0;
+        for step in [1, height, height - 1, height + 1] {
+            // Spaces with pieces in each of the `n` spaces before or after them along the line
+            let mut before = [Bitboard::MAX; MAX_LINE];
+            let mut after = [Bitboard::MAX; MAX_LINE];
+            for n in 1..=span {
+                let distance = n as isize * step;
+                before[n] = before[n - 1] & shift(pieces, distance);
+                after[n] = after[n - 1] & shift(pieces, -distance);
+            }
+            // The space completes a line if the pieces either side of it add up to the rest of the line
+            for gap in 0..=span {
+                spaces |= before[gap] & after[span - gap];
+            }
+        }
+        spaces & self.board & !mask
+    }
+
+    fn column_mask(&self, col: usize) -> Bitboard {
+        ((1 << self.rows) - 1) << (col * (self.rows + 1))
+    }
+
+    /// Score for the player to move when they can win with this move.
+    fn win_score(&self, moves: usize) -> i32 {
+        ((self.rows * self.cols + 1 - moves) / 2) as i32
+    }
+
+    /// Lowest possible score, as the loser must have played at least enough pieces for the winner to make a line.
+    fn min_score(&self) -> i32 {
+        -((self.rows * self.cols) as i32) / 2 + self.win_length as i32 - 1
+    }
+}
+
+/// Hashes a position for the transposition table.
+fn table_key(position: &Position) -> u64 {
+    // Every position has a different sum, as adding the mask carries each column's pieces up by one space
+    let key = position.current + position.mask;
+    (key as u64) ^ ((key >> 64) as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+}
+
+/// Picks a table size for boards with the given number of spaces: the smallest with about two entries to the power of the number of spaces, or the largest.
+///
+/// Small boards have few positions, so they don't need the memory a standard board does.
+fn table_size(spaces: usize) -> usize {
+    TABLE_SIZES
+        .into_iter()
+        .find(|size| size.next_power_of_two().trailing_zeros() as usize >= spaces)
+        .unwrap_or(TABLE_SIZES[TABLE_SIZES.len() - 1])
+}
+
+/// Shifts towards higher bits for positive distances, or lower bits for negative ones.
+fn shift(bits: Bitboard, distance: isize) -> Bitboard {
+    match distance >= 0 {
+        true => bits << distance,
+        false => bits >> -distance,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{board::GamePiece, FreeForAll};
+
+    fn play(board: GameBoard, moves: &str) -> GameState {
+        let mut state = GameState::with_board(board, GamePiece::Red);
+        for col in moves.chars().map(|c| c.to_digit(10).unwrap() as usize) {
+            let player = *state.get_next_player();
+            state.get_board_mut().insert_piece(player, col).unwrap();
+            state.toggle_player();
+        }
+        state
+    }
+
+    #[test]
+    fn immediate_win() {
+        let state = play(GameBoard::default(), "4455667");
+        let mut solver = Solver::new(state.get_board());
+        assert_eq!(Err(SolveError::GameOver), solver.solve(&state));
+
+        let state = play(GameBoard::default(), "445566");
+        // Red wins with their fourth piece, with 17 of 21 left over
+        assert_eq!(Ok(18), solver.solve(&state));
+        assert_eq!(Ok(18), solver.score_move(&state, 3));
+        assert_eq!(Ok(18), solver.score_move(&state, 7));
+        assert_eq!(Ok((3, 18)), solver.best_move(&state));
+    }
+
+    #[test]
+    fn forced_loss() {
+        // Yellow cannot block both ends of Red's three in a row
+        let state = play(GameBoard::default(), "44556");
+        let mut solver = Solver::new(state.get_board());
+        assert_eq!(Ok(-18), solver.solve(&state));
+        for col in 1..=7 {
+            assert_eq!(Ok(-18), solver.score_move(&state, col));
+        }
+        assert_eq!(Ok((4, -18)), solver.best_move(&state));
+        assert_eq!(
+            Err(SolveError::InvalidMove(8)),
+            solver.score_move(&state, 8)
+        );
+    }
+
+    #[test]
+    fn known_positions() {
+        let mut solver = Solver::new(&GameBoard::default());
+        for (moves, score) in [
+            ("2252576253462244111563365343671351441", -1),
+            ("7422341735647741166133573473242566", 1),
+            ("23163416124767223154467471272416755633", 0),
+            ("65214673556155731566316327373221417", -1),
+        ] {
+            let state = play(GameBoard::default(), moves);
+            assert_eq!(Ok(score), solver.solve(&state), "{moves}");
+        }
+    }
+
+    /// Scores a position by trying every possible continuation.
+    fn exhaustive_score(state: &GameState) -> i32 {
+        let board = state.get_board();
+        let spaces = board.rows() * board.cols();
+        let moves = (0..board.rows())
+            .flat_map(|row| (0..board.cols()).map(move |col| (row, col)))
+            .filter(|(row, col)| board.get_piece(*row, *col).is_some())
+            .count();
+        (1..=board.cols())
+            .filter_map(|col| {
+                let mut next = *state;
+                let player = *next.get_next_player();
+                next.get_board_mut().insert_piece(player, col).ok()?;
+                next.toggle_player();
+                Some(match next.check_endgame() {
+                    EndgameType::Win(_) => ((spaces + 1 - moves) / 2) as i32,
+                    EndgameType::Full | EndgameType::Repetition => 0,
+                    EndgameType::None => -exhaustive_score(&next),
+                })
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn matches_exhaustive_search() {
+        for win_length in [3, 4] {
+            let board = GameBoard::with_size(4, 4, win_length).unwrap();
+            let mut solver = Solver::new(&board);
+            for moves in [
+                "2233141", "1234213", "21342", "3222141", "4411233", "1232441", "11113",
+            ] {
+                let state = play(board, moves);
+                if state.check_endgame() != EndgameType::None {
+                    continue;
+                }
+                let expected = exhaustive_score(&state);
+                assert_eq!(Ok(expected), solver.solve(&state), "{moves} ({win_length})");
+                let (best, score) = solver.best_move(&state).unwrap();
+                assert_eq!(expected, score, "{moves} ({win_length})");
+                assert_eq!(Ok(expected), solver.score_move(&state, best));
+            }
+        }
+    }
+
+    #[test]
+    fn small_boards() {
+        // Three in a row on an empty 4x4 board is a win for the first player
+        let board = GameBoard::with_size(4, 4, 3).unwrap();
+        let mut solver = Solver::new(&board);
+        assert!(solver.solve(&play(board, "")).unwrap() > 0);
+
+        // Four in a row on an empty 4x4 board is a draw
+        let board = GameBoard::with_size(4, 4, 4).unwrap();
+        let mut solver = Solver::new(&board);
+        assert!(!solver.fits(&GameBoard::default()));
+        assert_eq!(Ok(0), solver.solve(&play(board, "")));
+
+        // Small boards only need small tables
+        assert_eq!(65_521, solver.table.capacity());
+        assert_eq!(4_093, table_size(12));
+        assert_eq!(4_194_301, table_size(42));
+
+        let mut blocked = board;
+        blocked.block_space(3, 0);
+        assert_eq!(
+            Err(SolveError::BlockedSpaces),
+            solver.solve(&play(blocked, ""))
+        );
+
+        let three_players = play(board, "").with_players(3, FreeForAll::FirstToConnect);
+        assert_eq!(
+            Err(SolveError::TooManyPlayers),
+            solver.solve(&three_players)
+        );
+    }
+}
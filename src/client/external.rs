@@ -0,0 +1,257 @@
+use super::{ClientError, GameClient};
+use crate::game::{
+    board::{GameBoard, GamePiece, MIN_PLAYERS},
+    record::{player_header, GameRecord, Repetitions, HEADER_TERMINATION},
+    state::{EndgameType, GameState, Move},
+    GameMode, Variant,
+};
+use crate::settings::Settings;
+
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Time an engine has to start and identify itself.
+const STARTUP_TIME: Duration = Duration::from_secs(5);
+/// Extra time an engine has to reply to `go`, beyond the time it is asked to think for, before it forfeits.
+const REPLY_GRACE: Duration = Duration::from_secs(2);
+/// Name of the engine option that sets how strongly it plays, as used by `fourstack engine`.
+const LEVEL_OPTION: &str = "Level";
+
+/// Plays against another program that speaks the same text protocol as `fourstack engine`.
+///
+/// The engine is sent the moves played so far before each of its turns, and its `bestmove` reply is played for it.
+/// If it stops running, takes too long to reply or plays an illegal move, it forfeits the game.
+#[derive(Debug)]
+pub struct ExternalEngineClient {
+    game_state: GameState,
+    record: GameRecord,
+    /// How often each position has been reached, to find draws by repetition.
+    repetitions: Repetitions,
+    engine_piece: GamePiece,
+    engine: Child,
+    input: ChildStdin,
+    /// Lines printed by the engine, read on another thread so that waiting for them can time out.
+    output: Receiver<String>,
+    think_time: Duration,
+    forfeited: bool,
+}
+
+impl ExternalEngineClient {
+    /// Starts the engine command given in the settings and waits for it to identify itself.
+    ///
+    /// If the engine moves first, it is asked for its move on the first turn, by [`GameClient::play_opponent_move`].
+    pub fn new(settings: &Settings) -> Result<Self, ClientError> {
+        if settings.game.players > MIN_PLAYERS {
+            return Err(ClientError::TooManyPlayers);
+        }
+        let board = settings.new_board().map_err(ClientError::Settings)?;
+        // Positions are sent as moves from the standard starting position
+        if board != GameBoard::default() || settings.game.variant != Variant::Classic {
+            return Err(ClientError::Engine(
+                "External engines can only play classic games on the standard 7x6 board"
+                    .to_string(),
+            ));
+        }
+        let path = &settings.ai.engine;
+        let mut words = path.split_whitespace();
+        let mut engine = Command::new(words.next().unwrap_or_default())
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| ClientError::Engine(format!("Could not start {path}: {e}")))?;
+        let input = engine.stdin.take().expect("Engine input is piped");
+        let reader = BufReader::new(engine.stdout.take().expect("Engine output is piped"));
+        let (sender, output) = mpsc::channel();
+        thread::spawn(move || {
+            for line in reader.lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let first_player = settings.game.first_player.pick(settings.game.players);
+        let engine_piece = match settings.ai.human_first {
+            true => first_player.opponent(),
+            false => first_player,
+        };
+        let (game_state, record) = super::new_game_on(board, settings, first_player, GameMode::Ai);
+        let repetitions = Repetitions::new(&game_state);
+        let mut client = Self {
+            game_state,
+            record,
+            repetitions,
+            engine_piece,
+            engine,
+            input,
+            output,
+            think_time: settings.ai.think_time(),
+            forfeited: false,
+        };
+
+        let (name, has_level) = client
+            .identify()
+            .map_err(|e| ClientError::Engine(format!("{path} did not start: {e}")))?;
+        let engine_header = player_header(engine_piece);
+        client
+            .record
+            .set_header(engine_header, name.unwrap_or_else(|| path.clone()));
+        if has_level {
+            let level = settings.ai.level;
+            client
+                .send(&format!("setoption name {LEVEL_OPTION} value {level}"))
+                .map_err(ClientError::Engine)?;
+        }
+        client.send("ucinewgame").map_err(ClientError::Engine)?;
+        Ok(client)
+    }
+
+    /// Asks the engine to identify itself, returning its name, if it gave one, and whether it has a level option.
+    fn identify(&mut self) -> Result<(Option<String>, bool), String> {
+        self.send("uci")?;
+        let deadline = Instant::now() + STARTUP_TIME;
+        let (mut name, mut has_level) = (None, false);
+        loop {
+            let line = self.receive(deadline)?;
+            if line == "uciok" {
+                return Ok((name, has_level));
+            }
+            if let Some(id) = line.strip_prefix("id name ") {
+                name = Some(id.trim().to_string());
+            }
+            has_level |= line.starts_with(&format!("option name {LEVEL_OPTION} "));
+        }
+    }
+
+    /// Makes the engine's move, if it is the engine's turn and the game is not over.
+    ///
+    /// If the engine fails to move, it forfeits and the reason is returned as an error.
+    fn play_engine_move(&mut self) -> Result<(), ClientError> {
+        if *self.game_state.get_next_player() != self.engine_piece
+            || self.get_endgame() != EndgameType::None
+        {
+            return Ok(());
+        }
+        let played = self.request_move().and_then(|column| {
+            super::process_move(
+                &mut self.game_state,
+                &mut self.record,
+                &mut self.repetitions,
+                Move::Drop(column),
+            )
+            .map_err(|_| format!("The engine played an illegal move in column {column}"))
+        });
+        match played {
+            Ok(_) => Ok(()),
+            Err(reason) => {
+                self.forfeited = true;
+                let _ = self.engine.kill();
+                Err(ClientError::Engine(format!(
+                    "{reason}, so it forfeits the game"
+                )))
+            }
+        }
+    }
+
+    /// Sends the position to the engine and waits for its best move.
+    fn request_move(&mut self) -> Result<usize, String> {
+        let moves = self
+            .record
+            .get_moves()
+            .iter()
+            .map(|m| m.game_move.to_string())
+            .collect::<Vec<_>>();
+        match moves.is_empty() {
+            true => self.send("position startpos")?,
+            false => self.send(&format!("position startpos moves {}", moves.join(" ")))?,
+        }
+        self.send(&format!("go movetime {}", self.think_time.as_millis()))?;
+
+        let deadline = Instant::now() + self.think_time + REPLY_GRACE;
+        loop {
+            // Anything else, such as `info` lines, is only for show
+            if let Some(best) = self.receive(deadline)?.strip_prefix("bestmove ") {
+                let best = best.split_whitespace().next().unwrap_or_default();
+                return best
+                    .parse()
+                    .map_err(|_| format!("The engine sent an invalid move: {best}"));
+            }
+        }
+    }
+
+    fn send(&mut self, command: &str) -> Result<(), String> {
+        writeln!(self.input, "{command}")
+            .and_then(|_| self.input.flush())
+            .map_err(|_| "The engine stopped running".to_string())
+    }
+
+    /// Waits for the next line from the engine until the deadline.
+    fn receive(&mut self, deadline: Instant) -> Result<String, String> {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        match self.output.recv_timeout(timeout) {
+            Ok(line) => Ok(line.trim().to_string()),
+            Err(RecvTimeoutError::Timeout) => Err("The engine took too long to reply".to_string()),
+            Err(RecvTimeoutError::Disconnected) => Err("The engine stopped running".to_string()),
+        }
+    }
+}
+
+impl GameClient for ExternalEngineClient {
+    fn get_current_state(&self) -> GameState {
+        self.game_state
+    }
+
+    fn play_opponent_move(&mut self) -> Result<(), ClientError> {
+        self.play_engine_move()
+    }
+
+    fn handle_input_move(&mut self, game_move: Move) -> Result<GameState, ClientError> {
+        super::process_move(
+            &mut self.game_state,
+            &mut self.record,
+            &mut self.repetitions,
+            game_move,
+        )?;
+        self.play_engine_move()?;
+        Ok(self.game_state)
+    }
+
+    fn undo_move(&mut self) -> Result<GameState, ClientError> {
+        let engine_piece = self.engine_piece;
+        super::undo_human_move(
+            &mut self.game_state,
+            &mut self.record,
+            &mut self.repetitions,
+            |piece| piece != engine_piece,
+        )
+    }
+
+    fn get_record(&self) -> GameRecord {
+        let mut record = self.record.clone();
+        record.set_result(&self.get_endgame());
+        if self.forfeited {
+            record.set_header(HEADER_TERMINATION, "Forfeit");
+        }
+        record
+    }
+
+    fn get_endgame(&self) -> EndgameType {
+        match self.forfeited {
+            true => EndgameType::Win(self.engine_piece.opponent()),
+            false => super::endgame(&self.game_state, &self.repetitions),
+        }
+    }
+}
+
+impl Drop for ExternalEngineClient {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        let _ = self.engine.kill();
+        let _ = self.engine.wait();
+    }
+}
@@ -1,17 +1,60 @@
 use super::{ClientError, GameClient};
-use crate::game::state::GameState;
+use crate::game::{
+    record::{GameRecord, Repetitions},
+    state::{EndgameType, GameState, Move},
+    GameMode,
+};
+use crate::settings::Settings;
 
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Debug)]
 pub struct LocalGameClient {
     game_state: GameState,
+    record: GameRecord,
+    /// How often each position has been reached, to find draws by repetition.
+    repetitions: Repetitions,
+}
+
+impl LocalGameClient {
+    pub fn new(settings: &Settings) -> Result<Self, ClientError> {
+        let first_player = settings.game.first_player.pick(settings.game.players);
+        let (game_state, record) = super::new_game(settings, first_player, GameMode::Local)?;
+        let repetitions = Repetitions::new(&game_state);
+        Ok(Self {
+            game_state,
+            record,
+            repetitions,
+        })
+    }
 }
 
 impl GameClient for LocalGameClient {
-    fn handle_input_move(&mut self, column_num: usize) -> Result<GameState, ClientError> {
-        super::process_move(&mut self.game_state, column_num)
+    fn handle_input_move(&mut self, game_move: Move) -> Result<GameState, ClientError> {
+        super::process_move(
+            &mut self.game_state,
+            &mut self.record,
+            &mut self.repetitions,
+            game_move,
+        )
     }
 
     fn get_current_state(&self) -> GameState {
         self.game_state
     }
+
+    fn undo_move(&mut self) -> Result<GameState, ClientError> {
+        super::undo_moves(
+            &mut self.game_state,
+            &mut self.record,
+            &mut self.repetitions,
+            1,
+        )
+    }
+
+    fn get_record(&self) -> GameRecord {
+        super::finish_record(&self.game_state, &self.record, &self.repetitions)
+    }
+
+    fn get_endgame(&self) -> EndgameType {
+        super::endgame(&self.game_state, &self.repetitions)
+    }
 }
@@ -1,20 +1,48 @@
+#[cfg(feature = "ai")]
 pub mod ai;
+pub mod external;
 pub mod local;
+#[cfg(feature = "online")]
 pub mod online;
 
-use crate::game::state::GameState;
+use crate::game::{
+    board::{GameBoard, GamePiece},
+    record::{player_header, GameRecord, Repetitions, HEADER_MODE},
+    state::{EndgameType, GameState, Move},
+    GameMode,
+};
+use crate::settings::{Settings, SettingsError};
 
 use std::fmt::{Display, Formatter, Result as FmtResult};
 
 #[derive(Clone, Debug)]
 pub enum ClientError {
     InvalidMove(String),
+    NothingToUndo,
+    /// Moves cannot be taken back in this mode.
+    UndoUnavailable,
+    /// The connection to an online opponent failed.
+    Connection(String),
+    /// An external engine could not be started, or failed while playing.
+    Engine(String),
+    /// This mode can't be played by more than two players.
+    TooManyPlayers,
+    /// A board could not be made from the settings, such as when the layout file can no longer be read.
+    Settings(SettingsError),
 }
 
 impl Display for ClientError {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         match self {
             ClientError::InvalidMove(m) => write!(f, "Invalid move: {}", m),
+            ClientError::NothingToUndo => write!(f, "There are no moves to undo"),
+            ClientError::UndoUnavailable => write!(f, "Moves cannot be undone in online games"),
+            ClientError::Connection(m) => write!(f, "Connection error: {}", m),
+            ClientError::Engine(m) => write!(f, "Engine error: {}", m),
+            ClientError::TooManyPlayers => {
+                write!(f, "Only two players can take part in this mode")
+            }
+            ClientError::Settings(e) => write!(f, "{}", e),
         }
     }
 }
@@ -30,23 +58,141 @@ pub trait GameClient {
     /// Yields the current [`GameState`] from the server.
     fn get_current_state(&self) -> GameState;
 
-    /// Handler for user-supplied input move as a column number.
+    /// Lets an opponent whose turn it is move before the user is asked for theirs, such as an engine that moves first.
+    ///
+    /// Does nothing for clients whose opponents always reply within [`Self::handle_input_move`].
+    fn play_opponent_move(&mut self) -> Result<(), ClientError> {
+        Ok(())
+    }
+
+    /// Handler for a user-supplied input move.
     ///
     /// Returns the updated [`GameState`] upon success, or the corresponding [`ClientError`].
-    fn handle_input_move(&mut self, column_num: usize) -> Result<GameState, ClientError>;
+    fn handle_input_move(&mut self, game_move: Move) -> Result<GameState, ClientError>;
+
+    /// Takes back the user's most recent move, along with any replies to it.
+    ///
+    /// Returns the restored [`GameState`] upon success, or the corresponding [`ClientError`].
+    fn undo_move(&mut self) -> Result<GameState, ClientError>;
+
+    /// Yields a [`GameRecord`] of every move played so far, with headers describing the game.
+    fn get_record(&self) -> GameRecord;
+
+    /// Yields how the game has ended, if it has.
+    ///
+    /// This is decided by the board and the positions reached so far, unless a player has forfeited.
+    fn get_endgame(&self) -> EndgameType;
+}
+
+/// Sets up the starting position and record for a new game with the given settings.
+///
+/// The board is made afresh for every game, so this fails if its layout file has been changed or removed since the settings were checked.
+fn new_game(
+    settings: &Settings,
+    first_player: GamePiece,
+    mode: GameMode,
+) -> Result<(GameState, GameRecord), ClientError> {
+    let board = settings.new_board().map_err(ClientError::Settings)?;
+    Ok(new_game_on(board, settings, first_player, mode))
 }
 
-fn process_move(game_state: &mut GameState, column_num: usize) -> Result<GameState, ClientError> {
-    let next_player = *game_state.get_next_player();
+/// Sets up the starting position and record for a new game on the given empty board, rather than one made from the settings.
+fn new_game_on(
+    board: GameBoard,
+    settings: &Settings,
+    first_player: GamePiece,
+    mode: GameMode,
+) -> (GameState, GameRecord) {
+    let game = &settings.game;
+    let mut record = GameRecord::new(first_player);
+    record.set_players(game.players, game.free_for_all);
+    for piece in &GamePiece::ALL[..game.players] {
+        record.set_header(player_header(*piece), settings.player_name(*piece));
+    }
+    record.set_header(HEADER_MODE, mode.to_string());
+    record.set_board(&board);
+    record.set_variant(game.variant);
+    let state = GameState::with_board(board, first_player)
+        .with_variant(game.variant)
+        .with_players(game.players, game.free_for_all);
+    (state, record)
+}
 
-    match game_state
-        .get_board_mut()
-        .insert_piece(next_player, column_num)
-    {
+/// Applies a move for the next player, adding it to the game's record and counting the position it reaches.
+pub fn process_move(
+    game_state: &mut GameState,
+    record: &mut GameRecord,
+    repetitions: &mut Repetitions,
+    game_move: Move,
+) -> Result<GameState, ClientError> {
+    match game_state.play(game_move) {
         Ok(_) => {
-            game_state.toggle_player();
+            record.push_move(game_move);
+            repetitions.push(game_state);
             Ok(*game_state)
         }
         Err(insert_error) => Err(ClientError::InvalidMove(insert_error.to_string())),
     }
 }
+
+/// Decides how a game has ended from its board, or from the positions reached if one has been repeated too often.
+fn endgame(game_state: &GameState, repetitions: &Repetitions) -> EndgameType {
+    match game_state.check_endgame() {
+        EndgameType::None if repetitions.is_drawn() => EndgameType::Repetition,
+        endgame => endgame,
+    }
+}
+
+/// Removes the last `count` moves from a game's record and rebuilds the game state from what remains.
+fn undo_moves(
+    game_state: &mut GameState,
+    record: &mut GameRecord,
+    repetitions: &mut Repetitions,
+    count: usize,
+) -> Result<GameState, ClientError> {
+    if record.get_moves().len() < count {
+        return Err(ClientError::NothingToUndo);
+    }
+    for _ in 0..count {
+        record.pop_move();
+        repetitions.pop();
+    }
+    *game_state = record
+        .replay()
+        .expect("Record of moves already played should replay");
+    Ok(*game_state)
+}
+
+/// Takes back the human's most recent move against computer opponents, along with every reply to it.
+///
+/// `is_human` tells which pieces the human plays.
+fn undo_human_move(
+    game_state: &mut GameState,
+    record: &mut GameRecord,
+    repetitions: &mut Repetitions,
+    is_human: impl Fn(GamePiece) -> bool,
+) -> Result<GameState, ClientError> {
+    let positions = record
+        .positions()
+        .expect("Record of moves already played should replay");
+    // Take back the opponents' replies too, so that it is the human's turn again
+    let count = positions
+        .iter()
+        .rev()
+        .skip(1)
+        .position(|state| is_human(*state.get_next_player()))
+        .ok_or(ClientError::NothingToUndo)?
+        + 1;
+    undo_moves(game_state, record, repetitions, count)
+}
+
+/// Completes a client's in-progress record with the current result.
+fn finish_record(
+    game_state: &GameState,
+    record: &GameRecord,
+    repetitions: &Repetitions,
+) -> GameRecord {
+    let mut record = record.clone();
+    record.set_result(&endgame(game_state, repetitions));
+    record
+}
@@ -1 +1,229 @@
+use super::{ClientError, GameClient};
+use crate::game::{
+    board::{GameBoard, GamePiece, MIN_PLAYERS},
+    layout,
+    record::{player_header, GameRecord, Repetitions},
+    state::{EndgameType, GameState, Move},
+    GameMode, Variant,
+};
+use crate::settings::Settings;
 
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream, ToSocketAddrs};
+
+/// Port used for online games when none is given.
+pub const DEFAULT_PORT: u16 = 4040;
+/// First word of the greeting each side sends when a game starts.
+const HELLO: &str = "HELLO";
+/// Sent in place of a layout in the host's greeting when the board has no blocked spaces.
+const NO_LAYOUT: &str = "-";
+/// First word of a message announcing a move, followed by the move as written in game records, e.g. `4` or `p4`.
+const MOVE: &str = "MOVE";
+
+/// Plays against an opponent on another computer, over a TCP connection.
+///
+/// The host plays Red and picks the board, its blocked spaces, the rules and who moves first; each move is then sent as a line of text.
+#[derive(Debug)]
+pub struct OnlineGameClient {
+    game_state: GameState,
+    record: GameRecord,
+    /// How often each position has been reached, to find draws by repetition.
+    repetitions: Repetitions,
+    local_piece: GamePiece,
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl OnlineGameClient {
+    /// Opens the given port for an opponent to connect to.
+    pub fn listen(port: u16) -> Result<TcpListener, ClientError> {
+        TcpListener::bind((Ipv4Addr::UNSPECIFIED, port)).map_err(connection_error)
+    }
+
+    /// Waits for an opponent to connect to the listener, then starts a game with them.
+    pub fn host(settings: &Settings, listener: &TcpListener) -> Result<Self, ClientError> {
+        if settings.game.players > MIN_PLAYERS {
+            return Err(ClientError::TooManyPlayers);
+        }
+        let (stream, _) = listener.accept().map_err(connection_error)?;
+        let first_player = settings.game.first_player.pick(settings.game.players);
+        let board = settings.new_board().map_err(ClientError::Settings)?;
+        let mut client = Self::start(
+            settings,
+            board,
+            BufReader::new(stream),
+            GamePiece::Red,
+            first_player,
+        )?;
+
+        let greeting = format!(
+            "{HELLO} {} {} {} {} {} {} {}",
+            board.rows(),
+            board.cols(),
+            board.win_length(),
+            client.game_state.get_next_player(),
+            client.game_state.get_variant(),
+            layout::to_layout(&board).as_deref().unwrap_or(NO_LAYOUT),
+            settings.player_name(GamePiece::Red)
+        );
+        client.send(&greeting)?;
+        let opponent = client.receive(HELLO)?;
+        client.set_opponent_name(opponent.trim());
+        Ok(client)
+    }
+
+    /// Joins a game hosted at the given address, such as `example.com:4040`.
+    pub fn connect(settings: &Settings, address: impl ToSocketAddrs) -> Result<Self, ClientError> {
+        let stream = TcpStream::connect(address).map_err(connection_error)?;
+        let mut reader = BufReader::new(stream);
+        let greeting = read_message(&mut reader, HELLO)?;
+        let invalid =
+            || ClientError::Connection(format!("Unexpected greeting from host: {greeting}"));
+
+        // Rows, columns, win length, first player, rules and blocked spaces, followed by the host's name
+        let mut fields = greeting.splitn(7, ' ');
+        let mut size = || {
+            fields
+                .next()
+                .and_then(|f| f.parse::<usize>().ok())
+                .ok_or_else(invalid)
+        };
+        let (rows, cols, win_length) = (size()?, size()?, size()?);
+        let mut board = GameBoard::with_size(rows, cols, win_length).map_err(|_| invalid())?;
+        let first_player = match fields.next() {
+            Some(p) if p == GamePiece::Red.to_string() => GamePiece::Red,
+            Some(p) if p == GamePiece::Yellow.to_string() => GamePiece::Yellow,
+            _ => return Err(invalid()),
+        };
+        let variant = fields
+            .next()
+            .and_then(|name| Variant::ALL.into_iter().find(|v| v.to_string() == name))
+            .ok_or_else(invalid)?;
+        match fields.next() {
+            Some(NO_LAYOUT) => {}
+            Some(blocked) => layout::apply(&mut board, blocked).map_err(|_| invalid())?,
+            None => return Err(invalid()),
+        }
+        let host_name = fields.next().unwrap_or_default().trim().to_string();
+
+        let mut settings = settings.clone();
+        settings.game.variant = variant;
+        settings.game.players = MIN_PLAYERS;
+        let name = settings.player_name(GamePiece::Yellow);
+
+        let mut client = Self::start(&settings, board, reader, GamePiece::Yellow, first_player)?;
+        client.set_opponent_name(&host_name);
+        client.send(&format!("{HELLO} {name}"))?;
+        client.receive_opponent_move()?;
+        Ok(client)
+    }
+
+    fn start(
+        settings: &Settings,
+        board: GameBoard,
+        reader: BufReader<TcpStream>,
+        local_piece: GamePiece,
+        first_player: GamePiece,
+    ) -> Result<Self, ClientError> {
+        let (game_state, record) =
+            super::new_game_on(board, settings, first_player, GameMode::Online);
+        let repetitions = Repetitions::new(&game_state);
+        Ok(Self {
+            game_state,
+            record,
+            repetitions,
+            local_piece,
+            writer: reader.get_ref().try_clone().map_err(connection_error)?,
+            reader,
+        })
+    }
+
+    fn set_opponent_name(&mut self, name: &str) {
+        let header = player_header(self.local_piece.opponent());
+        if !name.is_empty() {
+            self.record.set_header(header, name);
+        }
+    }
+
+    /// Waits for the opponent's move and plays it, if it is their turn and the game is not over.
+    fn receive_opponent_move(&mut self) -> Result<GameState, ClientError> {
+        if *self.game_state.get_next_player() == self.local_piece
+            || self.get_endgame() != EndgameType::None
+        {
+            return Ok(self.game_state);
+        }
+        let message = self.receive(MOVE)?;
+        let game_move = message.trim().parse().map_err(|_| {
+            ClientError::Connection(format!("Opponent sent an invalid move: {message}"))
+        })?;
+        super::process_move(
+            &mut self.game_state,
+            &mut self.record,
+            &mut self.repetitions,
+            game_move,
+        )
+    }
+
+    fn send(&mut self, message: &str) -> Result<(), ClientError> {
+        writeln!(self.writer, "{message}").map_err(connection_error)
+    }
+
+    fn receive(&mut self, kind: &str) -> Result<String, ClientError> {
+        read_message(&mut self.reader, kind)
+    }
+}
+
+impl GameClient for OnlineGameClient {
+    fn get_current_state(&self) -> GameState {
+        self.game_state
+    }
+
+    fn handle_input_move(&mut self, game_move: Move) -> Result<GameState, ClientError> {
+        // If the opponent's move was lost, keep waiting for it rather than playing for them
+        if *self.game_state.get_next_player() != self.local_piece {
+            return self.receive_opponent_move();
+        }
+        super::process_move(
+            &mut self.game_state,
+            &mut self.record,
+            &mut self.repetitions,
+            game_move,
+        )?;
+        self.send(&format!("{MOVE} {game_move}"))?;
+        self.receive_opponent_move()
+    }
+
+    fn undo_move(&mut self) -> Result<GameState, ClientError> {
+        Err(ClientError::UndoUnavailable)
+    }
+
+    fn get_record(&self) -> GameRecord {
+        super::finish_record(&self.game_state, &self.record, &self.repetitions)
+    }
+
+    fn get_endgame(&self) -> EndgameType {
+        super::endgame(&self.game_state, &self.repetitions)
+    }
+}
+
+/// Reads the next line, which should be a message of the given kind, returning the rest of the line.
+fn read_message(reader: &mut impl BufRead, kind: &str) -> Result<String, ClientError> {
+    let mut line = String::new();
+    match reader.read_line(&mut line) {
+        Ok(0) => Err(ClientError::Connection(
+            "The opponent left the game".to_string(),
+        )),
+        Ok(_) => match line.trim_end().split_once(' ') {
+            Some((word, rest)) if word == kind => Ok(rest.to_string()),
+            _ => Err(ClientError::Connection(format!(
+                "Unexpected message from opponent: {}",
+                line.trim_end()
+            ))),
+        },
+        Err(e) => Err(connection_error(e)),
+    }
+}
+
+fn connection_error(error: std::io::Error) -> ClientError {
+    ClientError::Connection(error.to_string())
+}
@@ -0,0 +1,206 @@
+use crate::client::ai::{self, SearchInfo};
+use crate::game::{
+    board::{GameBoard, GamePiece},
+    record::GameRecord,
+    state::{GameState, Move},
+};
+use crate::settings::{AiLevel, AiSettings};
+
+use std::io::{self, BufRead, Write};
+use std::time::Duration;
+
+const LEVELS: [AiLevel; 3] = [AiLevel::Easy, AiLevel::Medium, AiLevel::Hard];
+/// Name of the option that sets how strongly the engine plays.
+const LEVEL_OPTION: &str = "Level";
+/// Sent instead of a column when there is no move to play.
+const NO_MOVE: &str = "none";
+
+/// Plays the AI through a line-based protocol modelled on UCI, so that other programs can ask it for moves.
+///
+/// Columns are numbered from 1, and positions are given as the moves played from the empty board, with Red moving first:
+///
+/// ```text
+/// position startpos moves 4 4 3
+/// go movetime 1000
+/// info depth 1 score cp 0 time 0 pv 4
+/// ...
+/// bestmove 4
+/// ```
+pub struct Engine {
+    state: GameState,
+    level: AiLevel,
+    think_time: Duration,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        let ai = AiSettings::default();
+        Self {
+            state: start_position(),
+            level: ai.level,
+            think_time: ai.think_time(),
+        }
+    }
+}
+
+impl Engine {
+    /// Answers each command read from `input` until it runs out or a `quit` command is read.
+    pub fn run(&mut self, input: impl BufRead, mut output: impl Write) -> Result<(), String> {
+        for line in input.lines() {
+            let line = line.map_err(|e| e.to_string())?;
+            let mut words = line.split_whitespace();
+            let Some(command) = words.next() else {
+                continue;
+            };
+            let args = words.collect::<Vec<_>>();
+            let replies = match command {
+                "quit" => break,
+                "uci" => self.identify(),
+                "isready" => vec!["readyok".to_string()],
+                "ucinewgame" => {
+                    self.state = start_position();
+                    Vec::new()
+                }
+                "setoption" => self.set_option(&args).into_iter().collect(),
+                "position" => self.set_position(&args).into_iter().collect(),
+                "go" => {
+                    self.go(&args, &mut output).map_err(|e| e.to_string())?;
+                    Vec::new()
+                }
+                _ => vec![info_string(format!("Unknown command: {command}"))],
+            };
+            for reply in replies {
+                writeln!(output, "{reply}").map_err(|e| e.to_string())?;
+            }
+            output.flush().map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    /// Names the engine and lists its options.
+    fn identify(&self) -> Vec<String> {
+        let levels = LEVELS.map(|level| format!("var {level}")).join(" ");
+        vec![
+            format!("id name FourStack {}", env!("CARGO_PKG_VERSION")),
+            format!(
+                "option name {LEVEL_OPTION} type combo default {} {levels}",
+                self.level
+            ),
+            "uciok".to_string(),
+        ]
+    }
+
+    /// Handles `setoption name <name> value <value>`, returning a message if it could not be set.
+    fn set_option(&mut self, args: &[&str]) -> Option<String> {
+        let (name, value) = match args {
+            ["name", name, "value", value] => (*name, *value),
+            _ => return Some(info_string("Expected setoption name <name> value <value>")),
+        };
+        if !name.eq_ignore_ascii_case(LEVEL_OPTION) {
+            return Some(info_string(format!("Unknown option: {name}")));
+        }
+        match LEVELS
+            .iter()
+            .find(|level| level.to_string().eq_ignore_ascii_case(value))
+        {
+            Some(level) => self.level = *level,
+            None => return Some(info_string(format!("Unknown level: {value}"))),
+        }
+        None
+    }
+
+    /// Handles `position startpos [moves <column>...]`, keeping the current position if the new one is invalid.
+    fn set_position(&mut self, args: &[&str]) -> Option<String> {
+        let moves = match args {
+            ["startpos"] => &[][..],
+            ["startpos", "moves", moves @ ..] => moves,
+            _ => {
+                return Some(info_string(
+                    "Expected position startpos [moves <column>...]",
+                ))
+            }
+        };
+        let mut record = GameRecord::new(GamePiece::Red);
+        for column in moves {
+            match column.parse() {
+                Ok(column) => record.push_move(Move::Drop(column)),
+                Err(_) => return Some(info_string(format!("Invalid move: {column}"))),
+            }
+        }
+        match record.replay() {
+            Ok(state) => self.state = state,
+            Err(e) => return Some(info_string(e)),
+        }
+        None
+    }
+
+    /// Handles `go [movetime <ms>] [depth <moves>]`, searching the current position and replying with the best move.
+    ///
+    /// Without limits, the engine searches as deep as its level allows, for as long as the AI opponent would.
+    fn go(&self, args: &[&str], output: &mut impl Write) -> io::Result<()> {
+        let mut depth = self.level.depth();
+        let mut think_time = self.think_time;
+        for pair in args.chunks(2) {
+            let value = pair.get(1).and_then(|v| v.parse::<u64>().ok());
+            match (pair[0], value) {
+                ("movetime", Some(ms)) => think_time = Duration::from_millis(ms),
+                ("depth", Some(moves)) => depth = moves.min(u8::MAX.into()) as u8,
+                _ => {
+                    let message = "Expected go [movetime <ms>] [depth <moves>]";
+                    return writeln!(output, "{}", info_string(message));
+                }
+            }
+        }
+
+        // Report each depth as soon as it is searched, keeping the first error to return afterwards
+        let mut result = Ok(());
+        let best = ai::search(&self.state, depth, think_time, |info| {
+            if result.is_ok() {
+                result = writeln!(output, "{}", search_info(info)).and_then(|_| output.flush());
+            }
+        });
+        result?;
+        let best = best.map_or(NO_MOVE.to_string(), |column| column.to_string());
+        writeln!(output, "bestmove {best}")
+    }
+}
+
+/// Describes the result of searching to one depth, scoring certain wins and losses apart from the AI's estimates.
+fn search_info(info: &SearchInfo) -> String {
+    let score = match info.score {
+        minimax::BEST_EVAL => "win".to_string(),
+        minimax::WORST_EVAL => "loss".to_string(),
+        score => format!("cp {score}"),
+    };
+    format!(
+        "info depth {} score {score} time {} pv {}",
+        info.depth,
+        info.elapsed.as_millis(),
+        info.best_move
+    )
+}
+
+/// The empty board with Red to move, which every position is played from.
+fn start_position() -> GameState {
+    GameState::with_board(GameBoard::default(), GamePiece::Red)
+}
+
+/// A free-form message, which programs driving the engine can show or ignore.
+fn info_string(message: impl ToString) -> String {
+    format!("info string {}", message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_game_starts_with_red_to_move() {
+        let mut engine = Engine::default();
+        assert_eq!(&GamePiece::Red, engine.state.get_next_player());
+
+        let commands = "position startpos moves 4\nucinewgame\n".as_bytes();
+        engine.run(commands, io::sink()).unwrap();
+        assert_eq!(start_position(), engine.state);
+    }
+}
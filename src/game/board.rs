@@ -1,64 +1,206 @@
+use super::zobrist;
+
+#[cfg(feature = "serde")]
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::hash::{Hash, Hasher};
 
 const SPACE_EMPTY: char = ' ';
-const SPACE_RED: char = 'R';
-const SPACE_YELLOW: char = 'Y';
-pub(crate) const GRID_ROWS: usize = 6;
-pub(crate) const GRID_COLS: usize = 7;
+pub(crate) const SPACE_BLOCKED: char = '#';
+pub(crate) const SPACE_RED: char = 'R';
+pub(crate) const SPACE_YELLOW: char = 'Y';
+pub(crate) const SPACE_GREEN: char = 'G';
+pub(crate) const SPACE_BLUE: char = 'B';
+/// Number of rows on a standard board.
+pub const GRID_ROWS: usize = 6;
+/// Number of columns on a standard board.
+pub const GRID_COLS: usize = 7;
+/// Number of pieces in a row needed to win on a standard board.
+pub const WIN_LENGTH: usize = 4;
+/// Smallest number of rows or columns a board can have.
+pub const MIN_SIZE: usize = 4;
+/// Largest number of rows a board can have.
+pub const MAX_ROWS: usize = 9;
+/// Largest number of columns a board can have, so that each column has its own number key, with 0 for the tenth.
+pub const MAX_COLS: usize = 10;
+/// Smallest number of pieces in a row that can be needed to win.
+pub const MIN_WIN_LENGTH: usize = 3;
+/// Fewest players a game can have.
+pub const MIN_PLAYERS: usize = 2;
+/// Most players a game can have, one for each colour of piece.
+pub const MAX_PLAYERS: usize = 4;
 
 // Errors
 
-/// Occurs when a piece is attempted to be inserted.
+/// Occurs when a piece is attempted to be inserted or popped out.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum InsertError {
     InvalidColumn,
     FullColumn,
+    /// The bottom of the column is empty or holds the other player's piece.
+    CannotPop,
+    /// Pieces can only be popped out when playing PopOut.
+    PopUnavailable,
 }
 
 impl Display for InsertError {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         match self {
-            InsertError::InvalidColumn => write!(f, "Please choose a valid column number [1,7]."),
+            InsertError::InvalidColumn => write!(f, "Please choose a valid column number."),
             InsertError::FullColumn => {
                 write!(f, "Selected column is full, please choose another column.")
             }
+            InsertError::CannotPop => write!(
+                f,
+                "You can only pop out your own piece from the bottom of a column."
+            ),
+            InsertError::PopUnavailable => {
+                write!(f, "Pieces can only be popped out when playing PopOut.")
+            }
+        }
+    }
+}
+
+/// Occurs when a board is created with unsupported dimensions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SizeError {
+    Rows(usize),
+    Columns(usize),
+    WinLength(usize),
+}
+
+impl Display for SizeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            SizeError::Rows(n) => write!(
+                f,
+                "A board can't have {n} rows, it needs between {MIN_SIZE} and {MAX_ROWS}."
+            ),
+            SizeError::Columns(n) => write!(
+                f,
+                "A board can't have {n} columns, it needs between {MIN_SIZE} and {MAX_COLS}."
+            ),
+            SizeError::WinLength(n) => write!(
+                f,
+                "Can't play to {n} in a row, it must be at least {MIN_WIN_LENGTH} and fit on the board."
+            ),
         }
     }
 }
 
 /// Colored game peices, one per player.
-#[derive(Clone, Copy, Debug, Default, PartialEq)]
+///
+/// Two-player games are between Red and Yellow; Green and Blue join in games with more players.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
 pub enum GamePiece {
     #[default]
     Red,
     Yellow,
+    Green,
+    Blue,
 }
 
 impl Display for GamePiece {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
-        match self {
-            Self::Red => write!(f, "{}", SPACE_RED),
-            Self::Yellow => write!(f, "{}", SPACE_YELLOW),
+        write!(f, "{}", self.letter())
+    }
+}
+
+impl TryFrom<char> for GamePiece {
+    type Error = ();
+
+    fn try_from(value: char) -> Result<Self, Self::Error> {
+        match value.to_ascii_uppercase() {
+            SPACE_RED => Ok(Self::Red),
+            SPACE_YELLOW => Ok(Self::Yellow),
+            SPACE_GREEN => Ok(Self::Green),
+            SPACE_BLUE => Ok(Self::Blue),
+            _ => Err(()),
         }
     }
 }
 
+/// Serialized as its letter, e.g. `"R"`.
+#[cfg(feature = "serde")]
+impl Serialize for GamePiece {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_char(self.letter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for GamePiece {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let c = char::deserialize(deserializer)?;
+        Self::try_from(c).map_err(|_| de::Error::custom(format!("'{c}' is not a game piece")))
+    }
+}
+
 impl GamePiece {
-    pub fn random() -> Self {
-        use rand::Rng;
-        match rand::thread_rng().gen_range(0..=1) {
-            0 => Self::Red,
-            _ => Self::Yellow,
+    /// Every piece, in the order players take their turns.
+    pub const ALL: [Self; MAX_PLAYERS] = [Self::Red, Self::Yellow, Self::Green, Self::Blue];
+
+    /// The piece belonging to the other player in a two-player game.
+    pub fn opponent(&self) -> Self {
+        self.next(MIN_PLAYERS)
+    }
+
+    /// The piece whose turn comes after this one in a game between the given number of players, which take the first pieces of [`Self::ALL`].
+    pub fn next(&self, players: usize) -> Self {
+        Self::ALL[(self.index() + 1) % players]
+    }
+
+    /// How many turns before `last` this piece's player moves, counting back round the turn order.
+    ///
+    /// Sorting pieces played in one round from most turns before to least puts them in the order they were played, ending with `last`'s.
+    pub fn turns_before(&self, last: GamePiece) -> usize {
+        (last.index() + MAX_PLAYERS - self.index()) % MAX_PLAYERS
+    }
+
+    /// Position of the piece in the turn order, starting at 0 for Red.
+    pub fn index(&self) -> usize {
+        *self as usize
+    }
+
+    /// The colour of the piece written out in full, e.g. for narration.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Red => "Red",
+            Self::Yellow => "Yellow",
+            Self::Green => "Green",
+            Self::Blue => "Blue",
+        }
+    }
+
+    /// The letter the piece is written as on boards and in notation.
+    fn letter(&self) -> char {
+        match self {
+            Self::Red => SPACE_RED,
+            Self::Yellow => SPACE_YELLOW,
+            Self::Green => SPACE_GREEN,
+            Self::Blue => SPACE_BLUE,
         }
     }
+
+    /// Red or Yellow, picked at random.
+    pub fn random() -> Self {
+        Self::random_among(MIN_PLAYERS)
+    }
+
+    /// One of the pieces in a game between the given number of players, picked at random.
+    pub fn random_among(players: usize) -> Self {
+        Self::ALL[super::random::gen_range(0..=players - 1)]
+    }
 }
 
 /// Possible states for a game "board" space.
-#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 enum BoardSpace {
     #[default]
     Empty,
     Piece(GamePiece),
+    /// A neutral space that neither player owns, which pieces stack on and lines cannot pass through.
+    Blocked,
 }
 
 impl Display for BoardSpace {
@@ -66,6 +208,7 @@ impl Display for BoardSpace {
         match self {
             BoardSpace::Empty => write!(f, "{}", SPACE_EMPTY),
             BoardSpace::Piece(p) => write!(f, "{}", p),
+            BoardSpace::Blocked => write!(f, "{}", SPACE_BLOCKED),
         }
     }
 }
@@ -74,148 +217,414 @@ impl Display for BoardSpace {
 impl PartialEq<BoardSpace> for GamePiece {
     fn eq(&self, other: &BoardSpace) -> bool {
         match other {
-            BoardSpace::Empty => false,
+            BoardSpace::Empty | BoardSpace::Blocked => false,
             BoardSpace::Piece(p) => self.eq(p),
         }
     }
 }
 
-type BoardColumns = [BoardSpace; GRID_COLS];
-type GameGrid = [BoardColumns; GRID_ROWS];
+type BoardColumns = [BoardSpace; MAX_COLS];
+/// Room for the largest supported board, of which only the top-left `rows` by `cols` spaces are used.
+type GameGrid = [BoardColumns; MAX_ROWS];
+/// Spaces making up a winning line, as (row, column) pairs.
+pub type WinningLine = Vec<(usize, usize)>;
 
 /// Manages the state of the game board.
-#[derive(Clone, Copy, Debug, Default, PartialEq)]
+///
+/// Serialized as its rows from the top and its win length, in the same form as position notation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(
+        into = "super::notation::BoardRows",
+        try_from = "super::notation::BoardRows"
+    )
+)]
 pub struct GameBoard {
     grid: GameGrid,
+    rows: usize,
+    cols: usize,
+    win_length: usize,
+    /// Whether the left and right edges meet, so that lines can wrap around from the last column to the first.
+    wraps: bool,
+    /// Zobrist hash of the pieces and blocked spaces on the board, updated as they are added.
+    hash: u64,
+    /// Zobrist hash of the pieces and blocked spaces as they would be if the board were reflected left to right.
+    mirror_hash: u64,
+}
+
+/// Hashes boards by their Zobrist hash, which equal boards always share.
+impl Hash for GameBoard {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash);
+    }
+}
+
+impl Default for GameBoard {
+    fn default() -> Self {
+        Self {
+            grid: Default::default(),
+            rows: GRID_ROWS,
+            cols: GRID_COLS,
+            win_length: WIN_LENGTH,
+            wraps: false,
+            hash: 0,
+            mirror_hash: 0,
+        }
+    }
 }
 
 impl GameBoard {
+    /// Creates an empty board with the given number of rows and columns, won by getting `win_length` pieces in a row.
+    ///
+    /// Returns a [`SizeError`] if the board would be too small or too large, or could never be won.
+    pub fn with_size(rows: usize, cols: usize, win_length: usize) -> Result<Self, SizeError> {
+        if !(MIN_SIZE..=MAX_ROWS).contains(&rows) {
+            return Err(SizeError::Rows(rows));
+        }
+        if !(MIN_SIZE..=MAX_COLS).contains(&cols) {
+            return Err(SizeError::Columns(cols));
+        }
+        if win_length < MIN_WIN_LENGTH || win_length > rows.max(cols) {
+            return Err(SizeError::WinLength(win_length));
+        }
+        Ok(Self {
+            rows,
+            cols,
+            win_length,
+            ..Default::default()
+        })
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// Number of pieces in a row needed to win.
+    pub fn win_length(&self) -> usize {
+        self.win_length
+    }
+
+    /// Joins the left and right edges of the board, as if it were wrapped around a cylinder, or separates them again.
+    pub fn with_wrapping(mut self, wraps: bool) -> Self {
+        self.wraps = wraps;
+        self
+    }
+
+    /// Checks whether horizontal and diagonal lines can wrap around from the last column to the first.
+    pub fn wraps(&self) -> bool {
+        self.wraps
+    }
+
+    /// Creates a board with the same size, win length and blocked spaces as this one, but none of its pieces.
+    pub fn cleared(&self) -> Self {
+        let mut cleared = Self {
+            grid: Default::default(),
+            hash: 0,
+            mirror_hash: 0,
+            ..*self
+        };
+        for (row, col) in (0..self.rows).flat_map(|row| (0..self.cols).map(move |col| (row, col))) {
+            if self.is_blocked(row, col) {
+                cleared.block_space(row, col);
+            }
+        }
+        cleared
+    }
+
+    /// Blocks an empty space, so that neither player can take it and no line can pass through it.
+    /// Rows are counted from the top of the board and both indices start at zero.
+    ///
+    /// Returns `false`, leaving the board as it was, if the space is not on the board or not empty.
+    pub fn block_space(&mut self, row: usize, col: usize) -> bool {
+        if row >= self.rows || col >= self.cols || self.grid[row][col] != BoardSpace::Empty {
+            return false;
+        }
+        self.grid[row][col] = BoardSpace::Blocked;
+        self.hash ^= zobrist::blocked_key(row, col);
+        self.mirror_hash ^= zobrist::blocked_key(row, self.cols - 1 - col);
+        true
+    }
+
+    /// Checks if a space is blocked, with rows counted from the top and both indices starting at zero.
+    pub fn is_blocked(&self, row: usize, col: usize) -> bool {
+        row < self.rows && col < self.cols && self.grid[row][col] == BoardSpace::Blocked
+    }
+
+    /// Checks if the board has any blocked spaces.
+    pub fn has_blocked_spaces(&self) -> bool {
+        self.grid[..self.rows]
+            .iter()
+            .any(|row| row[..self.cols].contains(&BoardSpace::Blocked))
+    }
+
+    /// Checks if a piece can be dropped into the given column, numbered from 1.
+    pub fn has_room(&self, col_num: usize) -> bool {
+        (1..=self.cols).contains(&col_num) && self.grid[0][col_num - 1] == BoardSpace::Empty
+    }
+
+    /// A hash of the pieces and blocked spaces on the board, which is the same however the board was reached.
+    ///
+    /// Hashes are the same between runs, so they can be saved, but boards of different sizes may share one.
+    pub fn zobrist(&self) -> u64 {
+        self.hash
+    }
+
+    /// The hash that the board would have if it were reflected left to right.
+    pub fn mirrored_zobrist(&self) -> u64 {
+        self.mirror_hash
+    }
+
+    /// The hash of the board's canonical form, which it shares with its reflection.
+    pub fn canonical_zobrist(&self) -> u64 {
+        self.hash.min(self.mirror_hash)
+    }
+
+    /// The board reflected left to right, as it would look from the other side.
+    pub fn mirrored(&self) -> Self {
+        let mut mirrored = *self;
+        for row in &mut mirrored.grid[..self.rows] {
+            row[..self.cols].reverse();
+        }
+        mirrored.hash = self.mirror_hash;
+        mirrored.mirror_hash = self.hash;
+        mirrored
+    }
+
+    /// Picks one of the board and its reflection, the one with the smaller hash, so that both share a canonical form.
+    ///
+    /// Also returns whether the reflection was picked, in which case columns found on the canonical board are reflected back with [`Self::mirror_column`].
+    pub fn canonical(&self) -> (Self, bool) {
+        match self.is_canonical() {
+            true => (*self, false),
+            false => (self.mirrored(), true),
+        }
+    }
+
+    /// Checks if the board is its own canonical form, rather than its reflection being.
+    pub fn is_canonical(&self) -> bool {
+        self.hash <= self.mirror_hash
+    }
+
+    /// The column, numbered from 1, that the given column becomes when the board is reflected.
+    pub fn mirror_column(&self, col_num: usize) -> usize {
+        self.cols + 1 - col_num
+    }
+
     /// Insert a new piece into the game board in a given column number.
+    /// The piece comes to rest on the highest piece or blocked space in the column, or at the bottom if there are none.
     /// Returns an [`InsertError`] if the column number is invalid, or if the column is already full.
     pub fn insert_piece(&mut self, piece: GamePiece, col_num: usize) -> Result<(), InsertError> {
         // Validate input column number
-        if !(1..=GRID_COLS).contains(&col_num) {
+        if !(1..=self.cols).contains(&col_num) {
             return Err(InsertError::InvalidColumn);
         }
 
-        for row in self.grid.iter_mut().rev() {
-            if let BoardSpace::Empty = row[col_num - 1] {
-                row[col_num - 1] = BoardSpace::Piece(piece);
-                return Ok(());
+        // Fall from the top until the space below is taken
+        let landing = (0..self.rows)
+            .take_while(|row| self.grid[*row][col_num - 1] == BoardSpace::Empty)
+            .last();
+        match landing {
+            Some(row) => {
+                self.set_piece(piece, row, col_num - 1);
+                Ok(())
             }
+            // No spaces in given column left to put piece into
+            None => Err(InsertError::FullColumn),
         }
-        // No spaces in given column left to put piece into
-        Err(InsertError::FullColumn)
     }
 
-    /// Checks if there is a winner in the current game state.
-    /// Checks for four like pieces in a row horizontally, vertically, and diagonally.
-    ///
-    /// Returns an [`Option`] containing the [`GamePiece`] of the winning player, or [`None`] if there is no winner.
-    pub(crate) fn is_winner(&self) -> Option<GamePiece> {
-        // Horizontal
-        for row in 0..GRID_ROWS {
-            for col in 0..GRID_COLS - 3 {
-                match self.grid[row][col] {
-                    BoardSpace::Empty => (),
-                    BoardSpace::Piece(piece) => {
-                        if piece == self.grid[row][col + 1]
-                            && piece == self.grid[row][col + 2]
-                            && piece == self.grid[row][col + 3]
-                        {
-                            return Some(piece);
-                        }
-                    }
-                }
-            }
-        }
+    /// Places or removes a piece on a space, keeping the hashes up to date.
+    fn set_piece(&mut self, piece: GamePiece, row: usize, col: usize) {
+        self.grid[row][col] = match self.grid[row][col] {
+            BoardSpace::Empty => BoardSpace::Piece(piece),
+            _ => BoardSpace::Empty,
+        };
+        self.hash ^= zobrist::piece_key(piece, row, col);
+        self.mirror_hash ^= zobrist::piece_key(piece, row, self.cols - 1 - col);
+    }
 
-        // Vertical
-        for row in 0..GRID_ROWS - 3 {
-            for col in 0..GRID_COLS {
-                match self.grid[row][col] {
-                    BoardSpace::Empty => (),
-                    BoardSpace::Piece(piece) => {
-                        if piece == self.grid[row + 1][col]
-                            && piece == self.grid[row + 2][col]
-                            && piece == self.grid[row + 3][col]
-                        {
-                            return Some(piece);
-                        }
-                    }
-                }
-            }
+    /// Removes the given player's piece from the bottom of a column, letting the pieces above it fall down one space.
+    /// Pieces resting on a blocked space stay where they are.
+    /// Returns an [`InsertError`] if the column number is invalid, or if the bottom of the column is not the player's piece.
+    pub fn pop_piece(&mut self, piece: GamePiece, col_num: usize) -> Result<(), InsertError> {
+        if !(1..=self.cols).contains(&col_num) {
+            return Err(InsertError::InvalidColumn);
+        }
+        if self.get_piece(self.rows - 1, col_num - 1) != Some(piece) {
+            return Err(InsertError::CannotPop);
         }
 
-        // Diagonal up
-        for row in 0..GRID_ROWS - 3 {
-            for col in 3..GRID_COLS {
-                match self.grid[row][col] {
-                    BoardSpace::Empty => (),
-                    BoardSpace::Piece(piece) => {
-                        if piece == self.grid[row + 1][col - 1]
-                            && piece == self.grid[row + 2][col - 2]
-                            && piece == self.grid[row + 3][col - 3]
-                        {
-                            return Some(piece);
-                        }
-                    }
+        // Lift out the stack of pieces up to the first blocked space, then put it back one space lower
+        let col = col_num - 1;
+        let mut stack = Vec::with_capacity(self.rows);
+        for row in (0..self.rows).rev() {
+            match self.grid[row][col] {
+                BoardSpace::Piece(p) => {
+                    self.set_piece(p, row, col);
+                    stack.push(p);
                 }
+                _ => break,
             }
         }
+        for (p, row) in stack.into_iter().skip(1).zip((0..self.rows).rev()) {
+            self.set_piece(p, row, col);
+        }
+        Ok(())
+    }
 
-        // Diagonal down
-        for row in 3..GRID_ROWS {
-            for col in 3..GRID_COLS {
-                match self.grid[row][col] {
-                    BoardSpace::Empty => (),
-                    BoardSpace::Piece(piece) => {
-                        if piece == self.grid[row - 1][col - 1]
-                            && piece == self.grid[row - 2][col - 2]
-                            && piece == self.grid[row - 3][col - 3]
-                        {
-                            return Some(piece);
-                        }
-                    }
-                }
-            }
+    /// Checks if there is a winner in the current game state.
+    /// Checks for enough like pieces in a row horizontally, vertically, and diagonally.
+    ///
+    /// Returns an [`Option`] containing the [`GamePiece`] of the winning player, or [`None`] if there is no winner.
+    pub fn is_winner(&self) -> Option<GamePiece> {
+        self.winning_lines().next().map(|(piece, _)| piece)
+    }
+
+    /// Checks if the given player has a winning line anywhere on the board.
+    pub fn has_line(&self, piece: GamePiece) -> bool {
+        self.winning_lines().any(|(p, _)| p == piece)
+    }
+
+    /// Finds the spaces of a winning line of like pieces, as (row, column) pairs counted from the top-left and starting at zero.
+    ///
+    /// Returns [`None`] if there is no winner.
+    pub fn get_winning_line(&self) -> Option<WinningLine> {
+        self.winning_lines().next().map(|(_, line)| line)
+    }
+
+    /// Yields every winning line of like pieces on the board, along with the [`GamePiece`] that owns it.
+    fn winning_lines(&self) -> impl Iterator<Item = (GamePiece, WinningLine)> + '_ {
+        self.line_starts().filter_map(move |(start, direction)| {
+            let piece = match self.grid[start.0][start.1] {
+                BoardSpace::Piece(piece) => piece,
+                _ => return None,
+            };
+            // Check the line before collecting it, as most starting spaces won't have one
+            let is_line = (1..self.win_length).all(|step| {
+                self.line_space(start, direction, step)
+                    .is_some_and(|(r, c)| piece == self.grid[r][c])
+            });
+            is_line.then(|| (piece, self.window(start, direction).unwrap_or_default()))
+        })
+    }
+
+    /// Yields every line of spaces as long as the win length, whether or not it holds any pieces, as (row, column) pairs counted from the top-left and starting at zero.
+    ///
+    /// On a board that wraps, this includes the lines that wrap around from the last column to the first.
+    /// Lines through a blocked space are left out, as they can never be completed.
+    pub fn windows(&self) -> impl Iterator<Item = WinningLine> + '_ {
+        self.line_starts()
+            .filter_map(|(start, direction)| self.window(start, direction))
+            .filter(|line| line.iter().all(|(r, c)| !self.is_blocked(*r, *c)))
+    }
+
+    /// Yields every space paired with every direction a line could run in from it, as (row, column) steps.
+    fn line_starts(&self) -> impl Iterator<Item = ((usize, usize), (isize, isize))> {
+        // Horizontal, vertical, diagonal down and diagonal up
+        const DIRECTIONS: [(isize, isize); 4] = [(0, 1), (1, 0), (1, 1), (-1, 1)];
+
+        let (rows, cols) = (self.rows, self.cols);
+        (0..rows)
+            .flat_map(move |row| (0..cols).map(move |col| (row, col)))
+            .flat_map(|start| DIRECTIONS.iter().map(move |dir| (start, *dir)))
+    }
+
+    /// The spaces of a line from a starting space in a direction, if the whole line fits on the board.
+    fn window(&self, start: (usize, usize), direction: (isize, isize)) -> Option<WinningLine> {
+        (0..self.win_length)
+            .map(|step| self.line_space(start, direction, step))
+            .collect()
+    }
+
+    /// The space a number of steps along a line, if it is on the board, wrapping around the sides if the board wraps.
+    fn line_space(
+        &self,
+        (row, col): (usize, usize),
+        (d_row, d_col): (isize, isize),
+        step: usize,
+    ) -> Option<(usize, usize)> {
+        let r = row as isize + d_row * step as isize;
+        let mut c = col as isize + d_col * step as isize;
+        if self.wraps {
+            c = c.rem_euclid(self.cols as isize);
         }
+        ((0..self.rows as isize).contains(&r) && (0..self.cols as isize).contains(&c))
+            .then_some((r as usize, c as usize))
+    }
 
-        // All win condition checks failed - no one has won yet!
-        None
+    /// Finds the columns where dropping the given piece would immediately make four in a row.
+    pub fn winning_columns(&self, piece: GamePiece) -> Vec<usize> {
+        (1..=self.cols)
+            .filter(|col| {
+                let mut board = *self;
+                board.insert_piece(piece, *col).is_ok() && board.has_line(piece)
+            })
+            .collect()
     }
 
     /// Checks if the board is full by seeing if the topmost row is full.
-    pub(crate) fn is_full(&self) -> bool {
-        for space in self.grid[0] {
-            if BoardSpace::Empty == space {
+    pub fn is_full(&self) -> bool {
+        for space in &self.grid[0][..self.cols] {
+            if BoardSpace::Empty == *space {
                 return false;
             }
         }
         true
     }
 
+    /// Gets the piece occupying a given space, if any, which is never the case for a blocked space.
+    /// Rows are counted from the top of the board and both indices start at zero.
+    pub fn get_piece(&self, row: usize, col: usize) -> Option<GamePiece> {
+        if row >= self.rows || col >= self.cols {
+            return None;
+        }
+        match self.grid[row][col] {
+            BoardSpace::Piece(p) => Some(p),
+            _ => None,
+        }
+    }
+
     #[cfg(test)]
-    fn new(grid: GameGrid) -> Self {
-        Self {
-            grid,
-            ..Default::default()
+    fn new(spaces: [[BoardSpace; GRID_COLS]; GRID_ROWS]) -> Self {
+        let mut board = Self::default();
+        for (row, spaces) in board.grid.iter_mut().zip(spaces) {
+            row[..GRID_COLS].copy_from_slice(&spaces);
         }
+        for (row, col) in (0..GRID_ROWS).flat_map(|row| (0..GRID_COLS).map(move |col| (row, col))) {
+            match board.grid[row][col] {
+                BoardSpace::Piece(piece) => {
+                    board.hash ^= zobrist::piece_key(piece, row, col);
+                    board.mirror_hash ^= zobrist::piece_key(piece, row, GRID_COLS - 1 - col);
+                }
+                BoardSpace::Blocked => {
+                    board.hash ^= zobrist::blocked_key(row, col);
+                    board.mirror_hash ^= zobrist::blocked_key(row, GRID_COLS - 1 - col);
+                }
+                BoardSpace::Empty => {}
+            }
+        }
+        board
     }
 }
 
 impl Display for GameBoard {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
-        // Top of board should display the column number
-        let board_header = (1..=GRID_COLS)
-            .map(|num| format!("+-{}-", num))
+        // Top of board should display the column number, with two-digit numbers filling their space
+        let board_header = (1..=self.cols)
+            .map(|num| format!("+{:-^3}", num))
             .collect::<Vec<String>>()
             .join("");
         writeln!(f, "{}+", board_header)?;
 
-        let row_separator = "+---".repeat(GRID_COLS) + "+\n";
-        for row in &self.grid {
-            for space in row {
+        let row_separator = "+---".repeat(self.cols) + "+\n";
+        for row in &self.grid[..self.rows] {
+            for space in &row[..self.cols] {
                 write!(f, "| {} ", space)?;
             }
             write!(f, "|\n{}", row_separator)?;
@@ -247,6 +656,19 @@ mod tests {
         assert_eq!(empty_board, GameBoard::default().to_string());
     }
 
+    #[test]
+    fn wide_grid_display() {
+        let wide_board = GameBoard::with_size(8, 10, 4).unwrap().to_string();
+        assert_eq!(
+            Some("+-1-+-2-+-3-+-4-+-5-+-6-+-7-+-8-+-9-+10-+"),
+            wide_board.lines().next()
+        );
+        assert_eq!(
+            Some("+---".repeat(10) + "+"),
+            wide_board.lines().last().map(String::from)
+        );
+    }
+
     #[test]
     fn insert_piece_valid() {
         let mut board = GameBoard::default();
@@ -263,7 +685,8 @@ mod tests {
             BoardSpace::Piece(GamePiece::Yellow),
             BoardSpace::Piece(GamePiece::Red),
         ];
-        let actual_col_1: Vec<BoardSpace> = board.grid.iter().map(|row| row[0]).collect();
+        let actual_col_1: Vec<BoardSpace> =
+            board.grid[..GRID_ROWS].iter().map(|row| row[0]).collect();
         assert_eq!(expected_col_1, actual_col_1.as_slice());
 
         let expected_col_7 = [
@@ -274,7 +697,8 @@ mod tests {
             BoardSpace::Empty,
             BoardSpace::Piece(GamePiece::Yellow),
         ];
-        let actual_col_7: Vec<BoardSpace> = board.grid.iter().map(|row| row[6]).collect();
+        let actual_col_7: Vec<BoardSpace> =
+            board.grid[..GRID_ROWS].iter().map(|row| row[6]).collect();
         assert_eq!(expected_col_7, actual_col_7.as_slice());
     }
 
@@ -296,6 +720,37 @@ mod tests {
         assert!(bad_insert.is_err());
     }
 
+    #[test]
+    fn pop_piece() {
+        let mut board = GameBoard::default();
+        for piece in [GamePiece::Red, GamePiece::Yellow, GamePiece::Red] {
+            board.insert_piece(piece, 2).unwrap();
+        }
+        board.insert_piece(GamePiece::Yellow, 3).unwrap();
+
+        assert_eq!(
+            Err(InsertError::CannotPop),
+            board.pop_piece(GamePiece::Yellow, 2)
+        );
+        assert_eq!(
+            Err(InsertError::CannotPop),
+            board.pop_piece(GamePiece::Red, 1)
+        );
+        assert_eq!(
+            Err(InsertError::InvalidColumn),
+            board.pop_piece(GamePiece::Red, 8)
+        );
+
+        board.pop_piece(GamePiece::Red, 2).unwrap();
+        let mut expected = GameBoard::default();
+        expected.insert_piece(GamePiece::Yellow, 2).unwrap();
+        expected.insert_piece(GamePiece::Red, 2).unwrap();
+        expected.insert_piece(GamePiece::Yellow, 3).unwrap();
+        assert_eq!(expected, board);
+        assert_eq!(expected.zobrist(), board.zobrist());
+        assert_eq!(expected.mirrored_zobrist(), board.mirrored_zobrist());
+    }
+
     #[test]
     fn is_full_empty() {
         assert!(!GameBoard::default().is_full());
@@ -303,7 +758,7 @@ mod tests {
 
     #[test]
     fn is_full_full() {
-        let grid: GameGrid = [[BoardSpace::Piece(GamePiece::Red); 7]; 6];
+        let grid = [[BoardSpace::Piece(GamePiece::Red); GRID_COLS]; GRID_ROWS];
         let board = GameBoard::new(grid);
 
         assert!(board.is_full());
@@ -379,6 +834,151 @@ mod tests {
         assert_eq!(Some(win_piece), board.is_winner());
     }
 
+    #[test]
+    fn winning_line_cells() {
+        let mut board = GameBoard::default();
+        assert_eq!(None, board.get_winning_line());
+
+        for col in 2..=5 {
+            board.insert_piece(GamePiece::Yellow, col).unwrap();
+        }
+        assert_eq!(
+            Some(vec![(5, 1), (5, 2), (5, 3), (5, 4)]),
+            board.get_winning_line()
+        );
+    }
+
+    #[test]
+    fn with_size() {
+        assert_eq!(Err(SizeError::Rows(3)), GameBoard::with_size(3, 7, 4));
+        assert_eq!(Err(SizeError::Columns(11)), GameBoard::with_size(6, 11, 4));
+        assert_eq!(Err(SizeError::WinLength(2)), GameBoard::with_size(6, 7, 2));
+        assert_eq!(Err(SizeError::WinLength(6)), GameBoard::with_size(4, 5, 6));
+        assert_eq!(Ok(GameBoard::default()), GameBoard::with_size(6, 7, 4));
+
+        let mut board = GameBoard::with_size(4, 5, 3).unwrap();
+        assert_eq!(
+            Err(InsertError::InvalidColumn),
+            board.insert_piece(GamePiece::Red, 6)
+        );
+        for _ in 0..4 {
+            board.insert_piece(GamePiece::Yellow, 5).unwrap();
+        }
+        assert_eq!(
+            Err(InsertError::FullColumn),
+            board.insert_piece(GamePiece::Red, 5)
+        );
+        assert_eq!(
+            Some("+-1-+-2-+-3-+-4-+-5-+"),
+            board.to_string().lines().next()
+        );
+        assert_eq!(9, board.to_string().lines().count());
+    }
+
+    #[test]
+    fn custom_win_length() {
+        let mut board = GameBoard::with_size(9, 9, 5).unwrap();
+        for col in 1..=4 {
+            board.insert_piece(GamePiece::Red, col).unwrap();
+        }
+        assert_eq!(None, board.is_winner());
+        assert_eq!(vec![5], board.winning_columns(GamePiece::Red));
+
+        board.insert_piece(GamePiece::Red, 5).unwrap();
+        assert_eq!(Some(GamePiece::Red), board.is_winner());
+        assert_eq!(
+            Some(vec![(8, 0), (8, 1), (8, 2), (8, 3), (8, 4)]),
+            board.get_winning_line()
+        );
+        assert_eq!(board.cleared(), GameBoard::with_size(9, 9, 5).unwrap());
+    }
+
+    #[test]
+    fn wrapped_lines() {
+        let mut board = GameBoard::default();
+        assert_eq!(69, board.windows().count());
+        for col in [6, 7, 1] {
+            board.insert_piece(GamePiece::Red, col).unwrap();
+        }
+        assert!(board.winning_columns(GamePiece::Red).is_empty());
+
+        let mut board = board.with_wrapping(true);
+        assert_eq!(105, board.windows().count());
+        assert_eq!(vec![2, 5], board.winning_columns(GamePiece::Red));
+        board.insert_piece(GamePiece::Red, 2).unwrap();
+        assert_eq!(
+            Some(vec![(5, 5), (5, 6), (5, 0), (5, 1)]),
+            board.get_winning_line()
+        );
+
+        // Diagonals wrap too, climbing from the last column into the first
+        let mut board = GameBoard::default().with_wrapping(true);
+        for (col, height) in [(7, 1), (1, 2), (2, 3), (3, 4)] {
+            for _ in 1..height {
+                board.insert_piece(GamePiece::Yellow, col).unwrap();
+            }
+            board.insert_piece(GamePiece::Red, col).unwrap();
+        }
+        assert_eq!(Some(GamePiece::Red), board.is_winner());
+        assert_eq!(None, board.with_wrapping(false).is_winner());
+    }
+
+    #[test]
+    fn blocked_spaces() {
+        let mut board = GameBoard::default();
+        assert!(board.block_space(5, 3));
+        assert!(!board.block_space(5, 3) && !board.block_space(6, 0));
+        assert!(board.is_blocked(5, 3) && board.has_blocked_spaces());
+        // Four horizontal, one vertical and one of each diagonal line passed through the space
+        assert_eq!(62, board.windows().count());
+
+        for col in 1..=4 {
+            board.insert_piece(GamePiece::Red, col).unwrap();
+        }
+        assert_eq!(Some(GamePiece::Red), board.get_piece(4, 3));
+        assert_eq!(None, board.is_winner());
+        assert!(board.winning_columns(GamePiece::Red).is_empty());
+        assert_eq!(
+            Err(InsertError::CannotPop),
+            board.pop_piece(GamePiece::Red, 4)
+        );
+
+        // Spaces beneath a blocked space can't be reached, and pieces above it stay put when the bottom is popped
+        board.block_space(3, 0);
+        board.insert_piece(GamePiece::Yellow, 1).unwrap();
+        assert_eq!(Some(GamePiece::Yellow), board.get_piece(2, 0));
+        board.pop_piece(GamePiece::Red, 1).unwrap();
+        assert_eq!(Some(GamePiece::Yellow), board.get_piece(2, 0));
+        assert_eq!(None, board.get_piece(5, 0));
+
+        let cleared = board.cleared();
+        assert!(cleared.is_blocked(5, 3) && cleared.is_blocked(3, 0));
+        let mut layout = GameBoard::default();
+        layout.block_space(3, 0);
+        layout.block_space(5, 3);
+        assert_eq!(layout.zobrist(), cleared.zobrist());
+        assert_eq!(layout.mirrored_zobrist(), cleared.mirrored_zobrist());
+        assert!(!cleared.has_room(8));
+    }
+
+    #[test]
+    fn winning_columns() {
+        let mut board = GameBoard::default();
+        assert!(board.winning_columns(GamePiece::Red).is_empty());
+
+        for col in 3..=5 {
+            board.insert_piece(GamePiece::Red, col).unwrap();
+        }
+        for _ in 0..3 {
+            board.insert_piece(GamePiece::Yellow, 7).unwrap();
+        }
+        assert_eq!(vec![2, 6], board.winning_columns(GamePiece::Red));
+        assert_eq!(vec![7], board.winning_columns(GamePiece::Yellow));
+
+        board.insert_piece(GamePiece::Red, 7).unwrap();
+        assert!(board.winning_columns(GamePiece::Yellow).is_empty());
+    }
+
     #[test]
     fn is_winner_diagonal_down() {
         let mut board = GameBoard::default();
@@ -401,4 +1001,92 @@ mod tests {
 
         assert_eq!(Some(win_piece), board.is_winner());
     }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde_round_trip() {
+        assert_eq!("\"Y\"", serde_json::to_string(&GamePiece::Yellow).unwrap());
+        for piece in GamePiece::ALL {
+            let json = serde_json::to_string(&piece).unwrap();
+            assert_eq!(piece, serde_json::from_str::<GamePiece>(&json).unwrap());
+        }
+        assert!(serde_json::from_str::<GamePiece>("\"X\"").is_err());
+
+        let mut board = GameBoard::with_size(4, 5, 3).unwrap();
+        board.insert_piece(GamePiece::Red, 2).unwrap();
+        board.insert_piece(GamePiece::Yellow, 2).unwrap();
+        board.insert_piece(GamePiece::Red, 5).unwrap();
+        let json = serde_json::to_string(&board).unwrap();
+        assert_eq!(
+            r#"{"rows":[".....",".....",".Y...",".R..R"],"win_length":3}"#,
+            json
+        );
+        assert_eq!(board, serde_json::from_str(&json).unwrap());
+
+        let floating = r#"{"rows":[".....",".....",".Y...","....R"],"win_length":3}"#;
+        assert!(serde_json::from_str::<GameBoard>(floating).is_err());
+    }
+
+    #[test]
+    fn zobrist_follows_the_pieces() {
+        let mut board = GameBoard::default();
+        assert_eq!(0, board.zobrist());
+        board.insert_piece(GamePiece::Red, 4).unwrap();
+        let one_piece = board.zobrist();
+        assert_ne!(0, one_piece);
+        // A piece in the middle column is its own reflection
+        assert_eq!(one_piece, board.mirrored_zobrist());
+
+        // The same pieces reached in a different order hash the same
+        let mut first = board;
+        first.insert_piece(GamePiece::Yellow, 1).unwrap();
+        first.insert_piece(GamePiece::Red, 2).unwrap();
+        let mut second = board;
+        second.insert_piece(GamePiece::Red, 2).unwrap();
+        second.insert_piece(GamePiece::Yellow, 1).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(first.zobrist(), second.zobrist());
+
+        let mut reflected = board;
+        reflected.insert_piece(GamePiece::Yellow, 7).unwrap();
+        reflected.insert_piece(GamePiece::Red, 6).unwrap();
+        assert_eq!(first.mirrored_zobrist(), reflected.zobrist());
+        assert_eq!(reflected.mirrored_zobrist(), first.zobrist());
+        assert_ne!(first.zobrist(), reflected.zobrist());
+
+        assert_eq!(0, first.cleared().zobrist());
+    }
+
+    #[test]
+    fn mirrored_and_canonical() {
+        let mut board = GameBoard::with_size(5, 6, 4).unwrap();
+        board.insert_piece(GamePiece::Red, 1).unwrap();
+        board.insert_piece(GamePiece::Yellow, 1).unwrap();
+        board.insert_piece(GamePiece::Red, 5).unwrap();
+
+        let mirrored = board.mirrored();
+        assert_eq!(Some(GamePiece::Yellow), mirrored.get_piece(3, 5));
+        assert_eq!(Some(GamePiece::Red), mirrored.get_piece(4, 1));
+        assert_eq!(None, mirrored.get_piece(4, 0));
+        assert_eq!(board, mirrored.mirrored());
+        assert_eq!(6, board.mirror_column(1));
+
+        // Reflecting a board gives the same board as playing the reflected moves
+        let mut played = board.cleared();
+        for (piece, col) in [
+            (GamePiece::Red, 6),
+            (GamePiece::Yellow, 6),
+            (GamePiece::Red, 2),
+        ] {
+            played.insert_piece(piece, col).unwrap();
+        }
+        assert_eq!(played, mirrored);
+        assert_eq!(played.zobrist(), mirrored.zobrist());
+
+        let (canonical, flipped) = board.canonical();
+        assert_eq!((canonical, !flipped), mirrored.canonical());
+        assert_eq!(canonical.zobrist(), board.canonical_zobrist());
+        assert_eq!(board.canonical_zobrist(), mirrored.canonical_zobrist());
+        assert_eq!(flipped, canonical == mirrored);
+    }
 }
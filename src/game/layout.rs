@@ -0,0 +1,203 @@
+use crate::game::board::{GameBoard, SPACE_BLOCKED};
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::path::Path;
+
+const LAYOUT_OPEN: char = '.';
+const ROW_SEPARATOR: char = '/';
+
+/// Occurs when blocked spaces cannot be placed on a board.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LayoutError {
+    /// The layout file could not be read.
+    Io(String),
+    /// The layout does not have one row for each row of the board.
+    RowCount { expected: usize, found: usize },
+    /// A row (counted from the top, starting at 1) does not have one cell for each column of the board.
+    RowLength(usize),
+    /// A cell is neither open nor blocked.
+    InvalidCell(char),
+    /// More spaces were asked to be blocked than the board has room for.
+    TooManyBlockers { requested: usize, max: usize },
+}
+
+impl Display for LayoutError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Io(e) => write!(f, "Could not read layout file: {e}"),
+            Self::RowCount { expected, found } => write!(
+                f,
+                "Layout has {found} rows, but the board has {expected}."
+            ),
+            Self::RowLength(row) => {
+                write!(f, "Row {row} of the layout must have a cell for each column.")
+            }
+            Self::InvalidCell(c) => write!(
+                f,
+                "'{c}' is not a valid layout cell; use '{LAYOUT_OPEN}' for open and '{SPACE_BLOCKED}' for blocked spaces."
+            ),
+            Self::TooManyBlockers { requested, max } => write!(
+                f,
+                "Can't block {requested} spaces, this board has room for at most {max}."
+            ),
+        }
+    }
+}
+
+/// Most spaces that can be blocked at random on a board of the given size, which is a quarter of them.
+pub fn max_blockers(rows: usize, cols: usize) -> usize {
+    rows * cols / 4
+}
+
+/// Blocks the spaces marked `#` in a layout, leaving those marked `.` open.
+///
+/// Rows are listed from the top of the board, separated by new lines or `/`, and must match the board's size.
+/// Blocked spaces don't need anything beneath them, but pieces can't reach the spaces below one.
+pub fn apply(board: &mut GameBoard, layout: &str) -> Result<(), LayoutError> {
+    let rows: Vec<&str> = layout
+        .split(['\n', ROW_SEPARATOR])
+        .map(str::trim)
+        .filter(|row| !row.is_empty())
+        .collect();
+    if rows.len() != board.rows() {
+        return Err(LayoutError::RowCount {
+            expected: board.rows(),
+            found: rows.len(),
+        });
+    }
+
+    let mut blocked = Vec::new();
+    for (row, text) in rows.iter().enumerate() {
+        if text.chars().count() != board.cols() {
+            return Err(LayoutError::RowLength(row + 1));
+        }
+        for (col, c) in text.chars().enumerate() {
+            match c {
+                LAYOUT_OPEN => {}
+                SPACE_BLOCKED => blocked.push((row, col)),
+                c => return Err(LayoutError::InvalidCell(c)),
+            }
+        }
+    }
+    // Only change the board once the whole layout is known to be valid
+    for (row, col) in blocked {
+        board.block_space(row, col);
+    }
+    Ok(())
+}
+
+/// Reads a layout file and blocks the spaces it marks, as described for [`apply`].
+pub fn read(board: &mut GameBoard, path: impl AsRef<Path>) -> Result<(), LayoutError> {
+    let layout = std::fs::read_to_string(path).map_err(|e| LayoutError::Io(e.to_string()))?;
+    apply(board, &layout)
+}
+
+/// Blocks the given number of spaces on a board without pieces at random, using the shared, seedable source of randomness.
+///
+/// Each blocked space is stacked at the bottom of a random column, as if dropped in, and no column is ever blocked to the top.
+pub fn scatter(board: &mut GameBoard, count: usize) -> Result<(), LayoutError> {
+    let max = max_blockers(board.rows(), board.cols());
+    if count > max {
+        return Err(LayoutError::TooManyBlockers {
+            requested: count,
+            max,
+        });
+    }
+    for _ in 0..count {
+        // Columns with room for a blocked space below their top space
+        let open: Vec<usize> = (0..board.cols())
+            .filter(|col| !board.is_blocked(1, *col))
+            .collect();
+        let col = open[super::random::gen_range(0..=open.len() - 1)];
+        let row = (0..board.rows())
+            .rev()
+            .find(|row| !board.is_blocked(*row, col))
+            .expect("A column with room has an open space");
+        board.block_space(row, col);
+    }
+    Ok(())
+}
+
+/// Writes the blocked spaces of a board as a layout, with rows separated by `/`, or [`None`] if it has none.
+pub fn to_layout(board: &GameBoard) -> Option<String> {
+    if !board.has_blocked_spaces() {
+        return None;
+    }
+    let rows: Vec<String> = (0..board.rows())
+        .map(|row| {
+            (0..board.cols())
+                .map(|col| match board.is_blocked(row, col) {
+                    true => SPACE_BLOCKED,
+                    false => LAYOUT_OPEN,
+                })
+                .collect()
+        })
+        .collect();
+    Some(rows.join(&ROW_SEPARATOR.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::board::GamePiece;
+
+    #[test]
+    fn layouts_are_applied_and_written() {
+        let mut board = GameBoard::with_size(4, 5, 3).unwrap();
+        apply(&mut board, "\n.....\n..#..\n.....\n#...#\n").unwrap();
+        assert!(board.is_blocked(1, 2) && board.is_blocked(3, 0) && board.is_blocked(3, 4));
+        assert!(!board.is_blocked(3, 2));
+        assert_eq!(
+            Some("...../..#../...../#...#".to_string()),
+            to_layout(&board)
+        );
+        assert_eq!(None, to_layout(&GameBoard::default()));
+    }
+
+    #[test]
+    fn invalid_layouts_leave_the_board_alone() {
+        let board = GameBoard::with_size(4, 4, 3).unwrap();
+        let check = |layout: &str| {
+            let mut applied = board;
+            let result = apply(&mut applied, layout);
+            assert_eq!(board, applied);
+            result
+        };
+        assert_eq!(
+            Err(LayoutError::RowCount {
+                expected: 4,
+                found: 3
+            }),
+            check("#.../..../....")
+        );
+        assert_eq!(Err(LayoutError::RowLength(2)), check("..../.../..../...."));
+        assert_eq!(
+            Err(LayoutError::InvalidCell('R')),
+            check("..../..../..../R...")
+        );
+    }
+
+    #[test]
+    fn scattered_blockers_are_stacked() {
+        let mut board = GameBoard::default();
+        scatter(&mut board, 10).unwrap();
+
+        let blocked = (0..6)
+            .flat_map(|row| (0..7).map(move |col| (row, col)))
+            .filter(|(row, col)| board.is_blocked(*row, *col));
+        assert_eq!(10, blocked.clone().count());
+        for (row, col) in blocked {
+            assert!(row == 5 || board.is_blocked(row + 1, col));
+        }
+        assert!((1..=7).all(|col| board.has_room(col)));
+        assert!(board.insert_piece(GamePiece::Red, 1).is_ok());
+
+        assert_eq!(
+            Err(LayoutError::TooManyBlockers {
+                requested: 11,
+                max: 10
+            }),
+            scatter(&mut GameBoard::default(), 11)
+        );
+    }
+}
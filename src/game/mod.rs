@@ -1,9 +1,33 @@
 /// Contains components and logic for the game board and interactions.
 pub mod board;
+/// Neutral blocked spaces, placed from layouts or at random.
+pub mod layout;
+/// Text notation for describing whole board positions.
+pub mod notation;
+/// Shared source of randomness, which can be seeded to make games repeatable.
+pub mod random;
+/// Recording, saving and loading of played games.
+pub mod record;
+/// The rules that games can be played by.
+pub mod rules;
 /// Game state components and logic.
 pub mod state;
+/// Fixed-size tables of search results for positions, keyed by their hashes.
+pub mod transposition;
+/// Random keys for hashing positions.
+mod zobrist;
+
+use rules::Ruleset;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter, Result as FmtResult};
 
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "lowercase")
+)]
 pub enum GameMode {
     #[default]
     Ai,
@@ -11,6 +35,86 @@ pub enum GameMode {
     Online,
 }
 
+/// Sets of rules that games can be played by, each with its own [`Ruleset`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Variant {
+    /// See [`rules::Classic`].
+    #[default]
+    Classic,
+    /// See [`rules::PopOut`].
+    ///
+    /// Reaching the same position three times is a draw, as pieces can be popped out and dropped back in forever.
+    PopOut,
+    /// See [`rules::Cylinder`].
+    Cylinder,
+    /// See [`rules::Misere`].
+    Misere,
+}
+
+impl Variant {
+    pub const ALL: [Self; 4] = [Self::Classic, Self::PopOut, Self::Cylinder, Self::Misere];
+
+    /// The rules that games of this variant are played by.
+    pub fn rules(&self) -> &'static dyn Ruleset {
+        match self {
+            Self::Classic => &rules::Classic,
+            Self::PopOut => &rules::PopOut,
+            Self::Cylinder => &rules::Cylinder,
+            Self::Misere => &rules::Misere,
+        }
+    }
+}
+
+impl Display for Variant {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Classic => write!(f, "Classic"),
+            Self::PopOut => write!(f, "PopOut"),
+            Self::Cylinder => write!(f, "Cylinder"),
+            Self::Misere => write!(f, "Misere"),
+        }
+    }
+}
+
+/// How a game between more than two players is decided.
+///
+/// Two-player games are always decided by their [`Variant`].
+/// Neither way hands out places: a game has a single winner, or is drawn if the board fills first.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum FreeForAll {
+    /// The first player to complete a line wins.
+    #[default]
+    FirstToConnect,
+    /// Last player standing: completing a line knocks a player out rather than winning,
+    /// leaving their pieces on the board, and the last player left wins without needing a line of their own.
+    ///
+    /// Every line is something to avoid, much as in [`Variant::Misere`], until only one player is left.
+    Elimination,
+}
+
+impl FreeForAll {
+    pub const ALL: [Self; 2] = [Self::FirstToConnect, Self::Elimination];
+}
+
+impl Display for FreeForAll {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::FirstToConnect => write!(f, "First to connect"),
+            Self::Elimination => write!(f, "Elimination"),
+        }
+    }
+}
+
 impl TryFrom<i8> for GameMode {
     type Error = ();
 
@@ -23,3 +127,38 @@ impl TryFrom<i8> for GameMode {
         }
     }
 }
+
+impl Display for GameMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Ai => write!(f, "AI"),
+            Self::Local => write!(f, "Local"),
+            Self::Online => write!(f, "Online"),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::{FreeForAll, GameMode, Variant};
+
+    #[test]
+    fn serde_round_trip() {
+        for mode in [GameMode::Ai, GameMode::Local, GameMode::Online] {
+            let json = serde_json::to_string(&mode).unwrap();
+            assert_eq!(mode, serde_json::from_str(&json).unwrap());
+        }
+        assert_eq!(
+            r#""online""#,
+            serde_json::to_string(&GameMode::Online).unwrap()
+        );
+        assert_eq!(
+            Variant::PopOut,
+            serde_json::from_str(r#""popout""#).unwrap()
+        );
+        assert_eq!(
+            FreeForAll::FirstToConnect,
+            serde_json::from_str(r#""firsttoconnect""#).unwrap()
+        );
+    }
+}
@@ -0,0 +1,591 @@
+use crate::game::board::{
+    GameBoard, GamePiece, SizeError, MAX_PLAYERS, MIN_PLAYERS, SPACE_BLOCKED, WIN_LENGTH,
+};
+use crate::game::state::GameState;
+use crate::game::FreeForAll;
+#[cfg(feature = "serde")]
+use crate::game::Variant;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+const NOTATION_EMPTY: char = '.';
+const ROW_SEPARATOR: char = '/';
+/// Ends the field giving the number of players, e.g. `3p`.
+const PLAYERS_SUFFIX: char = 'p';
+/// Field marking a game between more than two players as decided by elimination.
+const ELIMINATION: &str = "elimination";
+
+/// Occurs when a position string cannot be turned into a valid [`GameState`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum NotationError {
+    /// The position is not made up of a board, a side to move, an optional win length and optional players.
+    MissingField,
+    /// The board has an unsupported size or win length.
+    Size(SizeError),
+    /// A row (counted from the top, starting at 1) does not have the same number of cells as the first row.
+    RowLength(usize),
+    /// The win length is not a number.
+    InvalidWinLength(String),
+    /// A cell is not one of the recognised piece or empty characters.
+    InvalidCell(char),
+    /// The number of players is not between two and four.
+    InvalidPlayers(String),
+    /// The side to move is not a recognised piece, or not one taking part.
+    InvalidSideToMove(String),
+    /// A piece in the given column (starting at 1) is resting on an empty space.
+    FloatingPiece(usize),
+    /// A piece in the given column (starting at 1) is beneath a blocked space, where no piece can reach.
+    BlockedPiece(usize),
+    /// The number of pieces each player has, in turn order, cannot arise from taking turns.
+    PieceCount(Vec<usize>),
+    /// The piece counts show that the other player should be the one to move.
+    WrongSideToMove(GamePiece),
+    /// More than one player has a winning line, which cannot happen in a real game unless players are eliminated.
+    MultipleWinners,
+}
+
+impl Display for NotationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::MissingField => write!(f, "Position must contain a board and a side to move."),
+            Self::Size(e) => write!(f, "{e}"),
+            Self::RowLength(row) => {
+                write!(f, "Row {row} must have as many cells as the first row.")
+            }
+            Self::InvalidWinLength(s) => write!(f, "'{s}' is not a valid win length."),
+            Self::InvalidCell(c) => write!(f, "'{c}' is not a valid board cell."),
+            Self::InvalidPlayers(s) => write!(f, "'{s}' is not a valid number of players."),
+            Self::InvalidSideToMove(s) => write!(f, "'{s}' is not a valid side to move."),
+            Self::FloatingPiece(col) => write!(f, "Column {col} has a piece above an empty space."),
+            Self::BlockedPiece(col) => {
+                write!(f, "Column {col} has a piece beneath a blocked space.")
+            }
+            Self::PieceCount(counts) => {
+                let counts: Vec<String> = GamePiece::ALL
+                    .iter()
+                    .zip(counts)
+                    .map(|(piece, count)| format!("{piece} {count}"))
+                    .collect();
+                write!(
+                    f,
+                    "Pieces {} cannot come from players taking turns in order.",
+                    counts.join(", ")
+                )
+            }
+            Self::WrongSideToMove(p) => write!(f, "{p} cannot be the next to move."),
+            Self::MultipleWinners => write!(f, "Only one player can have a winning line."),
+        }
+    }
+}
+
+/// Writes a [`GameState`] as a position string.
+///
+/// Rows are listed from the top of the board and separated by `/`, with `R`, `Y` and `.` for red, yellow and empty spaces, and `#` for blocked ones.
+/// The board is followed by a space and the piece of the player to move next, e.g. `......./......./......./......./......./...R... Y`.
+/// The board's size is given by its rows, and a win length other than four is written as a number, e.g. `... R 5`.
+/// Games between more than two players end with their number, using `G` and `B` for green and blue, and `elimination` if they are decided that way, e.g. `... G 3p elimination`.
+pub fn to_notation(state: &GameState) -> String {
+    let board = state.get_board();
+    let mut notation = format!(
+        "{} {}",
+        board_rows(board).join(&ROW_SEPARATOR.to_string()),
+        state.get_next_player()
+    );
+    if board.win_length() != WIN_LENGTH {
+        notation.push_str(&format!(" {}", board.win_length()));
+    }
+    if state.player_count() > MIN_PLAYERS {
+        notation.push_str(&format!(" {}{PLAYERS_SUFFIX}", state.player_count()));
+        if state.get_free_for_all() == FreeForAll::Elimination {
+            notation.push_str(&format!(" {ELIMINATION}"));
+        }
+    }
+    notation
+}
+
+/// Reads a position string as written by [`to_notation`], to be played by the classic rules.
+///
+/// Returns a [`NotationError`] if the string is malformed or describes a position that cannot be reached by legal play.
+pub fn from_notation(notation: &str) -> Result<GameState, NotationError> {
+    let mut fields = notation.split_whitespace().peekable();
+    let (Some(board_field), Some(side_field)) = (fields.next(), fields.next()) else {
+        return Err(NotationError::MissingField);
+    };
+    let is_players = |field: &&str| field.ends_with(PLAYERS_SUFFIX);
+
+    let win_length = match fields.next_if(|field| !is_players(field) && *field != ELIMINATION) {
+        Some(win) => win
+            .parse()
+            .map_err(|_| NotationError::InvalidWinLength(win.to_string()))?,
+        None => WIN_LENGTH,
+    };
+    let players = match fields.next_if(is_players) {
+        Some(players) => players
+            .trim_end_matches(PLAYERS_SUFFIX)
+            .parse()
+            .ok()
+            .filter(|n| (MIN_PLAYERS..=MAX_PLAYERS).contains(n))
+            .ok_or_else(|| NotationError::InvalidPlayers(players.to_string()))?,
+        None => MIN_PLAYERS,
+    };
+    let free_for_all = match fields.next_if_eq(&ELIMINATION) {
+        Some(_) => FreeForAll::Elimination,
+        None => FreeForAll::FirstToConnect,
+    };
+    if fields.next().is_some() {
+        return Err(NotationError::MissingField);
+    }
+
+    let rows: Vec<&str> = board_field.split(ROW_SEPARATOR).collect();
+    let board = board_from_rows(&rows, win_length)?;
+
+    let mut side_chars = side_field.chars();
+    let next_player = match (side_chars.next(), side_chars.next()) {
+        (Some(c), None) => GamePiece::try_from(c).ok(),
+        _ => None,
+    }
+    .filter(|piece| piece.index() < players)
+    .ok_or_else(|| NotationError::InvalidSideToMove(side_field.to_string()))?;
+
+    let state = GameState::with_board(board, next_player).with_players(players, free_for_all);
+    check_pieces(&state)?;
+    check_position(&state)?;
+    Ok(state)
+}
+
+/// Writes each row of a board from the top, with `R`, `Y`, `.` and `#` for red, yellow, empty and blocked spaces.
+fn board_rows(board: &GameBoard) -> Vec<String> {
+    (0..board.rows())
+        .map(|row| {
+            (0..board.cols())
+                .map(|col| match board.get_piece(row, col) {
+                    Some(p) => p.to_string(),
+                    None if board.is_blocked(row, col) => SPACE_BLOCKED.to_string(),
+                    None => NOTATION_EMPTY.to_string(),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// A cell of a row of notation.
+#[derive(Clone, Copy)]
+enum Cell {
+    Empty,
+    Piece(GamePiece),
+    Blocked,
+}
+
+/// Reads a board from its rows, as written by [`board_rows`], checking that every piece rests on another piece or a blocked space.
+fn board_from_rows(
+    rows: &[impl AsRef<str>],
+    win_length: usize,
+) -> Result<GameBoard, NotationError> {
+    let cols = rows.first().map_or(0, |row| row.as_ref().chars().count());
+    let mut board =
+        GameBoard::with_size(rows.len(), cols, win_length).map_err(NotationError::Size)?;
+
+    let mut cells = Vec::with_capacity(rows.len());
+    for (row, text) in rows.iter().enumerate() {
+        let text = text.as_ref();
+        if text.chars().count() != cols {
+            return Err(NotationError::RowLength(row + 1));
+        }
+        let row_cells = text
+            .chars()
+            .map(|c| match c {
+                NOTATION_EMPTY => Ok(Cell::Empty),
+                SPACE_BLOCKED => Ok(Cell::Blocked),
+                c => GamePiece::try_from(c)
+                    .map(Cell::Piece)
+                    .map_err(|_| NotationError::InvalidCell(c)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        cells.push(row_cells);
+    }
+
+    for (row, row_cells) in cells.iter().enumerate() {
+        for (col, cell) in row_cells.iter().enumerate() {
+            if let Cell::Blocked = cell {
+                board.block_space(row, col);
+            }
+        }
+    }
+    // Build the board from the bottom up so that pieces are only ever placed through regular inserts
+    for col in 0..cols {
+        let mut reached_top = false;
+        let mut has_pieces = false;
+        for row_cells in cells.iter().rev() {
+            match row_cells[col] {
+                Cell::Piece(_) if reached_top => return Err(NotationError::FloatingPiece(col + 1)),
+                Cell::Piece(piece) => {
+                    has_pieces = true;
+                    board
+                        .insert_piece(piece, col + 1)
+                        .expect("Column cannot overflow while following the grid")
+                }
+                Cell::Blocked if has_pieces => return Err(NotationError::BlockedPiece(col + 1)),
+                Cell::Blocked => reached_top = false,
+                Cell::Empty => reached_top = true,
+            }
+        }
+    }
+    Ok(board)
+}
+
+/// Checks that the board only has pieces of the players taking part.
+fn check_pieces(state: &GameState) -> Result<(), NotationError> {
+    let board = state.get_board();
+    for piece in &GamePiece::ALL[state.player_count()..] {
+        if count_pieces(board, *piece) > 0 {
+            return Err(NotationError::InvalidCell(piece.to_string().remove(0)));
+        }
+    }
+    Ok(())
+}
+
+fn count_pieces(board: &GameBoard, piece: GamePiece) -> usize {
+    (0..board.rows())
+        .flat_map(|row| (0..board.cols()).map(move |col| (row, col)))
+        .filter(|(row, col)| board.get_piece(*row, *col) == Some(piece))
+        .count()
+}
+
+/// Checks that the position could be reached by legal play, with the player to move next.
+///
+/// Taking turns in order, each player from the one to move round to the one who just moved has as many pieces as the one before or one more.
+/// Players knocked out of an elimination game stop taking turns, so only those still playing are counted.
+fn check_position(state: &GameState) -> Result<(), NotationError> {
+    let (board, next_player) = (state.get_board(), *state.get_next_player());
+    if state.is_eliminated(next_player) {
+        return Err(NotationError::WrongSideToMove(next_player));
+    }
+    let counts: Vec<usize> = state
+        .all_players()
+        .iter()
+        .map(|piece| count_pieces(board, *piece))
+        .collect();
+    let active: Vec<GamePiece> = state.active_players().collect();
+    let (min, max) = active
+        .iter()
+        .map(|piece| counts[piece.index()])
+        .fold((usize::MAX, 0), |(min, max), n| (min.min(n), max.max(n)));
+    // A knocked-out player may have played once more than those still playing
+    if max - min > 1 || counts.iter().any(|n| *n > max + 1) {
+        return Err(NotationError::PieceCount(counts));
+    }
+    let start = active
+        .iter()
+        .position(|piece| *piece == next_player)
+        .expect("The player to move is still playing");
+    let in_turn_order = (1..active.len()).all(|i| {
+        let count = |i: usize| counts[active[(start + i) % active.len()].index()];
+        count(i - 1) <= count(i)
+    });
+    if !in_turn_order {
+        return Err(NotationError::WrongSideToMove(next_player));
+    }
+
+    let winners = state
+        .all_players()
+        .iter()
+        .filter(|piece| board.has_line(**piece))
+        .count();
+    if winners > 1 && state.get_free_for_all() != FreeForAll::Elimination {
+        return Err(NotationError::MultipleWinners);
+    }
+    Ok(())
+}
+
+/// How a [`GameBoard`] is serialized: its rows from the top, as in position strings, its win length, and whether it wraps around.
+///
+/// For example, in JSON: `{"rows":["....",...,".RY."],"win_length":3}`.
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+pub(crate) struct BoardRows {
+    rows: Vec<String>,
+    win_length: usize,
+    #[serde(default, skip_serializing_if = "is_false")]
+    wraps: bool,
+}
+
+#[cfg(feature = "serde")]
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+#[cfg(feature = "serde")]
+impl From<GameBoard> for BoardRows {
+    fn from(board: GameBoard) -> Self {
+        Self {
+            rows: board_rows(&board),
+            win_length: board.win_length(),
+            wraps: board.wraps(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<BoardRows> for GameBoard {
+    type Error = NotationError;
+
+    fn try_from(board: BoardRows) -> Result<Self, Self::Error> {
+        board_from_rows(&board.rows, board.win_length).map(|b| b.with_wrapping(board.wraps))
+    }
+}
+
+/// How a [`GameState`] is serialized: its board, the player to move next and, unless they are classic, the rules.
+///
+/// Games between more than two players also give how many there are and how the game is decided.
+///
+/// For example, in JSON: `{"board":{"rows":[...],"win_length":4},"next_player":"Y"}`.
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Position {
+    board: GameBoard,
+    next_player: GamePiece,
+    #[serde(default, skip_serializing_if = "is_classic")]
+    variant: Variant,
+    #[serde(default = "two_players", skip_serializing_if = "is_two_players")]
+    players: usize,
+    #[serde(default, skip_serializing_if = "is_first_to_connect")]
+    free_for_all: FreeForAll,
+}
+
+#[cfg(feature = "serde")]
+fn is_classic(variant: &Variant) -> bool {
+    *variant == Variant::Classic
+}
+
+#[cfg(feature = "serde")]
+fn two_players() -> usize {
+    MIN_PLAYERS
+}
+
+#[cfg(feature = "serde")]
+fn is_two_players(players: &usize) -> bool {
+    *players == MIN_PLAYERS
+}
+
+#[cfg(feature = "serde")]
+fn is_first_to_connect(free_for_all: &FreeForAll) -> bool {
+    *free_for_all == FreeForAll::FirstToConnect
+}
+
+#[cfg(feature = "serde")]
+impl From<GameState> for Position {
+    fn from(state: GameState) -> Self {
+        Self {
+            board: *state.get_board(),
+            next_player: *state.get_next_player(),
+            variant: state.get_variant(),
+            players: state.player_count(),
+            free_for_all: state.get_free_for_all(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<Position> for GameState {
+    type Error = NotationError;
+
+    fn try_from(position: Position) -> Result<Self, Self::Error> {
+        let players = position.players;
+        if !(MIN_PLAYERS..=position.variant.rules().max_players()).contains(&players)
+            || position.next_player.index() >= players
+        {
+            return Err(NotationError::InvalidPlayers(players.to_string()));
+        }
+        let state = GameState::with_board(position.board, position.next_player)
+            .with_variant(position.variant)
+            .with_players(players, position.free_for_all);
+        check_pieces(&state)?;
+        // Popping pieces out changes how many each player has, so only positions without pops can be checked
+        if !state.rules().can_pop() {
+            check_position(&state)?;
+        }
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::state::EndgameType;
+
+    fn play(first: GamePiece, moves: &[usize]) -> GameState {
+        let mut state = GameState::with_board(GameBoard::default(), first);
+        for col in moves {
+            let player = *state.get_next_player();
+            state.get_board_mut().insert_piece(player, *col).unwrap();
+            state.toggle_player();
+        }
+        state
+    }
+
+    #[test]
+    fn empty_board_round_trip() {
+        let notation = "......./......./......./......./......./....... R";
+        let state = from_notation(notation).unwrap();
+        assert_eq!(
+            GameBoard::default().to_string(),
+            state.get_board().to_string()
+        );
+        assert_eq!(notation, to_notation(&state));
+    }
+
+    #[test]
+    fn played_game_round_trip() {
+        let played = play(GamePiece::Yellow, &[4, 4, 3, 5, 3, 1, 7]);
+        let notation = to_notation(&played);
+        assert_eq!(
+            "......./......./......./......./..YR.../R.YYR.Y R",
+            notation
+        );
+
+        let parsed = from_notation(&notation).unwrap();
+        assert_eq!(
+            played.get_board().to_string(),
+            parsed.get_board().to_string()
+        );
+        assert_eq!(played.get_next_player(), parsed.get_next_player());
+    }
+
+    #[test]
+    fn custom_size_round_trip() {
+        let notation = "...../...../...../..Y../.RR.. Y 3";
+        let state = from_notation(notation).unwrap();
+        assert_eq!(5, state.get_board().rows());
+        assert_eq!(5, state.get_board().cols());
+        assert_eq!(3, state.get_board().win_length());
+        assert_eq!(notation, to_notation(&state));
+        assert_eq!(
+            vec![1, 4],
+            state.get_board().winning_columns(GamePiece::Red)
+        );
+    }
+
+    #[test]
+    fn accepts_finished_game() {
+        let played = play(GamePiece::Red, &[1, 2, 1, 2, 1, 2, 1]);
+        let parsed = from_notation(&to_notation(&played)).unwrap();
+        assert_eq!(Some(GamePiece::Red), parsed.get_board().is_winner());
+    }
+
+    #[test]
+    fn full_board_is_a_draw() {
+        let notation = "RRRYRRR/YYYRYYY/RRRYRRR/YYYRYYY/RRRYRRR/YYYRYYY R";
+        let state = from_notation(notation).unwrap();
+        assert!(state.get_board().is_full());
+        assert_eq!(EndgameType::Full, state.check_endgame());
+        assert_eq!(notation, to_notation(&state));
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        assert_eq!(
+            Err(NotationError::MissingField),
+            from_notation("......./......./......./......./......./.......")
+        );
+        assert_eq!(
+            Err(NotationError::Size(SizeError::Rows(3))),
+            from_notation("......./......./....... R")
+        );
+        assert_eq!(
+            Err(NotationError::Size(SizeError::WinLength(8))),
+            from_notation("......./......./......./......./......./....... R 8")
+        );
+        assert_eq!(
+            Err(NotationError::InvalidWinLength("four".to_string())),
+            from_notation("......./......./......./......./......./....... R four")
+        );
+        assert_eq!(
+            Err(NotationError::RowLength(6)),
+            from_notation("......./......./......./......./......./...... R")
+        );
+        assert_eq!(
+            Err(NotationError::InvalidCell('X')),
+            from_notation("......./......./......./......./......./...X... R")
+        );
+        assert_eq!(
+            Err(NotationError::InvalidSideToMove("B".to_string())),
+            from_notation("......./......./......./......./......./....... B")
+        );
+    }
+
+    #[test]
+    fn rejects_floating_piece() {
+        assert_eq!(
+            Err(NotationError::FloatingPiece(3)),
+            from_notation("......./......./......./......./..R..../...Y... R")
+        );
+    }
+
+    #[test]
+    fn blocked_spaces_round_trip() {
+        let notation = "......./......./..R..../..#...Y/..#...#/R.#...# Y";
+        let state = from_notation(notation).unwrap();
+        let board = state.get_board();
+        assert!(board.is_blocked(3, 2) && board.is_blocked(5, 6));
+        assert_eq!(Some(GamePiece::Red), board.get_piece(2, 2));
+        assert_eq!(notation, to_notation(&state));
+
+        assert_eq!(
+            Err(NotationError::BlockedPiece(3)),
+            from_notation("......./......./......./..#..../......./..R.... Y")
+        );
+    }
+
+    #[test]
+    fn rejects_impossible_counts() {
+        assert_eq!(
+            Err(NotationError::PieceCount(vec![3, 1])),
+            from_notation("......./......./......./......./...R.../.RRY... Y")
+        );
+        assert_eq!(
+            Err(NotationError::WrongSideToMove(GamePiece::Red)),
+            from_notation("......./......./......./......./......./...R... R")
+        );
+    }
+
+    #[test]
+    fn rejects_multiple_winners() {
+        assert_eq!(
+            Err(NotationError::MultipleWinners),
+            from_notation("......./......./R.....Y/R.....Y/R.....Y/R.....Y R")
+        );
+    }
+
+    #[test]
+    fn free_for_all_round_trip() {
+        let notation = "...../...../...../...../RYGR. Y 3 3p";
+        let state = from_notation(notation).unwrap();
+        assert_eq!(3, state.player_count());
+        assert_eq!(Some(GamePiece::Green), state.get_board().get_piece(4, 2));
+        assert_eq!(notation, to_notation(&state));
+
+        let eliminated = "...../...../...../G..../RRRYG Y 3 3p elimination";
+        let state = from_notation(eliminated).unwrap();
+        assert!(state.is_eliminated(GamePiece::Red));
+        assert_eq!(eliminated, to_notation(&state));
+
+        assert_eq!(
+            Err(NotationError::WrongSideToMove(GamePiece::Green)),
+            from_notation("...../...../...../...../RYGR. G 3 3p")
+        );
+        assert_eq!(
+            Err(NotationError::InvalidCell('B')),
+            from_notation("...../...../...../...../RYB.. R 3 3p")
+        );
+        assert_eq!(
+            Err(NotationError::InvalidSideToMove("B".to_string())),
+            from_notation("...../...../...../...../RYG.. B 3 3p")
+        );
+        assert_eq!(
+            Err(NotationError::InvalidPlayers("5p".to_string())),
+            from_notation("...../...../...../...../..... R 3 5p")
+        );
+    }
+}
@@ -0,0 +1,59 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::ops::RangeInclusive;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// Generator shared by every random choice, started from an unpredictable seed unless one is set.
+static GENERATOR: Mutex<Option<SplitMix64>> = Mutex::new(None);
+
+/// A small, fast generator, which is plenty for choices such as who moves first.
+///
+/// See <https://prng.di.unimi.it/splitmix64.c>.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(GOLDEN_GAMMA);
+        mix(self.0)
+    }
+}
+
+/// Step between the states of [`SplitMix64`].
+pub(super) const GOLDEN_GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// Scrambles the bits of a [`SplitMix64`] state into its output, so that consecutive states give unrelated numbers.
+pub(super) const fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Makes every later random choice follow from the given seed.
+pub fn seed(seed: u64) {
+    *GENERATOR.lock().unwrap_or_else(|e| e.into_inner()) = Some(SplitMix64(seed));
+}
+
+/// Picks a number in the given range, using the seeded generator if there is one.
+pub fn gen_range(range: RangeInclusive<usize>) -> usize {
+    let mut generator = GENERATOR.lock().unwrap_or_else(|e| e.into_inner());
+    let generator = generator.get_or_insert_with(|| SplitMix64(unpredictable_seed()));
+    let span = (range.end() - range.start()) as u64 + 1;
+    range.start() + (generator.next() % span) as usize
+}
+
+/// Puts the items in a random order, using the seeded generator if there is one.
+pub fn shuffle<T>(items: &mut [T]) {
+    for i in (1..items.len()).rev() {
+        items.swap(i, gen_range(0..=i));
+    }
+}
+
+/// A seed that differs between runs, from the randomly keyed hasher that the standard library uses for hash maps.
+fn unpredictable_seed() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    if let Ok(since_epoch) = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+        hasher.write_u128(since_epoch.as_nanos());
+    }
+    hasher.finish()
+}
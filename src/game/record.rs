@@ -0,0 +1,888 @@
+use crate::game::board::{
+    GameBoard, GamePiece, InsertError, GRID_COLS, GRID_ROWS, MAX_PLAYERS, MIN_PLAYERS, WIN_LENGTH,
+};
+use crate::game::layout;
+use crate::game::state::{EndgameType, GameState, Move};
+use crate::game::{FreeForAll, Variant};
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::io::Write;
+use std::path::Path;
+
+/// File that game records are saved to and loaded from by default.
+pub const DEFAULT_RECORD_FILE: &str = "fourstack_games.rec";
+
+// Standard header keys
+pub const HEADER_RED: &str = "Red";
+pub const HEADER_YELLOW: &str = "Yellow";
+pub const HEADER_GREEN: &str = "Green";
+pub const HEADER_BLUE: &str = "Blue";
+pub const HEADER_MODE: &str = "Mode";
+pub const HEADER_LEVEL: &str = "Level";
+pub const HEADER_DATE: &str = "Date";
+pub const HEADER_FIRST: &str = "First";
+pub const HEADER_RESULT: &str = "Result";
+/// Board size as columns by rows, e.g. `9x7`, only written for non-standard boards.
+pub const HEADER_SIZE: &str = "Size";
+/// Pieces in a row needed to win, only written when it isn't four.
+pub const HEADER_WIN_LENGTH: &str = "WinLength";
+/// How the game ended, only written when it wasn't decided on the board, e.g. `Forfeit`.
+pub const HEADER_TERMINATION: &str = "Termination";
+/// Rules the game was played by, e.g. `PopOut`, only written when they aren't the classic rules.
+pub const HEADER_VARIANT: &str = "Variant";
+/// Blocked spaces of the board as a layout, e.g. `...../...../..#../#...#`, only written when there are any.
+pub const HEADER_BLOCKED: &str = "Blocked";
+/// Number of players, only written when there are more than two.
+pub const HEADER_PLAYERS: &str = "Players";
+/// How a game between more than two players was decided, e.g. `Elimination`, only written for such games.
+pub const HEADER_FREE_FOR_ALL: &str = "FreeForAll";
+/// Number of times a position must be reached for the game to be drawn by repetition.
+const REPETITION_LIMIT: usize = 3;
+
+const RESULT_RED: &str = "Red";
+const RESULT_YELLOW: &str = "Yellow";
+const RESULT_GREEN: &str = "Green";
+const RESULT_BLUE: &str = "Blue";
+const RESULT_DRAW: &str = "Draw";
+const RESULT_ONGOING: &str = "*";
+/// Shown for header values that are not known, e.g. an unnamed player.
+pub const UNKNOWN_VALUE: &str = "?";
+
+/// Occurs when reading or replaying a game record.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RecordError {
+    /// A header line is not of the form `[Key "Value"]`.
+    InvalidHeader(String),
+    /// A token in the move list is not a move, move number, comment or result.
+    InvalidToken(String),
+    /// A comment was opened with `{` but never closed.
+    UnclosedComment,
+    /// A game's move list does not end with a result.
+    MissingResult,
+    /// The `First` header is missing or is not a valid piece.
+    InvalidFirstPlayer,
+    /// The `Size`, `WinLength` or `Blocked` headers do not describe a supported board.
+    InvalidBoard,
+    /// The `Variant` header does not name a known set of rules.
+    InvalidVariant,
+    /// The `Players` or `FreeForAll` headers do not describe a game that can be played.
+    InvalidPlayers,
+    /// The move with the given number (starting at 1) cannot be played.
+    IllegalMove(usize, InsertError),
+}
+
+impl Display for RecordError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::InvalidHeader(h) => write!(f, "Invalid header: {h}"),
+            Self::InvalidToken(t) => write!(f, "Unexpected '{t}' in move list."),
+            Self::UnclosedComment => write!(f, "Comment is missing a closing '}}'."),
+            Self::MissingResult => write!(f, "Game record does not end with a result."),
+            Self::InvalidFirstPlayer => write!(f, "Game record has no valid starting player."),
+            Self::InvalidBoard => write!(f, "Game record has an invalid board size."),
+            Self::InvalidVariant => write!(f, "Game record has unknown rules."),
+            Self::InvalidPlayers => write!(f, "Game record has an invalid number of players."),
+            Self::IllegalMove(n, e) => write!(f, "Move {n} is illegal: {e}"),
+        }
+    }
+}
+
+/// A single move within a [`GameRecord`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordedMove {
+    pub game_move: Move,
+    /// Optional annotation for the move.
+    pub comment: Option<String>,
+}
+
+/// A finished or in-progress game: descriptive headers followed by the list of moves played.
+///
+/// Records are written in a PGN-like text format, for example:
+///
+/// ```text
+/// [Red "Alice"]
+/// [Yellow "Bob"]
+/// [First "R"]
+/// [Result "Red"]
+///
+/// 1. 4 4 2. 3 {Threatens both sides} 5 3. 2 Red
+/// ```
+///
+/// Pops are written with a `p` before the column, e.g. `p4`.
+/// Within header values and comments, `\\` escapes a backslash and the `"` or `}` that would otherwise end them, and `\n` and `\r` stand for line breaks.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameRecord {
+    headers: Vec<(String, String)>,
+    moves: Vec<RecordedMove>,
+}
+
+impl GameRecord {
+    /// Creates an empty record for a game starting with the given player, dated today.
+    pub fn new(first_player: GamePiece) -> Self {
+        let mut record = Self {
+            headers: Vec::new(),
+            moves: Vec::new(),
+        };
+        record.set_header(HEADER_RED, UNKNOWN_VALUE);
+        record.set_header(HEADER_YELLOW, UNKNOWN_VALUE);
+        record.set_header(HEADER_DATE, today());
+        record.set_header(HEADER_FIRST, first_player.to_string());
+        record.set_header(HEADER_RESULT, RESULT_ONGOING);
+        record
+    }
+
+    /// Gets the value of a header, if present.
+    pub fn get_header(&self, key: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Sets a header value, replacing any existing value while keeping header order.
+    pub fn set_header(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        let (key, value) = (key.into(), value.into());
+        match self.headers.iter_mut().find(|(k, _)| *k == key) {
+            Some(header) => header.1 = value,
+            None => self.headers.push((key, value)),
+        }
+    }
+
+    pub fn get_headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    pub fn get_moves(&self) -> &[RecordedMove] {
+        &self.moves
+    }
+
+    /// Appends a move without a comment.
+    pub fn push_move(&mut self, game_move: Move) {
+        self.moves.push(RecordedMove {
+            game_move,
+            comment: None,
+        });
+    }
+
+    /// Removes the most recent move, if any.
+    pub fn pop_move(&mut self) -> Option<RecordedMove> {
+        self.moves.pop()
+    }
+
+    /// Attaches a comment to the most recent move.
+    pub fn comment_last_move(&mut self, comment: impl Into<String>) {
+        if let Some(m) = self.moves.last_mut() {
+            m.comment = Some(comment.into());
+        }
+    }
+
+    /// Records the outcome of the game in the `Result` header.
+    pub fn set_result(&mut self, endgame: &EndgameType) {
+        let result = match endgame {
+            EndgameType::Win(GamePiece::Red) => RESULT_RED,
+            EndgameType::Win(GamePiece::Yellow) => RESULT_YELLOW,
+            EndgameType::Win(GamePiece::Green) => RESULT_GREEN,
+            EndgameType::Win(GamePiece::Blue) => RESULT_BLUE,
+            EndgameType::Full | EndgameType::Repetition => RESULT_DRAW,
+            EndgameType::None => RESULT_ONGOING,
+        };
+        self.set_header(HEADER_RESULT, result);
+    }
+
+    /// The player who made the first move, from the `First` header.
+    pub fn get_first_player(&self) -> Result<GamePiece, RecordError> {
+        let mut chars = self
+            .get_header(HEADER_FIRST)
+            .ok_or(RecordError::InvalidFirstPlayer)?
+            .chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => GamePiece::try_from(c).map_err(|_| RecordError::InvalidFirstPlayer),
+            _ => Err(RecordError::InvalidFirstPlayer),
+        }
+    }
+
+    /// Records the size, win length and blocked spaces of the board being played on, if they differ from a standard board.
+    pub fn set_board(&mut self, board: &GameBoard) {
+        if (board.rows(), board.cols()) != (GRID_ROWS, GRID_COLS) {
+            self.set_header(HEADER_SIZE, format!("{}x{}", board.cols(), board.rows()));
+        }
+        if board.win_length() != WIN_LENGTH {
+            self.set_header(HEADER_WIN_LENGTH, board.win_length().to_string());
+        }
+        if let Some(blocked) = layout::to_layout(board) {
+            self.set_header(HEADER_BLOCKED, blocked);
+        }
+    }
+
+    /// The empty board the game was played on, from the `Size`, `WinLength` and `Blocked` headers.
+    pub fn get_board(&self) -> Result<GameBoard, RecordError> {
+        let (cols, rows) = match self.get_header(HEADER_SIZE) {
+            Some(size) => {
+                let (cols, rows) = size.split_once('x').ok_or(RecordError::InvalidBoard)?;
+                (cols.parse().ok(), rows.parse().ok())
+            }
+            None => (Some(GRID_COLS), Some(GRID_ROWS)),
+        };
+        let win_length = match self.get_header(HEADER_WIN_LENGTH) {
+            Some(win) => win.parse().ok(),
+            None => Some(WIN_LENGTH),
+        };
+        let mut board = match (rows, cols, win_length) {
+            (Some(rows), Some(cols), Some(win)) => {
+                GameBoard::with_size(rows, cols, win).map_err(|_| RecordError::InvalidBoard)?
+            }
+            _ => return Err(RecordError::InvalidBoard),
+        };
+        if let Some(blocked) = self.get_header(HEADER_BLOCKED) {
+            layout::apply(&mut board, blocked).map_err(|_| RecordError::InvalidBoard)?;
+        }
+        Ok(board)
+    }
+
+    /// Records the rules the game is played by, if they are not the classic rules.
+    pub fn set_variant(&mut self, variant: Variant) {
+        if variant != Variant::Classic {
+            self.set_header(HEADER_VARIANT, variant.to_string());
+        }
+    }
+
+    /// The rules the game was played by, from the `Variant` header.
+    pub fn get_variant(&self) -> Result<Variant, RecordError> {
+        match self.get_header(HEADER_VARIANT) {
+            Some(name) => Variant::ALL
+                .into_iter()
+                .find(|v| v.to_string().eq_ignore_ascii_case(name))
+                .ok_or(RecordError::InvalidVariant),
+            None => Ok(Variant::Classic),
+        }
+    }
+
+    /// Records how many players take part and how the game is decided, if there are more than two.
+    ///
+    /// Also adds an unknown name for each player after Red and Yellow, to be filled in with [`player_header`].
+    pub fn set_players(&mut self, players: usize, free_for_all: FreeForAll) {
+        if players > MIN_PLAYERS {
+            self.set_header(HEADER_PLAYERS, players.to_string());
+            self.set_header(HEADER_FREE_FOR_ALL, free_for_all.to_string());
+            for piece in &GamePiece::ALL[MIN_PLAYERS..players] {
+                self.set_header(player_header(*piece), UNKNOWN_VALUE);
+            }
+        }
+    }
+
+    /// How many players took part and how the game was decided, from the `Players` and `FreeForAll` headers.
+    pub fn get_players(&self) -> Result<(usize, FreeForAll), RecordError> {
+        let players = match self.get_header(HEADER_PLAYERS) {
+            Some(players) => players
+                .parse()
+                .ok()
+                .filter(|n| (MIN_PLAYERS..=MAX_PLAYERS).contains(n))
+                .ok_or(RecordError::InvalidPlayers)?,
+            None => MIN_PLAYERS,
+        };
+        let free_for_all = match self.get_header(HEADER_FREE_FOR_ALL) {
+            Some(name) => FreeForAll::ALL
+                .into_iter()
+                .find(|rule| rule.to_string().eq_ignore_ascii_case(name))
+                .ok_or(RecordError::InvalidPlayers)?,
+            None => FreeForAll::default(),
+        };
+        Ok((players, free_for_all))
+    }
+
+    /// The names of every player, from their headers, e.g. `Alice vs Bob`.
+    pub fn matchup(&self) -> String {
+        let players = self.get_players().map_or(MIN_PLAYERS, |(n, _)| n);
+        GamePiece::ALL[..players]
+            .iter()
+            .map(|piece| {
+                self.get_header(player_header(*piece))
+                    .unwrap_or(UNKNOWN_VALUE)
+            })
+            .collect::<Vec<_>>()
+            .join(" vs ")
+    }
+
+    /// Replays every move, yielding the [`GameState`] before the first move and after each move.
+    pub fn positions(&self) -> Result<Vec<GameState>, RecordError> {
+        let (players, free_for_all) = self.get_players()?;
+        let mut state = GameState::with_board(self.get_board()?, self.get_first_player()?)
+            .with_variant(self.get_variant()?)
+            .with_players(players, free_for_all);
+        let mut positions = vec![state];
+        for (i, m) in self.moves.iter().enumerate() {
+            state
+                .play(m.game_move)
+                .map_err(|e| RecordError::IllegalMove(i + 1, e))?;
+            positions.push(state);
+        }
+        Ok(positions)
+    }
+
+    /// Checks if the latest position, with the same player to move, has been reached often enough to draw the game.
+    ///
+    /// This replays the whole game, so games in progress keep count with [`Repetitions`] instead.
+    pub fn repeats_position(&self) -> bool {
+        let Ok(positions) = self.positions() else {
+            return false;
+        };
+        let latest = positions
+            .last()
+            .expect("Positions always include the starting position");
+        positions.iter().filter(|p| *p == latest).count() >= REPETITION_LIMIT
+    }
+
+    /// Replays every move, yielding the final [`GameState`].
+    pub fn replay(&self) -> Result<GameState, RecordError> {
+        Ok(*self
+            .positions()?
+            .last()
+            .expect("Positions always include the starting position"))
+    }
+}
+
+/// Counts how often each position of a game has been reached as moves are played and taken back, to find draws by repetition without replaying the game.
+///
+/// Positions are told apart by [`GameState::zobrist`], which covers the board and the player to move.
+#[derive(Clone, Debug)]
+pub struct Repetitions {
+    /// Hash of every position reached, in order, starting with the position before the first move.
+    history: Vec<u64>,
+    counts: HashMap<u64, usize>,
+}
+
+impl Repetitions {
+    /// Starts counting from a game's starting position.
+    pub fn new(start: &GameState) -> Self {
+        let mut repetitions = Self {
+            history: Vec::new(),
+            counts: HashMap::new(),
+        };
+        repetitions.push(start);
+        repetitions
+    }
+
+    /// Counts the position reached by the latest move.
+    pub fn push(&mut self, state: &GameState) {
+        let key = state.zobrist();
+        self.history.push(key);
+        *self.counts.entry(key).or_default() += 1;
+    }
+
+    /// Stops counting the latest position, as its move has been taken back.
+    ///
+    /// The starting position is always counted, so it is never removed.
+    pub fn pop(&mut self) {
+        if self.history.len() <= 1 {
+            return;
+        }
+        let key = self
+            .history
+            .pop()
+            .expect("History has more than one position");
+        if let Some(count) = self.counts.get_mut(&key) {
+            *count -= 1;
+            if *count == 0 {
+                self.counts.remove(&key);
+            }
+        }
+    }
+
+    /// Checks if the latest position has been reached often enough to draw the game.
+    pub fn is_drawn(&self) -> bool {
+        self.history
+            .last()
+            .and_then(|key| self.counts.get(key))
+            .is_some_and(|count| *count >= REPETITION_LIMIT)
+    }
+}
+
+impl Display for GameRecord {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        for (key, value) in &self.headers {
+            writeln!(f, "[{} \"{}\"]", key, escape(value, '"'))?;
+        }
+        writeln!(f)?;
+
+        // Each move number covers one turn for every player
+        let players = self.get_players().map_or(MIN_PLAYERS, |(n, _)| n);
+        let mut tokens = Vec::new();
+        for (i, m) in self.moves.iter().enumerate() {
+            if i % players == 0 {
+                tokens.push(format!("{}.", i / players + 1));
+            }
+            tokens.push(m.game_move.to_string());
+            if let Some(comment) = &m.comment {
+                tokens.push(format!("{{{}}}", escape(comment, '}')));
+            }
+        }
+        tokens.push(
+            self.get_header(HEADER_RESULT)
+                .unwrap_or(RESULT_ONGOING)
+                .to_string(),
+        );
+        writeln!(f, "{}", tokens.join(" "))
+    }
+}
+
+/// The header naming the player of the given piece.
+pub fn player_header(piece: GamePiece) -> &'static str {
+    match piece {
+        GamePiece::Red => HEADER_RED,
+        GamePiece::Yellow => HEADER_YELLOW,
+        GamePiece::Green => HEADER_GREEN,
+        GamePiece::Blue => HEADER_BLUE,
+    }
+}
+
+/// Writes several game records, separated by blank lines.
+pub fn write_records(records: &[GameRecord]) -> String {
+    records
+        .iter()
+        .map(|r| r.to_string())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Appends a game record to a file, creating the file if needed.
+pub fn append_record(path: impl AsRef<Path>, record: &GameRecord) -> std::io::Result<()> {
+    let path = path.as_ref();
+    let needs_separator = path.metadata().map(|m| m.len() > 0).unwrap_or(false);
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    if needs_separator {
+        writeln!(file)?;
+    }
+    write!(file, "{}", record)
+}
+
+/// Reads every game record contained in some text, as written by [`write_records`].
+pub fn parse_records(text: &str) -> Result<Vec<GameRecord>, RecordError> {
+    let mut records = Vec::new();
+    let mut current: Option<GameRecord> = None;
+
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        let record = current.get_or_insert_with(|| GameRecord {
+            headers: Vec::new(),
+            moves: Vec::new(),
+        });
+
+        if line.starts_with('[') {
+            if !record.moves.is_empty() {
+                return Err(RecordError::MissingResult);
+            }
+            let (key, value) = parse_header(line)?;
+            record.headers.push((key, value));
+            continue;
+        }
+
+        let mut rest = line;
+        while let Some(token) = next_token(&mut rest)? {
+            if let Some(comment) = token.strip_prefix('{') {
+                let comment = comment.strip_suffix('}').unwrap_or(comment).trim();
+                match record.moves.last_mut() {
+                    Some(m) => m.comment = Some(unescape(comment)),
+                    None => return Err(RecordError::InvalidToken(token.to_string())),
+                }
+            } else if let Some(number) = token.strip_suffix('.') {
+                number
+                    .parse::<usize>()
+                    .map_err(|_| RecordError::InvalidToken(token.to_string()))?;
+            } else if let Ok(game_move) = token.parse::<Move>() {
+                record.push_move(game_move);
+            } else if [
+                RESULT_RED,
+                RESULT_YELLOW,
+                RESULT_GREEN,
+                RESULT_BLUE,
+                RESULT_DRAW,
+                RESULT_ONGOING,
+            ]
+            .contains(&token)
+            {
+                let mut record = current.take().expect("Record was just inserted");
+                record.set_header(HEADER_RESULT, token);
+                records.push(record);
+                if !rest.trim().is_empty() {
+                    return Err(RecordError::InvalidToken(rest.trim().to_string()));
+                }
+                break;
+            } else {
+                return Err(RecordError::InvalidToken(token.to_string()));
+            }
+        }
+    }
+
+    match current {
+        Some(_) => Err(RecordError::MissingResult),
+        None => Ok(records),
+    }
+}
+
+/// Reads every game record in a file.
+pub fn load_records(path: impl AsRef<Path>) -> Result<Vec<GameRecord>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    parse_records(&text).map_err(|e| e.to_string())
+}
+
+/* Helper functions */
+
+fn parse_header(line: &str) -> Result<(String, String), RecordError> {
+    let invalid = || RecordError::InvalidHeader(line.to_string());
+    let inner = line
+        .strip_prefix('[')
+        .and_then(|l| l.strip_suffix(']'))
+        .ok_or_else(invalid)?;
+    let (key, value) = inner.split_once(' ').ok_or_else(invalid)?;
+    let value = value
+        .trim()
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .ok_or_else(invalid)?;
+    if key.is_empty() {
+        return Err(invalid());
+    }
+    Ok((key.to_string(), unescape(value)))
+}
+
+/// Escapes backslashes, line breaks and the character that would end a header value or comment, so that any text can be written on one line.
+fn escape(text: &str, end: char) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c if c == end => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Reverses [`escape`], leaving any backslash that doesn't start an escape as it is.
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let escaped = match (c, chars.peek()) {
+            ('\\', Some('n')) => Some('\n'),
+            ('\\', Some('r')) => Some('\r'),
+            ('\\', Some(next @ ('\\' | '"' | '}'))) => Some(*next),
+            _ => None,
+        };
+        match escaped {
+            Some(escaped) => {
+                unescaped.push(escaped);
+                chars.next();
+            }
+            None => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+/// Splits the next whitespace-separated token, or whole `{...}` comment, off the front of a line.
+fn next_token<'a>(line: &mut &'a str) -> Result<Option<&'a str>, RecordError> {
+    let trimmed = line.trim_start();
+    if trimmed.is_empty() {
+        return Ok(None);
+    }
+    let end = if trimmed.starts_with('{') {
+        comment_end(trimmed).ok_or(RecordError::UnclosedComment)? + 1
+    } else {
+        trimmed
+            .find(|c: char| c.is_whitespace() || c == '{')
+            .unwrap_or(trimmed.len())
+    };
+    let (token, rest) = trimmed.split_at(end);
+    *line = rest;
+    Ok(Some(token))
+}
+
+/// Finds the `}` closing the comment at the start of some text, skipping any escaped characters.
+fn comment_end(text: &str) -> Option<usize> {
+    let mut chars = text.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '}' => return Some(i),
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Today's date in the `YYYY.MM.DD` form used by record headers.
+fn today() -> String {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    format!("{year:04}.{month:02}.{day:02}")
+}
+
+/// Converts days since the Unix epoch into a (year, month, day) Gregorian calendar date.
+// Adapted from http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_record() -> GameRecord {
+        let mut record = GameRecord::new(GamePiece::Yellow);
+        record.set_header(HEADER_RED, "Alice");
+        record.set_header(HEADER_YELLOW, "Bob");
+        record.set_header(HEADER_DATE, "2023.06.01");
+        for col in [4, 4, 3, 3, 2] {
+            record.push_move(Move::Drop(col));
+        }
+        record.comment_last_move("Opens a double threat");
+        for col in [1, 5] {
+            record.push_move(Move::Drop(col));
+        }
+        record
+    }
+
+    #[test]
+    fn write_format() {
+        let expected = r#"[Red "Alice"]
+[Yellow "Bob"]
+[Date "2023.06.01"]
+[First "Y"]
+[Result "*"]
+
+1. 4 4 2. 3 3 3. 2 {Opens a double threat} 1 4. 5 *
+"#;
+        assert_eq!(expected, sample_record().to_string());
+    }
+
+    #[test]
+    fn round_trip_through_replay() {
+        let mut record = sample_record();
+        let state = record.replay().unwrap();
+        record.set_result(&state.check_endgame());
+        assert_eq!(EndgameType::Win(GamePiece::Yellow), state.check_endgame());
+
+        let parsed = parse_records(&record.to_string()).unwrap();
+        assert_eq!(vec![record], parsed);
+        assert_eq!(state, parsed[0].replay().unwrap());
+        assert_eq!(Some(RESULT_YELLOW), parsed[0].get_header(HEADER_RESULT));
+    }
+
+    #[test]
+    fn special_characters_round_trip() {
+        let mut record = sample_record();
+        record.set_header(HEADER_RED, r#"Alice "the Ace" \ Co."#);
+        record.set_header(HEADER_YELLOW, "Bob\non two lines");
+        record.comment_last_move(r"Threatens 3 {and 5} \o/");
+        record.push_move(Move::Drop(6));
+        record.comment_last_move("First line\r\nsecond line");
+
+        let text = record.to_string();
+        assert!(text.contains(r#"[Red "Alice \"the Ace\" \\ Co."]"#));
+        assert!(text.contains(r"{Threatens 3 {and 5\} \\o/}"));
+        assert_eq!(vec![record], parse_records(&text).unwrap());
+
+        // Backslashes that don't start an escape are read as they are
+        let parsed = parse_records("[Red \"C:\\games\"]\n\n1. 4 {a\\b} *\n").unwrap();
+        assert_eq!(Some(r"C:\games"), parsed[0].get_header(HEADER_RED));
+        assert_eq!(Some(r"a\b"), parsed[0].get_moves()[0].comment.as_deref());
+    }
+
+    #[test]
+    fn multiple_games() {
+        let mut second = GameRecord::new(GamePiece::Red);
+        second.push_move(Move::Drop(1));
+        second.set_result(&EndgameType::Full);
+        let records = vec![sample_record(), second];
+
+        let parsed = parse_records(&write_records(&records)).unwrap();
+        assert_eq!(records, parsed);
+    }
+
+    #[test]
+    fn positions_include_start() {
+        let positions = sample_record().positions().unwrap();
+        assert_eq!(8, positions.len());
+        assert_eq!(GameBoard::default(), *positions[0].get_board());
+        assert_eq!(GamePiece::Yellow, *positions[0].get_next_player());
+    }
+
+    #[test]
+    fn custom_board() {
+        let board = GameBoard::with_size(7, 9, 5).unwrap();
+        let mut record = GameRecord::new(GamePiece::Red);
+        record.set_board(&board);
+        assert_eq!(Some("9x7"), record.get_header(HEADER_SIZE));
+        assert_eq!(Some("5"), record.get_header(HEADER_WIN_LENGTH));
+        record.push_move(Move::Drop(9));
+
+        let parsed = parse_records(&record.to_string()).unwrap();
+        let positions = parsed[0].positions().unwrap();
+        assert_eq!(board, *positions[0].get_board());
+        assert_eq!(
+            Some(GamePiece::Red),
+            positions[1].get_board().get_piece(6, 8)
+        );
+
+        let mut standard = GameRecord::new(GamePiece::Red);
+        standard.set_board(&GameBoard::default());
+        assert_eq!(None, standard.get_header(HEADER_SIZE));
+
+        record.set_header(HEADER_SIZE, "9 by 7");
+        assert_eq!(Err(RecordError::InvalidBoard), record.replay());
+    }
+
+    #[test]
+    fn blocked_board() {
+        let mut board = GameBoard::with_size(4, 4, 3).unwrap();
+        layout::apply(&mut board, "..../..../..../.#..").unwrap();
+        let mut record = GameRecord::new(GamePiece::Red);
+        record.set_board(&board);
+        assert_eq!(
+            Some("..../..../..../.#.."),
+            record.get_header(HEADER_BLOCKED)
+        );
+        record.push_move(Move::Drop(2));
+
+        let parsed = parse_records(&record.to_string()).unwrap();
+        let positions = parsed[0].positions().unwrap();
+        assert_eq!(board, *positions[0].get_board());
+        assert_eq!(
+            Some(GamePiece::Red),
+            positions[1].get_board().get_piece(2, 1)
+        );
+
+        record.set_header(HEADER_BLOCKED, "#");
+        assert_eq!(Err(RecordError::InvalidBoard), record.replay());
+    }
+
+    #[test]
+    fn free_for_all() {
+        let mut record = GameRecord::new(GamePiece::Green);
+        record.set_board(&GameBoard::with_size(5, 5, 3).unwrap());
+        record.set_players(3, FreeForAll::FirstToConnect);
+        assert_eq!(
+            Some("First to connect"),
+            record.get_header(HEADER_FREE_FOR_ALL)
+        );
+        assert_eq!(Some(UNKNOWN_VALUE), record.get_header(HEADER_GREEN));
+        record.set_header(HEADER_GREEN, "Carol");
+        for col in [3, 1, 2, 3, 1, 2, 3] {
+            record.push_move(Move::Drop(col));
+        }
+
+        let state = record.replay().unwrap();
+        assert_eq!(EndgameType::Win(GamePiece::Green), state.check_endgame());
+        record.set_result(&state.check_endgame());
+        let text = record.to_string();
+        assert!(text.ends_with("1. 3 1 2 2. 3 1 2 3. 3 Green\n"), "{text}");
+        assert!(record.matchup().ends_with("vs Carol"));
+
+        let parsed = parse_records(&text).unwrap();
+        assert_eq!(Ok((3, FreeForAll::FirstToConnect)), parsed[0].get_players());
+        assert_eq!(state, parsed[0].replay().unwrap());
+
+        record.set_header(HEADER_PLAYERS, "5");
+        assert_eq!(Err(RecordError::InvalidPlayers), record.replay());
+    }
+
+    #[test]
+    fn illegal_move() {
+        let mut record = GameRecord::new(GamePiece::Red);
+        record.push_move(Move::Drop(8));
+        assert_eq!(
+            Err(RecordError::IllegalMove(1, InsertError::InvalidColumn)),
+            record.replay()
+        );
+    }
+
+    #[test]
+    fn popout_games() {
+        let mut record = GameRecord::new(GamePiece::Red);
+        record.set_variant(Variant::PopOut);
+        for game_move in ["1", "2", "p1", "p2", "1", "2"] {
+            record.push_move(game_move.parse().unwrap());
+        }
+        assert!(record.to_string().ends_with("1. 1 2 2. p1 p2 3. 1 2 *\n"));
+        let parsed = parse_records(&record.to_string()).unwrap();
+        assert_eq!(Ok(Variant::PopOut), parsed[0].get_variant());
+        assert_eq!(record.replay(), parsed[0].replay());
+        assert!(!record.repeats_position());
+
+        // Both players pop their piece out and drop it back in, reaching the same position a third time
+        for game_move in ["p1", "p2", "1", "2"] {
+            record.push_move(game_move.parse().unwrap());
+        }
+        assert!(record.repeats_position());
+
+        // Counting positions as they are played agrees with replaying the record
+        let positions = record.positions().unwrap();
+        let mut repetitions = Repetitions::new(&positions[0]);
+        for (i, position) in positions.iter().enumerate().skip(1) {
+            repetitions.push(position);
+            let seen = positions[..=i].iter().filter(|p| *p == position).count();
+            assert_eq!(seen >= 3, repetitions.is_drawn(), "move {i}");
+        }
+        assert!(repetitions.is_drawn());
+        // Taking back moves stops counting the positions they reached
+        for _ in 0..3 {
+            repetitions.pop();
+        }
+        assert!(!repetitions.is_drawn());
+
+        record.set_header(HEADER_VARIANT, "Backwards");
+        assert_eq!(Err(RecordError::InvalidVariant), record.replay());
+        record.set_header(HEADER_VARIANT, "Classic");
+        assert_eq!(
+            Err(RecordError::IllegalMove(3, InsertError::PopUnavailable)),
+            record.replay()
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            Err(RecordError::InvalidHeader("[Red Alice]".to_string())),
+            parse_records("[Red Alice]\n1. 4 *")
+        );
+        assert_eq!(Err(RecordError::MissingResult), parse_records("1. 4 4"));
+        assert_eq!(
+            Err(RecordError::UnclosedComment),
+            parse_records("1. 4 {oops *")
+        );
+        assert_eq!(
+            Err(RecordError::InvalidToken("x".to_string())),
+            parse_records("1. 4 x *")
+        );
+    }
+
+    #[test]
+    fn civil_dates() {
+        assert_eq!((1970, 1, 1), civil_from_days(0));
+        assert_eq!((2000, 3, 1), civil_from_days(11_017));
+        assert_eq!((2024, 2, 29), civil_from_days(19_782));
+    }
+}
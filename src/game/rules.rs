@@ -0,0 +1,282 @@
+use crate::game::{
+    board::{GameBoard, GamePiece, InsertError, SizeError, MAX_PLAYERS, MIN_PLAYERS},
+    state::{EndgameType, Move},
+};
+
+/// The rules a game is played by: which moves are legal, what they do to the board, and how the game ends.
+///
+/// Each [`super::Variant`] has one, which games, clients, the AI and the views all go through.
+pub trait Ruleset: Sync {
+    /// Creates an empty board of the given size, or a [`SizeError`] if the rules cannot be played on it.
+    fn new_board(
+        &self,
+        rows: usize,
+        cols: usize,
+        win_length: usize,
+    ) -> Result<GameBoard, SizeError> {
+        GameBoard::with_size(rows, cols, win_length)
+    }
+
+    /// Every move the player can make, in the order they are best tried in.
+    fn legal_moves(&self, board: &GameBoard, player: GamePiece) -> Vec<Move>;
+
+    /// Makes a move for the player, or returns an [`InsertError`] if it is not allowed.
+    fn apply(
+        &self,
+        board: &mut GameBoard,
+        player: GamePiece,
+        game_move: Move,
+    ) -> Result<(), InsertError>;
+
+    /// Checks whether the player has completed a line.
+    fn has_line(&self, board: &GameBoard, player: GamePiece) -> bool {
+        board.has_line(player)
+    }
+
+    /// Checks whether the game is drawn, with the given player to move and no line on the board.
+    fn is_drawn(&self, board: &GameBoard, player: GamePiece) -> bool;
+
+    /// Checks whether the board has been won or drawn, with the given player to move.
+    ///
+    /// Completing a line wins, unless [`Self::lines_lose`].
+    /// If the last move completed lines for both players at once, only the player who made it counts as completing one.
+    fn endgame(&self, board: &GameBoard, next_player: GamePiece) -> EndgameType {
+        let mover = next_player.opponent();
+        let completed = if self.has_line(board, mover) {
+            mover
+        } else if self.has_line(board, next_player) {
+            next_player
+        } else if self.is_drawn(board, next_player) {
+            return EndgameType::Full;
+        } else {
+            return EndgameType::None;
+        };
+        match self.lines_lose() {
+            true => EndgameType::Win(completed.opponent()),
+            false => EndgameType::Win(completed),
+        }
+    }
+
+    /// Whether players can pop their own pieces out of the bottom of a column.
+    fn can_pop(&self) -> bool {
+        false
+    }
+
+    /// Whether the left and right edges of the board meet, so that lines can wrap around them.
+    fn wraps(&self) -> bool {
+        false
+    }
+
+    /// Whether completing a line loses the game rather than winning it.
+    fn lines_lose(&self) -> bool {
+        false
+    }
+
+    /// Most players that can take part in a game by these rules.
+    ///
+    /// Games between more than two players are decided by a [`super::FreeForAll`] rule rather than [`Self::endgame`].
+    fn max_players(&self) -> usize {
+        MAX_PLAYERS
+    }
+}
+
+/// Pieces are only ever dropped in, and a full board is a draw.
+pub struct Classic;
+
+impl Ruleset for Classic {
+    fn legal_moves(&self, board: &GameBoard, _player: GamePiece) -> Vec<Move> {
+        drops(board).collect()
+    }
+
+    fn apply(
+        &self,
+        board: &mut GameBoard,
+        player: GamePiece,
+        game_move: Move,
+    ) -> Result<(), InsertError> {
+        match game_move {
+            Move::Drop(col) => board.insert_piece(player, col),
+            Move::Pop(_) => Err(InsertError::PopUnavailable),
+        }
+    }
+
+    fn is_drawn(&self, board: &GameBoard, _player: GamePiece) -> bool {
+        board.is_full()
+    }
+}
+
+/// Players may pop one of their own pieces out of the bottom of a column instead of dropping one in.
+///
+/// A full board is only a draw if the player to move has nothing of their own to pop out.
+pub struct PopOut;
+
+impl Ruleset for PopOut {
+    fn legal_moves(&self, board: &GameBoard, player: GamePiece) -> Vec<Move> {
+        let bottom = board.rows() - 1;
+        let pops = (1..=board.cols())
+            .filter(|col| board.get_piece(bottom, col - 1) == Some(player))
+            .map(Move::Pop);
+        drops(board).chain(pops).collect()
+    }
+
+    fn apply(
+        &self,
+        board: &mut GameBoard,
+        player: GamePiece,
+        game_move: Move,
+    ) -> Result<(), InsertError> {
+        match game_move {
+            Move::Drop(col) => board.insert_piece(player, col),
+            Move::Pop(col) => board.pop_piece(player, col),
+        }
+    }
+
+    fn is_drawn(&self, board: &GameBoard, player: GamePiece) -> bool {
+        self.legal_moves(board, player).is_empty()
+    }
+
+    fn can_pop(&self) -> bool {
+        true
+    }
+
+    fn max_players(&self) -> usize {
+        MIN_PLAYERS
+    }
+}
+
+/// The classic rules on a board whose left and right edges meet, as if wrapped around a cylinder.
+///
+/// Horizontal and diagonal lines can run off one side and carry on from the other, so a line can't be longer than the board is wide.
+pub struct Cylinder;
+
+impl Ruleset for Cylinder {
+    fn new_board(
+        &self,
+        rows: usize,
+        cols: usize,
+        win_length: usize,
+    ) -> Result<GameBoard, SizeError> {
+        if win_length > cols {
+            return Err(SizeError::WinLength(win_length));
+        }
+        GameBoard::with_size(rows, cols, win_length).map(|board| board.with_wrapping(true))
+    }
+
+    fn legal_moves(&self, board: &GameBoard, player: GamePiece) -> Vec<Move> {
+        Classic.legal_moves(board, player)
+    }
+
+    fn apply(
+        &self,
+        board: &mut GameBoard,
+        player: GamePiece,
+        game_move: Move,
+    ) -> Result<(), InsertError> {
+        Classic.apply(board, player, game_move)
+    }
+
+    fn is_drawn(&self, board: &GameBoard, player: GamePiece) -> bool {
+        Classic.is_drawn(board, player)
+    }
+
+    fn wraps(&self) -> bool {
+        true
+    }
+}
+
+/// The classic rules turned around, so that the player who completes a line loses.
+pub struct Misere;
+
+impl Ruleset for Misere {
+    fn legal_moves(&self, board: &GameBoard, player: GamePiece) -> Vec<Move> {
+        Classic.legal_moves(board, player)
+    }
+
+    fn apply(
+        &self,
+        board: &mut GameBoard,
+        player: GamePiece,
+        game_move: Move,
+    ) -> Result<(), InsertError> {
+        Classic.apply(board, player, game_move)
+    }
+
+    fn is_drawn(&self, board: &GameBoard, player: GamePiece) -> bool {
+        Classic.is_drawn(board, player)
+    }
+
+    fn lines_lose(&self) -> bool {
+        true
+    }
+
+    fn max_players(&self) -> usize {
+        MIN_PLAYERS
+    }
+}
+
+/// Drops into every column with room, from left to right.
+fn drops(board: &GameBoard) -> impl Iterator<Item = Move> + '_ {
+    (1..=board.cols())
+        .filter(|col| board.has_room(*col))
+        .map(Move::Drop)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::board::{GameBoard, GamePiece, SizeError};
+    use crate::game::state::{EndgameType, Move};
+    use crate::game::Variant;
+
+    #[test]
+    fn legal_moves_can_be_applied() {
+        let mut board = GameBoard::with_size(4, 4, 3).unwrap();
+        for (piece, col) in [(GamePiece::Red, 1), (GamePiece::Yellow, 2)] {
+            board.insert_piece(piece, col).unwrap();
+        }
+        for _ in 0..4 {
+            board.insert_piece(GamePiece::Yellow, 4).unwrap();
+        }
+
+        for variant in Variant::ALL {
+            let rules = variant.rules();
+            let legal = rules.legal_moves(&board, GamePiece::Red);
+            let every_move = (1..=5).flat_map(|col| [Move::Drop(col), Move::Pop(col)]);
+            for game_move in every_move {
+                let mut played = board;
+                let result = rules.apply(&mut played, GamePiece::Red, game_move);
+                assert_eq!(
+                    legal.contains(&game_move),
+                    result.is_ok(),
+                    "{game_move} in {variant}"
+                );
+            }
+        }
+        assert_eq!(
+            vec![Move::Drop(1), Move::Drop(2), Move::Drop(3), Move::Pop(1)],
+            Variant::PopOut.rules().legal_moves(&board, GamePiece::Red)
+        );
+    }
+
+    #[test]
+    fn cylinder_boards_wrap() {
+        let rules = Variant::Cylinder.rules();
+        assert!(rules.new_board(6, 7, 4).unwrap().wraps());
+        assert!(!Variant::Classic.rules().new_board(6, 7, 4).unwrap().wraps());
+        // A line longer than the board is wide would meet itself
+        assert_eq!(Err(SizeError::WinLength(6)), rules.new_board(6, 5, 6));
+    }
+
+    #[test]
+    fn misere_lines_lose() {
+        let mut board = GameBoard::default();
+        for col in 1..=4 {
+            board.insert_piece(GamePiece::Red, col).unwrap();
+        }
+        let endgame = |variant: Variant| variant.rules().endgame(&board, GamePiece::Yellow);
+        assert_eq!(EndgameType::Win(GamePiece::Red), endgame(Variant::Classic));
+        assert_eq!(
+            EndgameType::Win(GamePiece::Yellow),
+            endgame(Variant::Misere)
+        );
+    }
+}
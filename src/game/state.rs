@@ -1,20 +1,104 @@
-use crate::game::board::{GameBoard, GamePiece};
+use crate::game::board::{GameBoard, GamePiece, InsertError, MIN_PLAYERS};
+use crate::game::{rules::Ruleset, zobrist, FreeForAll, Variant};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::str::FromStr;
+
+/// Prefix marking a pop in written moves, e.g. `p4`.
+const POP_PREFIX: char = 'p';
 
 /// Defines possible end-game states.
+///
+/// Serialized as `"none"`, `"full"` or `{"win":"R"}`.
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "lowercase")
+)]
 pub enum EndgameType {
     /// A given player represented by a [`GamePiece`] has won.
+    ///
+    /// This is usually by completing a line, but in [`Variant::Misere`] it is by the other player completing one,
+    /// and in [`FreeForAll::Elimination`] games it is by being the last player left.
     Win(GamePiece),
-    /// The board is full; draw.
+    /// The board is full, or the player to move has no moves left; draw.
     Full,
+    /// The same position has been reached three times; draw.
+    Repetition,
     /// No end-game state, play continues.
     None,
 }
 
-#[derive(Clone, Copy, Debug)]
+/// A move a player can make on their turn, in a column numbered from 1.
+///
+/// Written as the column number for a drop, e.g. `4`, and with a `p` before it for a pop, e.g. `p4`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Move {
+    /// Drop a piece in on top of the column.
+    Drop(usize),
+    /// Pop the player's own piece out of the bottom of the column, in the [`Variant::PopOut`] rules.
+    Pop(usize),
+}
+
+impl Move {
+    /// The column, numbered from 1, that the move is made in.
+    pub fn column(&self) -> usize {
+        match self {
+            Self::Drop(col) | Self::Pop(col) => *col,
+        }
+    }
+
+    /// The same kind of move in the column it becomes when the board is reflected.
+    pub fn mirrored(&self, board: &GameBoard) -> Self {
+        match self {
+            Self::Drop(col) => Self::Drop(board.mirror_column(*col)),
+            Self::Pop(col) => Self::Pop(board.mirror_column(*col)),
+        }
+    }
+}
+
+impl Display for Move {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Drop(col) => write!(f, "{col}"),
+            Self::Pop(col) => write!(f, "{POP_PREFIX}{col}"),
+        }
+    }
+}
+
+impl FromStr for Move {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        match s.strip_prefix([POP_PREFIX, POP_PREFIX.to_ascii_uppercase()]) {
+            Some(col) => col.trim().parse().map(Self::Pop).map_err(|_| ()),
+            None => s.parse().map(Self::Drop).map_err(|_| ()),
+        }
+    }
+}
+
+/// Serialized as its board and the player to move next, and only deserialized if the position could arise in a real game.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(
+        into = "super::notation::Position",
+        try_from = "super::notation::Position"
+    )
+)]
 pub struct GameState {
     board: GameBoard,
     next_player: GamePiece,
+    variant: Variant,
+    /// Number of players taking turns, who play the first pieces of [`GamePiece::ALL`].
+    players: usize,
+    /// How the game is decided if there are more than two players.
+    free_for_all: FreeForAll,
 }
 
 impl Default for GameState {
@@ -22,16 +106,88 @@ impl Default for GameState {
         Self {
             board: Default::default(),
             next_player: GamePiece::random(),
+            variant: Variant::default(),
+            players: MIN_PLAYERS,
+            free_for_all: FreeForAll::default(),
         }
     }
 }
 
 impl GameState {
+    /// Creates a two-player game state from an existing board and the player whose turn it is.
+    pub fn with_board(board: GameBoard, next_player: GamePiece) -> Self {
+        Self {
+            board,
+            next_player,
+            variant: Variant::default(),
+            players: MIN_PLAYERS,
+            free_for_all: FreeForAll::default(),
+        }
+    }
+
+    /// Plays between the given number of players, decided by the given rule if there are more than two.
+    ///
+    /// Players should be no more than the variant's [`Ruleset::max_players`].
+    pub fn with_players(mut self, players: usize, free_for_all: FreeForAll) -> Self {
+        self.players = players;
+        self.free_for_all = free_for_all;
+        self
+    }
+
+    /// Number of players taking turns, including any who have been knocked out.
+    pub fn player_count(&self) -> usize {
+        self.players
+    }
+
+    pub fn get_free_for_all(&self) -> FreeForAll {
+        self.free_for_all
+    }
+
+    /// Every player in the game, in turn order, including any who have been knocked out.
+    pub fn all_players(&self) -> &'static [GamePiece] {
+        &GamePiece::ALL[..self.players]
+    }
+
+    /// Checks whether the player has been knocked out of an elimination game by completing a line.
+    ///
+    /// Only the player making a move can complete a line by the rules that allow more than two players, so this follows from the board alone.
+    pub fn is_eliminated(&self, piece: GamePiece) -> bool {
+        self.players > MIN_PLAYERS
+            && self.free_for_all == FreeForAll::Elimination
+            && self.rules().has_line(&self.board, piece)
+    }
+
+    /// The players still taking turns, in turn order.
+    pub fn active_players(&self) -> impl Iterator<Item = GamePiece> + '_ {
+        self.all_players()
+            .iter()
+            .copied()
+            .filter(|piece| !self.is_eliminated(*piece))
+    }
+
+    /// Plays by the rules of the given variant instead of the classic rules.
+    ///
+    /// The board is wrapped around, or not, to match.
+    pub fn with_variant(mut self, variant: Variant) -> Self {
+        self.variant = variant;
+        self.board = self.board.with_wrapping(variant.rules().wraps());
+        self
+    }
+
+    pub fn get_variant(&self) -> Variant {
+        self.variant
+    }
+
+    /// The rules the game is played by.
+    pub fn rules(&self) -> &'static dyn Ruleset {
+        self.variant.rules()
+    }
+
     pub fn get_board(&self) -> &GameBoard {
         &self.board
     }
 
-    pub(crate) fn get_board_mut(&mut self) -> &mut GameBoard {
+    pub fn get_board_mut(&mut self) -> &mut GameBoard {
         &mut self.board
     }
 
@@ -39,20 +195,97 @@ impl GameState {
         &self.next_player
     }
 
+    /// The player whose turn comes just before the next player's, whether or not they have been knocked out.
+    pub fn previous_player(&self) -> GamePiece {
+        let index = self.next_player.index() + self.players - 1;
+        GamePiece::ALL[index % self.players]
+    }
+
+    /// Passes the turn on to the next player in turn order, skipping any who have been knocked out.
     pub fn toggle_player(&mut self) {
-        self.next_player = match self.next_player {
-            GamePiece::Red => GamePiece::Yellow,
-            GamePiece::Yellow => GamePiece::Red,
+        let mut next = self.next_player.next(self.players);
+        for _ in 1..self.players {
+            if !self.is_eliminated(next) {
+                break;
+            }
+            next = next.next(self.players);
         }
+        self.next_player = next;
+    }
+
+    /// Drops the next player's piece into the given column, numbered from 1, then passes the turn on.
+    pub fn play_move(&mut self, col_num: usize) -> Result<(), InsertError> {
+        self.play(Move::Drop(col_num))
+    }
+
+    /// Makes a move for the next player, then passes the turn on.
+    pub fn play(&mut self, game_move: Move) -> Result<(), InsertError> {
+        self.rules()
+            .apply(&mut self.board, self.next_player, game_move)?;
+        self.toggle_player();
+        Ok(())
     }
 
+    /// Every move the next player can make, drops first, from left to right.
+    pub fn legal_moves(&self) -> Vec<Move> {
+        self.rules().legal_moves(&self.board, self.next_player)
+    }
+
+    /// A hash of the board and the player to move, which is the same however the position was reached.
+    pub fn zobrist(&self) -> u64 {
+        self.board.zobrist() ^ zobrist::player_key(self.next_player)
+    }
+
+    /// The hash that the position would have if the board were reflected left to right.
+    pub fn mirrored_zobrist(&self) -> u64 {
+        self.board.mirrored_zobrist() ^ zobrist::player_key(self.next_player)
+    }
+
+    /// The hash of the position's canonical form, which it shares with its reflection.
+    pub fn canonical_zobrist(&self) -> u64 {
+        self.board.canonical_zobrist() ^ zobrist::player_key(self.next_player)
+    }
+
+    /// The position with its board reflected left to right.
+    pub fn mirrored(&self) -> Self {
+        Self {
+            board: self.board.mirrored(),
+            ..*self
+        }
+    }
+
+    /// Picks the position or its reflection as its canonical form, and whether the reflection was picked, like [`GameBoard::canonical`].
+    pub fn canonical(&self) -> (Self, bool) {
+        let (board, mirrored) = self.board.canonical();
+        (Self { board, ..*self }, mirrored)
+    }
+
+    /// Checks whether the board has been won or drawn by the game's rules, without knowing how it was reached.
+    ///
+    /// Repeated positions are found by counting the positions of the game as it is played, with [`super::record::Repetitions`].
     pub fn check_endgame(&self) -> EndgameType {
-        if let Some(p) = self.board.is_winner() {
-            EndgameType::Win(p)
-        } else if self.board.is_full() {
-            EndgameType::Full
-        } else {
-            EndgameType::None
+        let rules = self.rules();
+        if self.players <= MIN_PLAYERS {
+            return rules.endgame(&self.board, self.next_player);
+        }
+        let winner = match self.free_for_all {
+            FreeForAll::FirstToConnect => self
+                .all_players()
+                .iter()
+                .copied()
+                .find(|piece| rules.has_line(&self.board, *piece)),
+            FreeForAll::Elimination => {
+                let mut left = self.active_players();
+                match (left.next(), left.next()) {
+                    (Some(last), None) => Some(last),
+                    _ => None,
+                }
+            }
+        };
+        match winner {
+            Some(piece) => EndgameType::Win(piece),
+            None if rules.is_drawn(&self.board, self.next_player) => EndgameType::Full,
+            None => EndgameType::None,
         }
     }
 
@@ -67,8 +300,9 @@ impl GameState {
 
 #[cfg(test)]
 mod tests {
-    use super::{EndgameType, GameState};
-    use crate::game::board::{GameBoard, GamePiece};
+    use super::{EndgameType, GameState, Move};
+    use crate::game::board::{GameBoard, GamePiece, InsertError};
+    use crate::game::{FreeForAll, Variant};
 
     #[test]
     fn toggle_player() {
@@ -82,6 +316,17 @@ mod tests {
         assert_eq!(state.get_next_player(), &initial_player);
     }
 
+    #[test]
+    fn play_move() {
+        let mut state = GameState::with_board(GameBoard::default(), GamePiece::Yellow);
+        state.play_move(3).unwrap();
+        assert_eq!(Some(GamePiece::Yellow), state.get_board().get_piece(5, 2));
+        assert_eq!(&GamePiece::Red, state.get_next_player());
+
+        assert!(state.play_move(8).is_err());
+        assert_eq!(&GamePiece::Red, state.get_next_player());
+    }
+
     #[test]
     fn check_endgame_winner() {
         let mut board = GameBoard::default();
@@ -95,6 +340,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::match_like_matches_macro)]
     fn check_endgame_full() {
         let mut board = GameBoard::default();
 
@@ -118,9 +364,180 @@ mod tests {
         assert_eq!(EndgameType::Full, state.check_endgame());
     }
 
+    #[test]
+    fn wide_board() {
+        // Ten columns by eight rows, won along the bottom of the last four columns
+        let board = GameBoard::with_size(8, 10, 4).unwrap();
+        let mut state = GameState::with_board(board, GamePiece::Red);
+        for col in [10, 10, 9, 9, 8, 8] {
+            state.play_move(col).unwrap();
+        }
+        assert_eq!(Some(GamePiece::Red), state.get_board().get_piece(7, 9));
+        assert_eq!(EndgameType::None, state.check_endgame());
+        assert_eq!(10, state.legal_moves().len());
+        assert_eq!(Ok(Move::Drop(10)), "10".parse());
+
+        state.play_move(7).unwrap();
+        assert_eq!(EndgameType::Win(GamePiece::Red), state.check_endgame());
+        assert_eq!(Err(InsertError::InvalidColumn), state.play_move(11));
+    }
+
     #[test]
     fn check_endgame_none() {
         let state = GameState::default();
         assert_eq!(state.check_endgame(), EndgameType::None);
     }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde_round_trip() {
+        let mut state = GameState::with_board(GameBoard::default(), GamePiece::Red);
+        for col in [4, 4, 3] {
+            state.play_move(col).unwrap();
+        }
+        let json = serde_json::to_string(&state).unwrap();
+        assert!(json.ends_with(r#""...Y...","..RR..."],"win_length":4},"next_player":"Y"}"#));
+        assert_eq!(state, serde_json::from_str(&json).unwrap());
+
+        // Red cannot move next when they already have more pieces
+        let wrong_side = json.replace(r#""next_player":"Y""#, r#""next_player":"R""#);
+        assert!(serde_json::from_str::<GameState>(&wrong_side).is_err());
+
+        // Popping pieces out unbalances the counts, which PopOut positions allow
+        let mut popout = GameState::with_board(GameBoard::default(), GamePiece::Red)
+            .with_variant(Variant::PopOut);
+        for game_move in [Move::Drop(4), Move::Drop(3), Move::Pop(4)] {
+            popout.play(game_move).unwrap();
+        }
+        let json = serde_json::to_string(&popout).unwrap();
+        assert!(json.contains(r#""variant":"popout""#));
+        assert_eq!(popout, serde_json::from_str(&json).unwrap());
+
+        for endgame in [
+            EndgameType::Win(GamePiece::Yellow),
+            EndgameType::Full,
+            EndgameType::None,
+        ] {
+            let json = serde_json::to_string(&endgame).unwrap();
+            assert_eq!(endgame, serde_json::from_str(&json).unwrap());
+        }
+        assert_eq!(
+            r#"{"win":"Y"}"#,
+            serde_json::to_string(&EndgameType::Win(GamePiece::Yellow)).unwrap()
+        );
+        assert_eq!(
+            r#""full""#,
+            serde_json::to_string(&EndgameType::Full).unwrap()
+        );
+    }
+
+    #[test]
+    fn moves_are_written_and_read() {
+        assert_eq!("4", Move::Drop(4).to_string());
+        assert_eq!("p4", Move::Pop(4).to_string());
+        assert_eq!(Ok(Move::Pop(2)), " P2".parse());
+        assert_eq!(Ok(Move::Drop(7)), "7".parse());
+        assert!("pp2".parse::<Move>().is_err());
+        assert!("x".parse::<Move>().is_err());
+    }
+
+    #[test]
+    fn popout_moves() {
+        let mut state = GameState::with_board(GameBoard::default(), GamePiece::Red);
+        state.play_move(2).unwrap();
+        // Yellow can only drop
+        assert_eq!(Err(InsertError::PopUnavailable), state.play(Move::Pop(2)));
+
+        let mut state = state.with_variant(Variant::PopOut);
+        assert_eq!(Err(InsertError::CannotPop), state.play(Move::Pop(2)));
+        state.play_move(2).unwrap();
+        let moves = state.legal_moves();
+        assert_eq!(8, moves.len());
+        assert_eq!(Some(&Move::Pop(2)), moves.last());
+
+        state.play(Move::Pop(2)).unwrap();
+        assert_eq!(Some(GamePiece::Yellow), state.get_board().get_piece(5, 1));
+        assert_eq!(None, state.get_board().get_piece(4, 1));
+        assert_eq!(&GamePiece::Yellow, state.get_next_player());
+    }
+
+    #[test]
+    fn popout_endgames() {
+        // A full board can still be played on by popping
+        let mut board = GameBoard::with_size(4, 4, 3).unwrap();
+        for (col, pieces) in ["RYRY", "RYRR", "YRYY", "YRYR"].iter().enumerate() {
+            for c in pieces.chars() {
+                board.insert_piece(c.try_into().unwrap(), col + 1).unwrap();
+            }
+        }
+        let state = GameState::with_board(board, GamePiece::Red);
+        assert_eq!(EndgameType::Full, state.check_endgame());
+        let mut state = state.with_variant(Variant::PopOut);
+        assert_eq!(EndgameType::None, state.check_endgame());
+
+        // Popping column 2 gives both players three in a row, and the player who popped wins
+        state.play(Move::Pop(2)).unwrap();
+        assert!(state.get_board().has_line(GamePiece::Yellow));
+        assert_eq!(EndgameType::Win(GamePiece::Red), state.check_endgame());
+    }
+
+    #[test]
+    fn free_for_all_turns_and_wins() {
+        let three_players = |free_for_all| {
+            let board = GameBoard::with_size(5, 5, 3).unwrap();
+            let mut state =
+                GameState::with_board(board, GamePiece::Red).with_players(3, free_for_all);
+            for col in [1, 2, 3, 1, 2, 3] {
+                state.play_move(col).unwrap();
+            }
+            assert_eq!(&GamePiece::Red, state.get_next_player());
+            state.play_move(1).unwrap();
+            state
+        };
+
+        let state = three_players(FreeForAll::FirstToConnect);
+        assert_eq!(EndgameType::Win(GamePiece::Red), state.check_endgame());
+
+        // Red is knocked out, and play goes on between Yellow and Green
+        let mut state = three_players(FreeForAll::Elimination);
+        assert_eq!(EndgameType::None, state.check_endgame());
+        assert!(state.is_eliminated(GamePiece::Red));
+        for col in [4, 4] {
+            state.play_move(col).unwrap();
+        }
+        assert_eq!(&GamePiece::Yellow, state.get_next_player());
+        state.play_move(2).unwrap();
+        assert_eq!(
+            vec![GamePiece::Green],
+            state.active_players().collect::<Vec<_>>()
+        );
+        assert_eq!(EndgameType::Win(GamePiece::Green), state.check_endgame());
+    }
+
+    #[test]
+    fn elimination_is_last_player_standing() {
+        let board = GameBoard::with_size(5, 6, 3).unwrap();
+        let mut state =
+            GameState::with_board(board, GamePiece::Red).with_players(4, FreeForAll::Elimination);
+        for col in [1, 2, 3, 4, 1, 2, 3, 4, 1] {
+            state.play_move(col).unwrap();
+        }
+
+        // Red's line knocks them out rather than winning, and their pieces stay put
+        assert_eq!(EndgameType::None, state.check_endgame());
+        assert!(state.is_eliminated(GamePiece::Red));
+        assert_eq!(Some(GamePiece::Red), state.get_board().get_piece(2, 0));
+        assert_eq!(&GamePiece::Yellow, state.get_next_player());
+
+        // A second line still leaves two players, so play goes on
+        state.play_move(2).unwrap();
+        assert_eq!(EndgameType::None, state.check_endgame());
+        assert_eq!(&GamePiece::Green, state.get_next_player());
+
+        // Green never completes a line, but wins once Blue is knocked out too
+        state.play_move(5).unwrap();
+        state.play_move(4).unwrap();
+        assert!(!state.get_board().has_line(GamePiece::Green));
+        assert_eq!(EndgameType::Win(GamePiece::Green), state.check_endgame());
+    }
 }
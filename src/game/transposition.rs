@@ -0,0 +1,168 @@
+use super::state::GameState;
+
+/// How a score stored by an alpha-beta search relates to the position's true score.
+///
+/// A search that cuts off part of a position's moves only learns a bound on its score, which can still settle a later search with a narrower window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    /// The true score is at least the stored one.
+    Lower,
+    /// The true score is at most the stored one.
+    Upper,
+}
+
+/// A result stored for a position, along with how it was found.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TableHit<T> {
+    pub value: T,
+    /// How many moves ahead the position was searched to find the value.
+    pub depth: u8,
+    /// Whether the value was stored for the reflection of the position, so any columns in it need reflecting too.
+    pub mirrored: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct TableEntry<T> {
+    key: u64,
+    depth: u8,
+    value: T,
+    /// Whether the value was stored for the reflection of the canonical position.
+    mirrored: bool,
+}
+
+/// Remembers results found when searching positions, so that positions reached again can be looked up instead of searched.
+///
+/// The table has a fixed number of entries, each shared by every position whose hash points to it.
+/// A result replaces the one already in its entry if it is for the same position, or was searched at least as deeply.
+/// Positions are stored under the hash of their canonical form, so a result also serves the mirror image of its position, and mirror images take up one entry between them.
+#[derive(Clone, Debug)]
+pub struct TranspositionTable<T> {
+    entries: Vec<Option<TableEntry<T>>>,
+}
+
+impl<T: Copy> TranspositionTable<T> {
+    /// Creates an empty table with room for `size` entries, which must not be zero.
+    pub fn new(size: usize) -> Self {
+        assert!(size > 0, "A transposition table needs at least one entry");
+        Self {
+            entries: vec![None; size],
+        }
+    }
+
+    /// Stores a result for the position, found by searching `depth` moves ahead.
+    pub fn insert(&mut self, state: &GameState, depth: u8, value: T) {
+        let mirrored = !state.get_board().is_canonical();
+        self.store(state.canonical_zobrist(), depth, value, mirrored);
+    }
+
+    /// Stores a result under a hash, for positions hashed some other way than [`GameState::canonical_zobrist`].
+    pub fn insert_key(&mut self, key: u64, depth: u8, value: T) {
+        self.store(key, depth, value, false);
+    }
+
+    /// Looks up the result stored for the position or its reflection.
+    pub fn get(&self, state: &GameState) -> Option<TableHit<T>> {
+        let mirrored = !state.get_board().is_canonical();
+        self.entry(state.canonical_zobrist()).map(|e| TableHit {
+            value: e.value,
+            depth: e.depth,
+            mirrored: e.mirrored != mirrored,
+        })
+    }
+
+    /// Looks up the result and search depth stored under a hash.
+    pub fn get_key(&self, key: u64) -> Option<(T, u8)> {
+        self.entry(key).map(|e| (e.value, e.depth))
+    }
+
+    /// Number of entries the table has room for.
+    pub fn capacity(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Forgets every stored result.
+    pub fn clear(&mut self) {
+        self.entries.fill(None);
+    }
+
+    fn store(&mut self, key: u64, depth: u8, value: T, mirrored: bool) {
+        let index = self.index(key);
+        let entry = &mut self.entries[index];
+        if entry.is_some_and(|e| e.key != key && e.depth > depth) {
+            return;
+        }
+        *entry = Some(TableEntry {
+            key,
+            depth,
+            value,
+            mirrored,
+        });
+    }
+
+    fn entry(&self, key: u64) -> Option<TableEntry<T>> {
+        self.entries[self.index(key)].filter(|e| e.key == key)
+    }
+
+    fn index(&self, key: u64) -> usize {
+        (key % self.entries.len() as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TableHit, TranspositionTable};
+    use crate::game::{board::GamePiece, state::GameState};
+
+    fn state(moves: &[usize]) -> GameState {
+        let mut state = GameState::with_board(Default::default(), GamePiece::Red);
+        for col in moves {
+            state.play_move(*col).unwrap();
+        }
+        state
+    }
+
+    #[test]
+    fn looks_up_positions_and_reflections() {
+        let mut table = TranspositionTable::new(1024);
+        table.insert(&state(&[4, 3]), 5, 'a');
+        let found = |value, depth, mirrored| {
+            Some(TableHit {
+                value,
+                depth,
+                mirrored,
+            })
+        };
+        assert_eq!(found('a', 5, false), table.get(&state(&[4, 3])));
+        assert_eq!(found('a', 5, true), table.get(&state(&[4, 5])));
+        assert_eq!(None, table.get(&state(&[4, 2])));
+        // Storing the reflection replaces the result for both
+        table.insert(&state(&[4, 5]), 6, 'b');
+        assert_eq!(found('b', 6, false), table.get(&state(&[4, 5])));
+        assert_eq!(found('b', 6, true), table.get(&state(&[4, 3])));
+        // The same board with the other player to move is a different position
+        let mut passed = state(&[4, 3]);
+        passed.toggle_player();
+        assert_eq!(None, table.get(&passed));
+
+        table.clear();
+        assert_eq!(None, table.get(&state(&[4, 3])));
+    }
+
+    #[test]
+    fn keeps_deeper_results() {
+        // With a single entry every position competes for it
+        let mut table = TranspositionTable::new(1);
+        let (first, second) = (state(&[1]), state(&[2]));
+        table.insert(&first, 4, 1);
+        table.insert(&second, 3, 2);
+        assert_eq!(Some((1, 4)), table.get_key(first.canonical_zobrist()));
+        assert_eq!(None, table.get_key(second.canonical_zobrist()));
+
+        // Results for the same position always replace each other, and equally deep ones replace others
+        table.insert(&first, 2, 3);
+        assert_eq!(Some((3, 2)), table.get_key(first.canonical_zobrist()));
+        table.insert(&second, 2, 4);
+        assert_eq!(Some((4, 2)), table.get_key(second.canonical_zobrist()));
+    }
+}
@@ -0,0 +1,79 @@
+use super::board::{GamePiece, MAX_COLS, MAX_PLAYERS, MAX_ROWS};
+use super::random::{mix, GOLDEN_GAMMA};
+
+/// Number of spaces on the largest board, each of which has a key per piece.
+const SPACES: usize = MAX_ROWS * MAX_COLS;
+
+/// Random keys for each piece in each space, which a position's hash combines with exclusive or.
+///
+/// The keys are fixed when compiling, so hashes are the same between runs and can be saved.
+const PIECE_KEYS: [[[u64; MAX_COLS]; MAX_ROWS]; MAX_PLAYERS] = piece_keys();
+/// Keys added to a game state's hash for the player to move, with none for Red.
+const PLAYER_KEYS: [u64; MAX_PLAYERS] = [
+    0,
+    key(MAX_PLAYERS * SPACES + 1),
+    key(MAX_PLAYERS * SPACES + 2),
+    key(MAX_PLAYERS * SPACES + 3),
+];
+
+/// Keys for each blocked space, so that boards with different layouts hash differently.
+const BLOCKED_KEYS: [[u64; MAX_COLS]; MAX_ROWS] = blocked_keys();
+
+/// The key numbered `n`, each of which comes from a different state of the generator.
+const fn key(n: usize) -> u64 {
+    mix(GOLDEN_GAMMA.wrapping_mul(n as u64 + 1))
+}
+
+const fn piece_keys() -> [[[u64; MAX_COLS]; MAX_ROWS]; MAX_PLAYERS] {
+    let mut keys = [[[0; MAX_COLS]; MAX_ROWS]; MAX_PLAYERS];
+    let mut n = 0;
+    while n < MAX_PLAYERS * SPACES {
+        let (piece, space) = (n / SPACES, n % SPACES);
+        keys[piece][space / MAX_COLS][space % MAX_COLS] = key(n);
+        n += 1;
+    }
+    keys
+}
+
+/// Numbered after every piece and player key.
+const fn blocked_keys() -> [[u64; MAX_COLS]; MAX_ROWS] {
+    let mut keys = [[0; MAX_COLS]; MAX_ROWS];
+    let mut space = 0;
+    while space < SPACES {
+        keys[space / MAX_COLS][space % MAX_COLS] = key(MAX_PLAYERS * SPACES + MAX_PLAYERS + space);
+        space += 1;
+    }
+    keys
+}
+
+/// Key for the given piece in a space, as (row, column) counted from the top-left and starting at zero.
+pub(crate) fn piece_key(piece: GamePiece, row: usize, col: usize) -> u64 {
+    PIECE_KEYS[piece.index()][row][col]
+}
+
+/// Key for a blocked space, as (row, column) counted from the top-left and starting at zero.
+pub(crate) fn blocked_key(row: usize, col: usize) -> u64 {
+    BLOCKED_KEYS[row][col]
+}
+
+/// Key for the player to move, so that the same board with different players to move hashes differently.
+pub(crate) fn player_key(piece: GamePiece) -> u64 {
+    PLAYER_KEYS[piece.index()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_are_distinct() {
+        let mut keys = PIECE_KEYS.concat().concat();
+        keys.extend(&PLAYER_KEYS[1..]);
+        keys.extend(BLOCKED_KEYS.concat());
+        let count = keys.len();
+        keys.sort_unstable();
+        keys.dedup();
+        assert_eq!(count, keys.len());
+        assert!(!keys.contains(&0));
+    }
+}
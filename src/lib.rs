@@ -0,0 +1,47 @@
+//! FourStack, a game of dropping pieces into columns to make four in a row.
+//!
+//! The game itself lives in [`game`]: boards, whose turn it is, the rules games are played by and how positions are written down.
+//! The built-in AI is in `client::ai`, and `client::ai::solver` scores positions exactly.
+//! The rest of the crate is the `fourstack` program built on top of these.
+//!
+//! Parts that need extra dependencies can be left out with Cargo features, all of which are on by default:
+//! `cli` for the `fourstack` program and its config file, `tui` for the full-screen interface, `ai` for the AI and solver, and `online` for network play.
+//! Boards, positions and settings can be serialized with `serde`, which `cli` and `ai` turn on.
+//!
+//! ```
+//! use fourstack::{notation, EndgameType, GameBoard, GamePiece, GameState};
+//!
+//! let mut state = GameState::with_board(GameBoard::default(), GamePiece::Red);
+//! for column in [4, 4, 5, 5, 6, 6, 7] {
+//!     state.play_move(column).unwrap();
+//! }
+//! assert_eq!(EndgameType::Win(GamePiece::Red), state.check_endgame());
+//! assert!(notation::to_notation(&state).ends_with("/...YYY./...RRRR Y"));
+//! ```
+
+/// Non-interactive solving of positions and analysis of recorded games.
+#[cfg(feature = "ai")]
+pub mod analysis;
+/// Handles moves, updating game state, and communication to server(s).
+pub mod client;
+/// A text protocol through which other programs can ask the AI for moves.
+#[cfg(feature = "ai")]
+pub mod engine;
+/// Core components that comprise the game itself.
+pub mod game;
+/// Arbitrates the lifecycle of the game.
+pub mod manager;
+/// User preferences and their config file.
+pub mod settings;
+/// UI-related functionality.
+pub mod view;
+
+#[cfg(feature = "ai")]
+pub use client::ai::{choose_move, solver::Solver};
+pub use game::{
+    board::{GameBoard, GamePiece, InsertError, SizeError},
+    notation,
+    rules::Ruleset,
+    state::{EndgameType, GameState, Move},
+    FreeForAll, GameMode, Variant,
+};
@@ -1,27 +1,232 @@
-/// Handles moves, updating game state, and communication to server(s).
-pub mod client;
-/// Core components that comprise the game itself.
-pub mod game;
-/// Arbitrates the lifecycle of the game.
-pub mod manager;
-/// UI-related functionality.
-pub mod view;
-
-use crate::client::{ai::AiGameClient, local::LocalGameClient, GameClient};
-use crate::game::GameMode;
-use crate::manager::{FourStackGame, GameManager};
-use crate::view::{tui::TuiManager, ViewManager};
+/// Command-line arguments and subcommands.
+mod cli;
+
+use crate::cli::{Cli, Command};
+#[cfg(feature = "ai")]
+use fourstack::client::ai::AiGameClient;
+#[cfg(feature = "online")]
+use fourstack::client::online::OnlineGameClient;
+use fourstack::client::{external::ExternalEngineClient, local::LocalGameClient, GameClient};
+use fourstack::game::{self, GameMode};
+use fourstack::manager::{FourStackGame, GameManager};
+use fourstack::settings::{Settings, SettingsError};
+use fourstack::view::{plain::PlainManager, MenuChoice, ViewManager};
+#[cfg(feature = "ai")]
+use fourstack::{analysis, engine};
+
+use clap::Parser;
+use std::io::{self, IsTerminal};
 
 fn main() {
+    let cli = Cli::parse();
+    if let Some(seed) = cli.seed {
+        game::random::seed(seed);
+    }
+    #[cfg(feature = "ai")]
+    if let Some(result) = cli.command.as_ref().and_then(run_analysis) {
+        if let Err(e) = result {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+    let settings = Settings::load();
+    let view_settings = settings.clone().unwrap_or_default();
+
+    if cli.narrate {
+        let view_manager = PlainManager::default().with_settings(view_settings);
+        run_games(view_manager.narrated(), settings, cli.command);
+        return;
+    }
+    // Piped input can't drive the full-screen interface, so fall back to plain text
+    if cli.plain || !cfg!(feature = "tui") || !io::stdin().is_terminal() {
+        let view_manager = PlainManager::default().with_settings(view_settings);
+        run_games(view_manager, settings, cli.command);
+    } else {
+        #[cfg(feature = "tui")]
+        run_tui(view_settings, settings, cli.command);
+    }
+}
+
+/// Plays games in the full-screen interface.
+#[cfg(feature = "tui")]
+fn run_tui(
+    view_settings: Settings,
+    settings: Result<Settings, SettingsError>,
+    command: Option<Command>,
+) {
+    let (runtime, view_manager) = fourstack::view::tui::create(view_settings);
+
+    // The game runs on its own thread and drives the UI event loop, which must stay on the main thread
+    let game = std::thread::spawn(move || run_games(view_manager, settings, command));
+
+    // If the user quits early the game is left waiting for input, and simply ends with the process
+    if runtime.run() {
+        if let Err(panic) = game.join() {
+            std::panic::resume_unwind(panic);
+        }
+    }
+}
+
+/// Runs commands that print their results rather than opening a view, or returns [`None`] for other commands.
+#[cfg(feature = "ai")]
+fn run_analysis(command: &Command) -> Option<Result<(), String>> {
+    match command {
+        Command::Solve { batch: true, .. } => Some(analysis::solve_batch(io::stdin().lock())),
+        Command::Solve {
+            position: Some(position),
+            ..
+        } => Some(analysis::solve(position)),
+        Command::Analyze { file } => Some(analysis::analyze(file)),
+        Command::Engine => Some(engine::Engine::default().run(io::stdin().lock(), io::stdout())),
+        _ => None,
+    }
+}
+
+/// Something for the game to do next, either chosen on the main menu or given on the command line.
+enum Action {
+    Menu(MenuChoice),
+    #[cfg(feature = "online")]
+    Host(u16),
+    #[cfg(feature = "online")]
+    Connect(String),
+    Replay(String),
+}
+
+/// Starts with the command given on the command line, if any, then shows the main menu and plays the chosen games until the user quits.
+///
+/// If the saved settings could not be loaded, the user is told why and the defaults are used instead.
+fn run_games(
+    mut view_manager: impl ViewManager,
+    settings: Result<Settings, SettingsError>,
+    command: Option<Command>,
+) {
+    let mut settings = settings.unwrap_or_else(|e| {
+        view_manager.show_error(format!("{e} Using default settings."));
+        Settings::default()
+    });
+    if let Some(command) = &command {
+        let mut overridden = settings.clone();
+        command.apply_to(&mut overridden);
+        match overridden.validate() {
+            Ok(()) => settings = overridden,
+            Err(e) => view_manager.show_error(format!("{e} Ignoring the game options given.")),
+        }
+    }
+    let mut next_action = command.map(|command| match command {
+        // An engine can only be played against in place of the AI
+        Command::Play { mode, engine, .. } => {
+            Action::Menu(MenuChoice::Play(match (mode, engine) {
+                (Some(mode), _) => mode.into(),
+                (None, Some(_)) => GameMode::Ai,
+                (None, None) => settings.game.mode,
+            }))
+        }
+        #[cfg(feature = "online")]
+        Command::Host { port } => Action::Host(port),
+        #[cfg(feature = "online")]
+        Command::Connect { address } => Action::Connect(address),
+        Command::Replay { file } => Action::Replay(file),
+        #[cfg(feature = "ai")]
+        Command::Solve { .. } | Command::Analyze { .. } | Command::Engine => {
+            unreachable!("Analysis and engine commands run without a view")
+        }
+    });
+
     loop {
-        let mut view_manager = TuiManager::default();
-        let game_client: Box<dyn GameClient> = match view_manager.main_menu() {
-            GameMode::Ai => Box::<AiGameClient>::default(),
-            GameMode::Local => Box::<LocalGameClient>::default(),
-            GameMode::Online => todo!(),
+        let action = next_action
+            .take()
+            .unwrap_or_else(|| Action::Menu(view_manager.main_menu()));
+        let game_client: Box<dyn GameClient> = match action {
+            Action::Menu(MenuChoice::Play(GameMode::Ai)) if !settings.ai.engine.is_empty() => {
+                view_manager.show_status(format!("Starting {}...", settings.ai.engine));
+                match ExternalEngineClient::new(&settings) {
+                    Ok(client) => Box::new(client),
+                    Err(e) => {
+                        view_manager.show_error(e);
+                        continue;
+                    }
+                }
+            }
+            #[cfg(feature = "ai")]
+            Action::Menu(MenuChoice::Play(GameMode::Ai)) => match AiGameClient::new(&settings) {
+                Ok(client) => Box::new(client),
+                Err(e) => {
+                    view_manager.show_error(e);
+                    continue;
+                }
+            },
+            #[cfg(not(feature = "ai"))]
+            Action::Menu(MenuChoice::Play(GameMode::Ai)) => {
+                view_manager.show_message(
+                    "This copy of FourStack was built without the AI. Play against an engine with `fourstack play --engine <command>` instead.",
+                );
+                continue;
+            }
+            Action::Menu(MenuChoice::Play(GameMode::Local)) => {
+                match LocalGameClient::new(&settings) {
+                    Ok(client) => Box::new(client),
+                    Err(e) => {
+                        view_manager.show_error(e);
+                        continue;
+                    }
+                }
+            }
+            Action::Menu(MenuChoice::Play(GameMode::Online)) => {
+                view_manager.show_message(match cfg!(feature = "online") {
+                    true => "To play online, start FourStack with `fourstack host` or `fourstack connect <address>`.",
+                    false => "This copy of FourStack was built without online play.",
+                });
+                continue;
+            }
+            #[cfg(feature = "online")]
+            Action::Host(port) => {
+                let hosted = OnlineGameClient::listen(port).and_then(|listener| {
+                    view_manager.show_status(format!(
+                        "Waiting for an opponent to connect on port {port}..."
+                    ));
+                    OnlineGameClient::host(&settings, &listener)
+                });
+                match hosted {
+                    Ok(client) => Box::new(client),
+                    Err(e) => {
+                        view_manager.show_error(e);
+                        continue;
+                    }
+                }
+            }
+            #[cfg(feature = "online")]
+            Action::Connect(address) => {
+                view_manager.show_status(format!("Connecting to {address}..."));
+                match OnlineGameClient::connect(&settings, address.as_str()) {
+                    Ok(client) => Box::new(client),
+                    Err(e) => {
+                        view_manager.show_error(e);
+                        continue;
+                    }
+                }
+            }
+            Action::Menu(MenuChoice::Replays) => {
+                view_manager.show_replays(None);
+                continue;
+            }
+            Action::Replay(file) => {
+                view_manager.show_replays(Some(&file));
+                continue;
+            }
+            Action::Menu(MenuChoice::Settings) => {
+                if let Some(new_settings) = view_manager.show_settings() {
+                    if let Err(e) = new_settings.save() {
+                        view_manager.show_error(e.to_string());
+                    }
+                    settings = new_settings;
+                }
+                continue;
+            }
+            Action::Menu(MenuChoice::Quit) => break,
         };
 
-        let mut game_manager = GameManager::new(game_client, view_manager);
+        let mut game_manager = GameManager::new(game_client, &mut view_manager);
         if !game_manager.game_loop() {
             break;
         }
@@ -1,9 +1,12 @@
 use crate::client::GameClient;
-use crate::game::state::EndgameType;
-use crate::view::ViewManager;
+use crate::game::{
+    record,
+    state::{EndgameType, Move},
+};
+use crate::view::{EndgameChoice, TurnChoice, ViewManager};
 
 /// High-level functions for the game implementation.
-pub(crate) trait FourStackGame {
+pub trait FourStackGame {
     /// Main game loop that runs continuously until and endgame state is reached.
     ///
     /// Returns a [`bool`] when the loop exits; if `true` the user wants to start a new game.
@@ -26,22 +29,49 @@ impl<V> GameManager<V> {
     }
 }
 
+impl<V: ViewManager> GameManager<V> {
+    /// Appends a record of the current game to the export file and reports the outcome.
+    fn export_game(&mut self) {
+        match record::append_record(record::DEFAULT_RECORD_FILE, &self.client.get_record()) {
+            Ok(_) => self
+                .view_manager
+                .show_message(format!("Game saved to {}", record::DEFAULT_RECORD_FILE)),
+            Err(e) => self
+                .view_manager
+                .show_error(format!("Could not save game: {e}")),
+        }
+    }
+}
+
 impl<V: ViewManager> FourStackGame for GameManager<V> {
     fn game_loop(&mut self) -> bool {
         loop {
+            if let Err(e) = self.client.play_opponent_move() {
+                self.view_manager.show_error(e);
+            }
             let game_state = self.client.get_current_state();
-            match game_state.check_endgame() {
+            match self.client.get_endgame() {
                 EndgameType::None => (),
-                s => {
-                    return self.view_manager.show_endgame(game_state.get_board(), &s);
-                }
+                s => loop {
+                    match self.view_manager.show_endgame(game_state.get_board(), &s) {
+                        EndgameChoice::PlayAgain => return true,
+                        EndgameChoice::Quit => return false,
+                        EndgameChoice::Export => self.export_game(),
+                    }
+                },
             }
 
-            let col_choice = self
-                .view_manager
-                .get_column_selection(game_state.get_board(), game_state.get_next_player());
+            let turn_choice = self.view_manager.get_turn_choice(&game_state);
 
-            if let Err(e) = self.client.handle_input_move(col_choice) {
+            let result = match turn_choice {
+                TurnChoice::Drop(col_choice) => {
+                    self.client.handle_input_move(Move::Drop(col_choice))
+                }
+                TurnChoice::Pop(col_choice) => self.client.handle_input_move(Move::Pop(col_choice)),
+                TurnChoice::Undo => self.client.undo_move(),
+                TurnChoice::Quit => return false,
+            };
+            if let Err(e) = result {
                 self.view_manager.show_error(e);
             }
         }
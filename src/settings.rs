@@ -0,0 +1,500 @@
+use crate::game::board::{
+    GameBoard, GamePiece, SizeError, GRID_COLS, GRID_ROWS, MIN_PLAYERS, WIN_LENGTH,
+};
+use crate::game::layout::{self, LayoutError};
+use crate::game::{FreeForAll, GameMode, Variant};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Directory for FourStack's files within the user's config directory.
+#[cfg(feature = "cli")]
+const CONFIG_DIR: &str = "fourstack";
+#[cfg(feature = "cli")]
+const CONFIG_FILE: &str = "config.toml";
+
+/// Occurs when settings cannot be loaded or saved.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SettingsError {
+    /// Neither `XDG_CONFIG_HOME` nor `HOME` is set, so there is nowhere to keep the config file.
+    NoConfigDir,
+    /// The config file could not be read or written.
+    Io(String),
+    /// The config file is not valid TOML or has unexpected values.
+    Parse(String),
+    /// The board settings do not describe a playable board.
+    Board(SizeError),
+    /// The blocked spaces cannot be placed on the board.
+    Layout(LayoutError),
+    /// The configured rules can't be played by this many players.
+    Players { requested: usize, max: usize },
+    /// The player chosen to move first is not taking part.
+    FirstPlayer(GamePiece),
+}
+
+impl Display for SettingsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::NoConfigDir => {
+                write!(f, "Could not find a config directory to keep settings in.")
+            }
+            Self::Io(e) => write!(f, "Could not access settings file: {e}"),
+            Self::Parse(e) => write!(f, "Settings file is invalid: {e}"),
+            Self::Board(e) => write!(f, "Invalid board settings: {e}"),
+            Self::Layout(e) => write!(f, "Invalid blocked spaces: {e}"),
+            Self::Players { requested, max } => write!(
+                f,
+                "Can't play with {requested} players, these rules allow {MIN_PLAYERS} to {max}."
+            ),
+            Self::FirstPlayer(piece) => {
+                write!(f, "{piece} can't move first, as they are not playing.")
+            }
+        }
+    }
+}
+
+/// How strongly the AI plays.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum AiLevel {
+    Easy,
+    Medium,
+    #[default]
+    Hard,
+}
+
+impl AiLevel {
+    /// How many moves ahead the AI searches at this level.
+    pub fn depth(&self) -> u8 {
+        match self {
+            Self::Easy => 2,
+            Self::Medium => 4,
+            Self::Hard => 7,
+        }
+    }
+}
+
+impl Display for AiLevel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Easy => write!(f, "Easy"),
+            Self::Medium => write!(f, "Medium"),
+            Self::Hard => write!(f, "Hard"),
+        }
+    }
+}
+
+/// Which colour moves first in a new game.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum FirstPlayer {
+    #[default]
+    Random,
+    Red,
+    Yellow,
+    Green,
+    Blue,
+}
+
+impl FirstPlayer {
+    pub const ALL: [Self; 5] = [
+        Self::Random,
+        Self::Red,
+        Self::Yellow,
+        Self::Green,
+        Self::Blue,
+    ];
+
+    /// The piece chosen to move first, unless it is chosen at random.
+    fn piece(&self) -> Option<GamePiece> {
+        match self {
+            Self::Random => None,
+            Self::Red => Some(GamePiece::Red),
+            Self::Yellow => Some(GamePiece::Yellow),
+            Self::Green => Some(GamePiece::Green),
+            Self::Blue => Some(GamePiece::Blue),
+        }
+    }
+
+    /// Picks the piece that moves first in a game between the given number of players.
+    pub fn pick(&self, players: usize) -> GamePiece {
+        self.piece()
+            .unwrap_or_else(|| GamePiece::random_among(players))
+    }
+}
+
+impl Display for FirstPlayer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Random => write!(f, "Random"),
+            Self::Red => write!(f, "Red"),
+            Self::Yellow => write!(f, "Yellow"),
+            Self::Green => write!(f, "Green"),
+            Self::Blue => write!(f, "Blue"),
+        }
+    }
+}
+
+/// Characters used to draw the board in the terminal UI.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum GlyphStyle {
+    /// Box drawing and discs where the terminal supports colour, plain ASCII otherwise.
+    #[default]
+    Auto,
+    Unicode,
+    Ascii,
+}
+
+impl Display for GlyphStyle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Auto => write!(f, "Auto"),
+            Self::Unicode => write!(f, "Unicode"),
+            Self::Ascii => write!(f, "ASCII"),
+        }
+    }
+}
+
+/// Terminal colours that pieces can be drawn in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum PieceColour {
+    Red,
+    Yellow,
+    Green,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+}
+
+impl PieceColour {
+    pub const ALL: [Self; 7] = [
+        Self::Red,
+        Self::Yellow,
+        Self::Green,
+        Self::Blue,
+        Self::Magenta,
+        Self::Cyan,
+        Self::White,
+    ];
+}
+
+impl Display for PieceColour {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Red => write!(f, "Red"),
+            Self::Yellow => write!(f, "Yellow"),
+            Self::Green => write!(f, "Green"),
+            Self::Blue => write!(f, "Blue"),
+            Self::Magenta => write!(f, "Magenta"),
+            Self::Cyan => write!(f, "Cyan"),
+            Self::White => write!(f, "White"),
+        }
+    }
+}
+
+/// Rules and defaults for new games.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct GameSettings {
+    /// Mode highlighted on the main menu.
+    pub mode: GameMode,
+    pub first_player: FirstPlayer,
+    pub rows: usize,
+    pub cols: usize,
+    /// Pieces in a row needed to win.
+    pub win_length: usize,
+    /// Rules that new games are played by.
+    pub variant: Variant,
+    /// Number of neutral spaces to block at random on each new board.
+    pub blockers: usize,
+    /// File giving the spaces to block on each new board, used instead of blocking them at random.
+    pub layout: Option<PathBuf>,
+    /// Number of players taking turns, from two up to four in local and AI games.
+    pub players: usize,
+    /// How games between more than two players are decided.
+    pub free_for_all: FreeForAll,
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        Self {
+            mode: GameMode::default(),
+            first_player: FirstPlayer::default(),
+            rows: GRID_ROWS,
+            cols: GRID_COLS,
+            win_length: WIN_LENGTH,
+            variant: Variant::default(),
+            blockers: 0,
+            layout: None,
+            players: MIN_PLAYERS,
+            free_for_all: FreeForAll::default(),
+        }
+    }
+}
+
+/// How the AI opponent plays.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct AiSettings {
+    pub level: AiLevel,
+    /// Most time the AI should spend choosing a move, in milliseconds.
+    pub think_time_ms: u64,
+    /// Whether the human makes the first move, rather than the AI.
+    pub human_first: bool,
+    /// Command to start an engine program to play against instead of the built-in AI, with any arguments separated by spaces, or empty to use the built-in AI.
+    pub engine: String,
+}
+
+impl Default for AiSettings {
+    fn default() -> Self {
+        Self {
+            level: AiLevel::default(),
+            think_time_ms: 2000,
+            human_first: true,
+            engine: String::new(),
+        }
+    }
+}
+
+impl AiSettings {
+    pub fn think_time(&self) -> Duration {
+        Duration::from_millis(self.think_time_ms)
+    }
+}
+
+/// Names shown and recorded for each player; blank names fall back to the piece colour.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct PlayerSettings {
+    pub red: String,
+    pub yellow: String,
+    pub green: String,
+    pub blue: String,
+}
+
+/// How the board is drawn in the terminal UI.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct AppearanceSettings {
+    pub glyphs: GlyphStyle,
+    pub red_colour: PieceColour,
+    pub yellow_colour: PieceColour,
+    pub green_colour: PieceColour,
+    pub blue_colour: PieceColour,
+}
+
+impl Default for AppearanceSettings {
+    fn default() -> Self {
+        Self {
+            glyphs: GlyphStyle::default(),
+            red_colour: PieceColour::Red,
+            yellow_colour: PieceColour::Yellow,
+            green_colour: PieceColour::Green,
+            blue_colour: PieceColour::Blue,
+        }
+    }
+}
+
+impl AppearanceSettings {
+    /// The colour a player's pieces are drawn in.
+    pub fn colour(&self, piece: GamePiece) -> PieceColour {
+        match piece {
+            GamePiece::Red => self.red_colour,
+            GamePiece::Yellow => self.yellow_colour,
+            GamePiece::Green => self.green_colour,
+            GamePiece::Blue => self.blue_colour,
+        }
+    }
+}
+
+/// User preferences, kept in a TOML file in the XDG config directory.
+///
+/// Missing values in the file take their defaults, so older or hand-written files keep working.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct Settings {
+    pub game: GameSettings,
+    pub ai: AiSettings,
+    pub players: PlayerSettings,
+    pub appearance: AppearanceSettings,
+}
+
+impl Settings {
+    /// Location of the config file: `$XDG_CONFIG_HOME/fourstack/config.toml`, or under `~/.config` if that is not set.
+    #[cfg(feature = "cli")]
+    pub fn path() -> Option<PathBuf> {
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|p| p.is_absolute())
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(config_home.join(CONFIG_DIR).join(CONFIG_FILE))
+    }
+
+    /// Loads the saved settings, or the defaults if none have been saved yet.
+    #[cfg(feature = "cli")]
+    pub fn load() -> Result<Self, SettingsError> {
+        let path = Self::path().ok_or(SettingsError::NoConfigDir)?;
+        match std::fs::read_to_string(path) {
+            Ok(text) => Self::from_toml(&text),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(SettingsError::Io(e.to_string())),
+        }
+    }
+
+    /// Saves the settings, creating the config directory if needed.
+    #[cfg(feature = "cli")]
+    pub fn save(&self) -> Result<(), SettingsError> {
+        let path = Self::path().ok_or(SettingsError::NoConfigDir)?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| SettingsError::Io(e.to_string()))?;
+        }
+        std::fs::write(path, self.to_toml()).map_err(|e| SettingsError::Io(e.to_string()))
+    }
+
+    /// Reads and validates settings written as TOML.
+    #[cfg(feature = "cli")]
+    pub fn from_toml(text: &str) -> Result<Self, SettingsError> {
+        let settings: Self =
+            toml::from_str(text).map_err(|e| SettingsError::Parse(e.to_string()))?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    #[cfg(feature = "cli")]
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).expect("Settings should always serialize")
+    }
+
+    /// Checks that the settings describe a playable game.
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        let game = &self.game;
+        let max = game.variant.rules().max_players();
+        if !(MIN_PLAYERS..=max).contains(&game.players) {
+            return Err(SettingsError::Players {
+                requested: game.players,
+                max,
+            });
+        }
+        if let Some(first) = game.first_player.piece() {
+            if first.index() >= game.players {
+                return Err(SettingsError::FirstPlayer(first));
+            }
+        }
+        self.new_board().map(|_| ())
+    }
+
+    /// Creates an empty board with the configured size, win length and blocked spaces, if the configured rules can be played on it.
+    ///
+    /// Spaces are blocked at random each time unless a layout file is given, so two boards from the same settings may differ.
+    pub fn new_board(&self) -> Result<GameBoard, SettingsError> {
+        let game = &self.game;
+        let mut board = game
+            .variant
+            .rules()
+            .new_board(game.rows, game.cols, game.win_length)
+            .map_err(SettingsError::Board)?;
+        match &game.layout {
+            Some(path) => layout::read(&mut board, path),
+            None => layout::scatter(&mut board, game.blockers),
+        }
+        .map_err(SettingsError::Layout)?;
+        Ok(board)
+    }
+
+    /// Name to show for the given player, which is their colour unless they have set one.
+    pub fn player_name(&self, piece: GamePiece) -> String {
+        let name = match piece {
+            GamePiece::Red => &self.players.red,
+            GamePiece::Yellow => &self.players.yellow,
+            GamePiece::Green => &self.players.green,
+            GamePiece::Blue => &self.players.blue,
+        };
+        match name.trim() {
+            "" => piece.name().to_string(),
+            name => name.to_string(),
+        }
+    }
+}
+
+// Every test here goes through the config file format
+#[cfg(all(test, feature = "cli"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toml_round_trip() {
+        let mut settings = Settings::default();
+        settings.game.mode = GameMode::Local;
+        settings.game.cols = 9;
+        settings.ai.level = AiLevel::Easy;
+        settings.players.red = "Alice".to_string();
+        settings.appearance.yellow_colour = PieceColour::Cyan;
+
+        let text = settings.to_toml();
+        assert!(text.contains("mode = \"local\""));
+        assert!(text.contains("level = \"easy\""));
+        assert_eq!(Ok(settings), Settings::from_toml(&text));
+    }
+
+    #[test]
+    fn missing_values_use_defaults() {
+        let settings = Settings::from_toml("[players]\nyellow = \"Bob\"\n").unwrap();
+        assert_eq!("Bob", settings.player_name(GamePiece::Yellow));
+        assert_eq!("Red", settings.player_name(GamePiece::Red));
+        assert_eq!(GameSettings::default(), settings.game);
+        assert_eq!(Ok(GameBoard::default()), settings.new_board());
+    }
+
+    #[test]
+    fn rejects_invalid_settings() {
+        assert_eq!(
+            Err(SettingsError::Board(SizeError::Rows(20))),
+            Settings::from_toml("[game]\nrows = 20\n")
+        );
+        assert_eq!(
+            Err(SettingsError::Layout(LayoutError::TooManyBlockers {
+                requested: 11,
+                max: 10
+            })),
+            Settings::from_toml("[game]\nblockers = 11\n")
+        );
+        assert_eq!(
+            Err(SettingsError::Players {
+                requested: 3,
+                max: 2
+            }),
+            Settings::from_toml("[game]\nvariant = \"popout\"\nplayers = 3\n")
+        );
+        assert_eq!(
+            Err(SettingsError::FirstPlayer(GamePiece::Blue)),
+            Settings::from_toml("[game]\nplayers = 3\nfirst_player = \"blue\"\n")
+        );
+        assert!(Settings::from_toml("[game]\nplayers = 4\nfirst_player = \"blue\"\n").is_ok());
+        assert!(matches!(
+            Settings::from_toml("[ai]\nlevel = \"impossible\"\n"),
+            Err(SettingsError::Parse(_))
+        ));
+    }
+}
@@ -1,27 +1,112 @@
-pub(crate) mod tui;
+mod narration;
+pub mod plain;
+mod settings_form;
+#[cfg(feature = "tui")]
+pub mod tui;
 
 use crate::game::GameMode;
 use crate::game::{
-    board::{GameBoard, GamePiece},
-    state::EndgameType,
+    board::GameBoard,
+    state::{EndgameType, GameState},
 };
+use crate::settings::Settings;
+
+/// Possible user choices on the main menu.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MenuChoice {
+    /// Start a new game in the given [`GameMode`].
+    Play(GameMode),
+    /// Browse and watch recorded games.
+    Replays,
+    /// Change and save the user's [`Settings`].
+    Settings,
+    /// Exit the program.
+    Quit,
+}
+
+/// Possible user choices when it is their turn.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TurnChoice {
+    /// Drop a piece into the given column, numbered from 1.
+    Drop(usize),
+    /// Pop the user's own piece out of the bottom of the given column, numbered from 1.
+    Pop(usize),
+    /// Take back the user's last move.
+    Undo,
+    /// Abandon the game and exit the program.
+    Quit,
+}
+
+/// Possible user choices on the endgame screen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EndgameChoice {
+    /// Start a new game.
+    PlayAgain,
+    /// Save a record of the finished game, then return to the endgame screen.
+    Export,
+    /// Exit the program.
+    Quit,
+}
 
 /// Defines the behavior of UI components, mainly displaying individual views/screens.
 pub trait ViewManager {
     /// Displays the main menu, including the title splash and game mode selection menu.
     ///
-    /// Returns the user-selected [`GameMode`].
-    fn main_menu(&mut self) -> GameMode;
+    /// Returns the user-selected [`MenuChoice`].
+    fn main_menu(&mut self) -> MenuChoice;
+    /// Lets the user open a file of game records and step through a chosen game move by move.
+    ///
+    /// If `file` is given it is opened straight away, rather than asking the user which file to open.
+    fn show_replays(&mut self, file: Option<&str>);
+    /// Lets the user change the [`Settings`] the view was created with.
+    ///
+    /// Returns the new settings if the user saved them, which the view also uses from then on, or [`None`] if they cancelled.
+    fn show_settings(&mut self) -> Option<Settings>;
     /// Displays a given [`GameBoard`].
     fn show_board(&mut self, board: &GameBoard);
-    /// Displays the game board and prompts the user for input to get a column number selection.
+    /// Displays the game board and prompts the player to move for their turn, usually a column number selection.
     ///
-    /// Returns the column selection.
-    fn get_column_selection(&mut self, board: &GameBoard, player: &GamePiece) -> usize;
+    /// Returns the user's [`TurnChoice`].
+    fn get_turn_choice(&mut self, state: &GameState) -> TurnChoice;
     /// Displays an error message to the user.
     fn show_error(&mut self, error: impl Into<String>);
-    /// Shows the endgame board state when a game is over and asks the user if they want to play again.
+    /// Displays an informational message to the user.
+    fn show_message(&mut self, message: impl Into<String>);
+    /// Tells the user what the game is waiting for, without waiting for them to respond.
+    fn show_status(&mut self, status: impl Into<String>);
+    /// Shows the endgame board state when a game is over and asks the user what to do next.
     ///
-    /// Returns user choice to play again where yes = true.
-    fn show_endgame(&mut self, board: &GameBoard, state: &EndgameType) -> bool;
+    /// Returns the user's [`EndgameChoice`].
+    fn show_endgame(&mut self, board: &GameBoard, state: &EndgameType) -> EndgameChoice;
+}
+
+/// Lets a long-lived view manager be lent out to each game in turn.
+impl<V: ViewManager> ViewManager for &mut V {
+    fn main_menu(&mut self) -> MenuChoice {
+        (**self).main_menu()
+    }
+    fn show_replays(&mut self, file: Option<&str>) {
+        (**self).show_replays(file)
+    }
+    fn show_settings(&mut self) -> Option<Settings> {
+        (**self).show_settings()
+    }
+    fn show_board(&mut self, board: &GameBoard) {
+        (**self).show_board(board)
+    }
+    fn get_turn_choice(&mut self, state: &GameState) -> TurnChoice {
+        (**self).get_turn_choice(state)
+    }
+    fn show_error(&mut self, error: impl Into<String>) {
+        (**self).show_error(error)
+    }
+    fn show_message(&mut self, message: impl Into<String>) {
+        (**self).show_message(message)
+    }
+    fn show_status(&mut self, status: impl Into<String>) {
+        (**self).show_status(status)
+    }
+    fn show_endgame(&mut self, board: &GameBoard, state: &EndgameType) -> EndgameChoice {
+        (**self).show_endgame(board, state)
+    }
 }
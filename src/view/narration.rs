@@ -0,0 +1,242 @@
+use crate::game::{
+    board::{GameBoard, GamePiece, MIN_PLAYERS},
+    state::{EndgameType, GameState, Move},
+    FreeForAll,
+};
+
+use std::cmp::Reverse;
+
+/// Converts a row index counted from the top of the board into a row number counted from the bottom, starting at one.
+fn row_number(board: &GameBoard, row: usize) -> usize {
+    board.rows() - row
+}
+
+/// Describes how the board changed between two positions, such as pieces that were dropped or taken back.
+///
+/// When more than one piece was dropped, they are described in turn order, ending with the piece belonging to `last_mover`.
+/// When `pops` is set, pieces may have been popped out of the bottom of a column as well.
+pub(crate) fn describe_changes(
+    old: &GameBoard,
+    new: &GameBoard,
+    last_mover: Option<GamePiece>,
+    pops: bool,
+) -> Vec<String> {
+    let mut dropped = Vec::new();
+    let mut removed = Vec::new();
+    let mut popped = Vec::new();
+    for col in 0..new.cols() {
+        if let Some(piece) = popped_piece(old, new, col).filter(|_| pops) {
+            popped.push((piece, col));
+            continue;
+        }
+        // Bottom first, so that stacked pieces are described in the order they landed
+        for row in (0..new.rows()).rev() {
+            match (old.get_piece(row, col), new.get_piece(row, col)) {
+                (None, Some(piece)) => dropped.push((piece, row, col)),
+                (Some(piece), None) => removed.push((piece, row, col)),
+                _ => (),
+            }
+        }
+    }
+    if let Some(last_mover) = last_mover {
+        dropped.sort_by_key(|(piece, _, _)| Reverse(piece.turns_before(last_mover)));
+    }
+
+    let dropped = dropped.into_iter().map(|(piece, row, col)| {
+        format!(
+            "{} dropped in column {}, landing on row {}.",
+            piece.name(),
+            col + 1,
+            row_number(new, row)
+        )
+    });
+    let removed = removed.into_iter().map(|(piece, row, col)| {
+        format!(
+            "{}'s piece in column {}, row {} was taken back.",
+            piece.name(),
+            col + 1,
+            row_number(new, row)
+        )
+    });
+    let popped = popped.into_iter().map(|(piece, col)| {
+        format!(
+            "{} popped a piece out of the bottom of column {}.",
+            piece.name(),
+            col + 1
+        )
+    });
+    removed.chain(popped).chain(dropped).collect()
+}
+
+/// Finds the piece popped out of the bottom of a column, if the rest of the column slid down by one.
+///
+/// A column whose pieces all match looks the same whether its bottom piece was popped or its top piece taken back, and is taken to be a pop.
+fn popped_piece(old: &GameBoard, new: &GameBoard, col: usize) -> Option<GamePiece> {
+    let column = |board: &GameBoard| {
+        (0..board.rows())
+            .rev()
+            .filter_map(|row| board.get_piece(row, col))
+            .collect::<Vec<_>>()
+    };
+    let (old, new) = (column(old), column(new));
+    let (bottom, rest) = old.split_first()?;
+    (rest == new.as_slice()).then_some(*bottom)
+}
+
+/// Describes a move as it was played, e.g. "dropped in column 4".
+pub(crate) fn describe_move(game_move: Move) -> String {
+    match game_move {
+        Move::Drop(col) => format!("dropped in column {col}"),
+        Move::Pop(col) => format!("popped out of column {col}"),
+    }
+}
+
+/// Describes the pieces and blocked spaces in a column from the bottom up, with columns numbered from one.
+pub(crate) fn describe_column(board: &GameBoard, col: usize) -> String {
+    let pieces = (0..board.rows())
+        .rev()
+        .filter_map(|row| match board.get_piece(row, col - 1) {
+            Some(piece) => Some(piece.name()),
+            None if board.is_blocked(row, col - 1) => Some("a blocked space"),
+            None => None,
+        })
+        .collect::<Vec<_>>();
+
+    match pieces.len() {
+        0 => format!("Column {col} is empty."),
+        _ if !board.has_room(col) => {
+            format!("Column {col} is full: {} from bottom.", pieces.join(", "))
+        }
+        _ => format!("Column {col} has {} from bottom.", pieces.join(", ")),
+    }
+}
+
+/// Describes every column of the board.
+pub(crate) fn describe_board(board: &GameBoard) -> Vec<String> {
+    (1..=board.cols())
+        .map(|col| describe_column(board, col))
+        .collect()
+}
+
+/// Announces any columns where a player still in the game could complete a line on their next move, starting with the player to move.
+///
+/// When completing a line loses, these are announced as columns to avoid rather than as wins and threats.
+pub(crate) fn describe_threats(state: &GameState) -> Vec<String> {
+    let (board, player) = (state.get_board(), *state.get_next_player());
+    let lines_lose = state.rules().lines_lose()
+        || state.get_free_for_all() == FreeForAll::Elimination
+            && state.player_count() > MIN_PLAYERS;
+    let column_list = |columns: Vec<usize>| {
+        columns
+            .iter()
+            .map(|col| col.to_string())
+            .collect::<Vec<_>>()
+            .join(" or ")
+    };
+
+    let mut threats = Vec::new();
+    let wins = board.winning_columns(player);
+    if !wins.is_empty() {
+        threats.push(match lines_lose {
+            true => format!(
+                "{} would lose by playing column {}.",
+                player.name(),
+                column_list(wins)
+            ),
+            false => format!("{} can win in column {}.", player.name(), column_list(wins)),
+        });
+    }
+    for opponent in state.active_players().filter(|piece| *piece != player) {
+        let blocks = board.winning_columns(opponent);
+        if blocks.is_empty() {
+            continue;
+        }
+        threats.push(match lines_lose {
+            true => format!(
+                "{} would lose by playing column {}.",
+                opponent.name(),
+                column_list(blocks)
+            ),
+            false => format!(
+                "{} threatens to win in column {}.",
+                opponent.name(),
+                column_list(blocks)
+            ),
+        });
+    }
+    threats
+}
+
+/// The players who have completed a line on the board.
+fn line_makers(board: &GameBoard) -> Vec<GamePiece> {
+    GamePiece::ALL
+        .into_iter()
+        .filter(|piece| board.has_line(*piece))
+        .collect()
+}
+
+/// Works out who made the move that ended a game, if it was won.
+///
+/// This is the winner, unless they won because the only other player to complete a line did so.
+/// When several players were knocked out by completing lines, the last of them can't be told from the board.
+pub(crate) fn last_mover(board: &GameBoard, state: &EndgameType) -> Option<GamePiece> {
+    match state {
+        EndgameType::Win(winner) if !board.has_line(*winner) => match line_makers(board)[..] {
+            [loser] => Some(loser),
+            _ => None,
+        },
+        EndgameType::Win(winner) => Some(*winner),
+        _ => None,
+    }
+}
+
+/// Announces how the game ended, naming players with `name`.
+///
+/// A player who won because others completed lines, rather than by completing one themselves, is told why.
+pub(crate) fn describe_endgame(
+    board: &GameBoard,
+    state: &EndgameType,
+    name: impl Fn(GamePiece) -> String,
+) -> String {
+    match (state, &line_makers(board)[..]) {
+        (EndgameType::Win(winner), [loser]) if loser != winner => format!(
+            "{} made {} in a row, so {} wins!",
+            name(*loser),
+            in_a_row(board.win_length()),
+            name(*winner)
+        ),
+        (EndgameType::Win(winner), [_, _, ..]) if !board.has_line(*winner) => {
+            format!("{} is the last player left, so wins!", name(*winner))
+        }
+        (EndgameType::Win(winner), _) => format!("{} wins!", name(*winner)),
+        (EndgameType::Repetition, _) => "It's a draw by repetition!".to_string(),
+        (EndgameType::Full | EndgameType::None, _) => "It's a draw!".to_string(),
+    }
+}
+
+/// Describes where the winning line is, if there is one.
+pub(crate) fn describe_win(board: &GameBoard) -> Option<String> {
+    let line = board.get_winning_line()?;
+    let (row, col) = line[0];
+    let piece = board.get_piece(row, col)?;
+    let spaces = line
+        .iter()
+        .map(|(row, col)| format!("column {} row {}", col + 1, row_number(board, *row)))
+        .collect::<Vec<_>>();
+    Some(format!(
+        "{} has {} in a row at {}.",
+        piece.name(),
+        in_a_row(board.win_length()),
+        spaces.join(", ")
+    ))
+}
+
+/// Spells out small win lengths, e.g. "four", as a screen reader would say them.
+fn in_a_row(win_length: usize) -> String {
+    const WORDS: [&str; 10] = [
+        "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+    ];
+    WORDS
+        .get(win_length)
+        .map_or_else(|| win_length.to_string(), |w| w.to_string())
+}
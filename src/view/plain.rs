@@ -0,0 +1,414 @@
+use crate::game::{
+    board::{GameBoard, GamePiece},
+    record::{self, GameRecord, HEADER_DATE, HEADER_RESULT},
+    state::{EndgameType, GameState, Move},
+    GameMode,
+};
+use crate::settings::Settings;
+use crate::view::{
+    narration, settings_form::settings_fields, EndgameChoice, MenuChoice, TurnChoice, ViewManager,
+};
+
+use std::io::{self, BufRead, BufReader, Stdin, Stdout, Write};
+
+const TITLE: &str = "FourStack";
+const UNDO_COMMAND: &str = "undo";
+const QUIT_COMMAND: &str = "quit";
+const BOARD_COMMAND: &str = "board";
+const COLUMN_COMMAND: &str = "column";
+const SAVE_COMMAND: &str = "save";
+const CANCEL_COMMAND: &str = "cancel";
+
+/// View manager that reads lines from an input and writes plain text to an output, usually stdin and stdout.
+///
+/// Nothing is drawn in place, so it suits screen readers, dumb terminals and scripted input.
+/// When narrated, the board is described in words rather than drawn.
+pub struct PlainManager<R, W> {
+    input: R,
+    output: W,
+    narrated: bool,
+    settings: Settings,
+    /// Board as the user last saw it, used to narrate what changed since.
+    last_board: GameBoard,
+    /// Whether pieces can be popped out in the game being played, which changes how the board is narrated.
+    pops: bool,
+}
+
+impl Default for PlainManager<BufReader<Stdin>, Stdout> {
+    fn default() -> Self {
+        Self::new(BufReader::new(io::stdin()), io::stdout())
+    }
+}
+
+impl<R: BufRead, W: Write> PlainManager<R, W> {
+    pub fn new(input: R, output: W) -> Self {
+        Self {
+            input,
+            output,
+            narrated: false,
+            settings: Settings::default(),
+            last_board: GameBoard::default(),
+            pops: false,
+        }
+    }
+
+    /// Uses the given settings, e.g. for player names, instead of the defaults.
+    pub fn with_settings(mut self, settings: Settings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Describes the game in words, such as the pieces dropped and any threats, instead of drawing the board.
+    pub fn narrated(mut self) -> Self {
+        self.narrated = true;
+        self
+    }
+
+    /// Narrates the pieces played since the board was last shown.
+    fn say_changes(&mut self, board: &GameBoard, last_mover: Option<GamePiece>) {
+        if self.narrated {
+            for line in narration::describe_changes(&self.last_board, board, last_mover, self.pops)
+            {
+                self.say(line);
+            }
+        }
+        self.last_board = *board;
+    }
+
+    /// Names a player, adding their piece when they have a name of their own, e.g. "Alice (R)".
+    fn describe_player(&self, piece: GamePiece) -> String {
+        let name = self.settings.player_name(piece);
+        match name == piece.name() {
+            true => name,
+            false if self.narrated => format!("{name} ({})", piece.name()),
+            false => format!("{name} ({piece})"),
+        }
+    }
+
+    /// Handles a request to hear a single column, e.g. "column 3".
+    fn say_column(&mut self, board: &GameBoard, col: &str) {
+        match col.trim().parse::<usize>() {
+            Ok(col) if (1..=board.cols()).contains(&col) => {
+                self.say(narration::describe_column(board, col))
+            }
+            _ => self.say(format!("Please choose a column from 1 to {}", board.cols())),
+        }
+    }
+
+    /// Writes a line of output.
+    ///
+    /// Output errors are ignored, as there is nowhere left to report them.
+    fn say(&mut self, text: impl AsRef<str>) {
+        let _ = writeln!(self.output, "{}", text.as_ref());
+    }
+
+    /// Prompts the user and reads their trimmed reply.
+    ///
+    /// Returns [`None`] once the input has been closed.
+    fn ask(&mut self, prompt: &str) -> Option<String> {
+        let _ = write!(self.output, "{prompt} ");
+        let _ = self.output.flush();
+
+        let mut line = String::new();
+        match self.input.read_line(&mut line) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(line.trim().to_string()),
+        }
+    }
+
+    /// Prints every position of a recorded game, one move at a time.
+    fn show_record(&mut self, record: &GameRecord) {
+        let positions = match record.positions() {
+            Ok(positions) => positions,
+            Err(e) => return self.show_error(format!("Could not replay game: {e}")),
+        };
+
+        self.say(format!(
+            "Start of game, {} to move",
+            positions[0].get_next_player()
+        ));
+        for (n, (recorded, mover)) in record.get_moves().iter().zip(&positions).enumerate() {
+            if self.narrated {
+                let (before, after) = (positions[n].get_board(), positions[n + 1].get_board());
+                let pops = record.get_variant().is_ok_and(|v| v.rules().can_pop());
+                for line in narration::describe_changes(before, after, None, pops) {
+                    self.say(line);
+                }
+                continue;
+            }
+            let mut text = format!(
+                "Move {}/{}: {} {}",
+                n + 1,
+                record.get_moves().len(),
+                mover.get_next_player(),
+                narration::describe_move(recorded.game_move)
+            );
+            if let Some(comment) = &recorded.comment {
+                text.push_str(&format!(" ({comment})"));
+            }
+            self.say(text);
+            self.show_board(positions[n + 1].get_board());
+        }
+        self.say(format!(
+            "Result: {}",
+            record.get_header(HEADER_RESULT).unwrap_or_default()
+        ));
+    }
+}
+
+impl<R: BufRead, W: Write> ViewManager for PlainManager<R, W> {
+    fn main_menu(&mut self) -> MenuChoice {
+        let choices = [
+            MenuChoice::Play(GameMode::Ai),
+            MenuChoice::Play(GameMode::Local),
+            MenuChoice::Play(GameMode::Online),
+            MenuChoice::Replays,
+            MenuChoice::Settings,
+            MenuChoice::Quit,
+        ];
+        let default_mode = self.settings.game.mode;
+
+        self.last_board = GameBoard::default();
+        self.say(TITLE);
+        self.say("Select game mode:");
+        for (i, choice) in choices.iter().enumerate() {
+            let label = match choice {
+                MenuChoice::Play(mode) if *mode == default_mode => format!("{mode} (default)"),
+                MenuChoice::Play(mode) => mode.to_string(),
+                MenuChoice::Replays => "Replays".to_string(),
+                MenuChoice::Settings => "Settings".to_string(),
+                MenuChoice::Quit => "Quit".to_string(),
+            };
+            self.say(format!("{}. {label}", i + 1));
+        }
+
+        loop {
+            let Some(answer) = self.ask(">") else {
+                return MenuChoice::Quit;
+            };
+            if answer.is_empty() {
+                return MenuChoice::Play(default_mode);
+            }
+            match answer.parse::<usize>() {
+                Ok(n) if (1..=choices.len()).contains(&n) => return choices[n - 1],
+                _ => self.say(format!("Please enter a number from 1 to {}", choices.len())),
+            }
+        }
+    }
+
+    fn show_replays(&mut self, file: Option<&str>) {
+        let path = match file {
+            Some(file) => file.to_string(),
+            None => {
+                let prompt = format!("Game record file [{}]:", record::DEFAULT_RECORD_FILE);
+                let Some(path) = self.ask(&prompt) else {
+                    return;
+                };
+                match path.is_empty() {
+                    true => record::DEFAULT_RECORD_FILE.to_string(),
+                    false => path,
+                }
+            }
+        };
+
+        let records = match record::load_records(&path) {
+            Ok(records) if records.is_empty() => {
+                return self.show_error(format!("{path} has no games in it"))
+            }
+            Ok(records) => records,
+            Err(e) => return self.show_error(e),
+        };
+
+        for (i, record) in records.iter().enumerate() {
+            let header = |key: &str| record.get_header(key).unwrap_or_default();
+            self.say(format!(
+                "{}. {} - {} ({}, {} moves)",
+                i + 1,
+                header(HEADER_DATE),
+                record.matchup(),
+                header(HEADER_RESULT),
+                record.get_moves().len()
+            ));
+        }
+
+        loop {
+            let Some(answer) = self.ask("Game to replay, or blank to go back:") else {
+                return;
+            };
+            if answer.is_empty() {
+                return;
+            }
+            match answer.parse::<usize>() {
+                Ok(n) if (1..=records.len()).contains(&n) => {
+                    return self.show_record(&records[n - 1])
+                }
+                _ => self.say(format!("Please enter a number from 1 to {}", records.len())),
+            }
+        }
+    }
+
+    fn show_settings(&mut self) -> Option<Settings> {
+        let fields = settings_fields();
+        let mut settings = self.settings.clone();
+        loop {
+            self.say("Settings:");
+            for (i, field) in fields.iter().enumerate() {
+                self.say(format!(
+                    "{}. {}: {}",
+                    i + 1,
+                    field.label,
+                    (field.get)(&settings)
+                ));
+            }
+
+            let prompt = format!(
+                "Enter a setting number to change it, \"{SAVE_COMMAND}\" or \"{CANCEL_COMMAND}\":"
+            );
+            let answer = self.ask(&prompt)?;
+            let field = match answer.to_lowercase().as_str() {
+                SAVE_COMMAND => match settings.validate() {
+                    Ok(_) => {
+                        self.settings = settings.clone();
+                        return Some(settings);
+                    }
+                    Err(e) => {
+                        self.show_error(e.to_string());
+                        continue;
+                    }
+                },
+                CANCEL_COMMAND => return None,
+                a => match a.parse::<usize>() {
+                    Ok(n) if (1..=fields.len()).contains(&n) => &fields[n - 1],
+                    _ => {
+                        self.say(format!("Please enter a number from 1 to {}", fields.len()));
+                        continue;
+                    }
+                },
+            };
+
+            let current = (field.get)(&settings);
+            let prompt = match field.options.is_empty() {
+                true => format!("{} [{current}]:", field.label),
+                false => format!(
+                    "{} ({}) [{current}]:",
+                    field.label,
+                    field.options.join(", ")
+                ),
+            };
+            let value = self.ask(&prompt)?;
+            if value.is_empty() {
+                continue;
+            }
+            match field
+                .options
+                .iter()
+                .find(|o| o.eq_ignore_ascii_case(&value))
+            {
+                Some(option) => (field.set)(&mut settings, option),
+                None if field.options.is_empty() => (field.set)(&mut settings, &value),
+                None => self.say(format!("{value} is not one of the choices")),
+            }
+        }
+    }
+
+    fn show_board(&mut self, board: &GameBoard) {
+        match self.narrated {
+            true => {
+                for line in narration::describe_board(board) {
+                    self.say(line);
+                }
+            }
+            false => {
+                let _ = write!(self.output, "{board}");
+            }
+        }
+    }
+
+    fn get_turn_choice(&mut self, state: &GameState) -> TurnChoice {
+        let (board, player) = (state.get_board(), state.get_next_player());
+        self.pops = state.rules().can_pop();
+        self.say_changes(board, Some(state.previous_player()));
+        let pop_hint = match self.pops {
+            true => ", \"p<column>\" to pop out",
+            false => "",
+        };
+        let prompt = match self.narrated {
+            true => {
+                for line in narration::describe_threats(state) {
+                    self.say(line);
+                }
+                format!(
+                    "{} to move. Enter a column (1-{}){pop_hint}, \"{BOARD_COMMAND}\", \"{COLUMN_COMMAND} <number>\", \"{UNDO_COMMAND}\" or \"{QUIT_COMMAND}\":",
+                    self.describe_player(*player),
+                    board.cols()
+                )
+            }
+            false => {
+                self.show_board(board);
+                format!(
+                    "{} to move. Enter a column (1-{}){pop_hint}, \"{UNDO_COMMAND}\" or \"{QUIT_COMMAND}\":",
+                    self.describe_player(*player),
+                    board.cols()
+                )
+            }
+        };
+
+        loop {
+            let Some(answer) = self.ask(&prompt) else {
+                return TurnChoice::Quit;
+            };
+            match answer.to_lowercase().as_str() {
+                UNDO_COMMAND => return TurnChoice::Undo,
+                QUIT_COMMAND => return TurnChoice::Quit,
+                BOARD_COMMAND => self.show_board(board),
+                a if a.starts_with(COLUMN_COMMAND) => {
+                    self.say_column(board, &a[COLUMN_COMMAND.len()..])
+                }
+                // Out of range columns are reported by the game itself
+                a => match a.parse::<Move>() {
+                    Ok(Move::Drop(col)) => return TurnChoice::Drop(col),
+                    Ok(Move::Pop(col)) => return TurnChoice::Pop(col),
+                    Err(_) => self.say(format!("Unknown command: {answer}")),
+                },
+            }
+        }
+    }
+
+    fn show_error(&mut self, error: impl Into<String>) {
+        self.say(format!("Error: {}", error.into()));
+    }
+
+    fn show_message(&mut self, message: impl Into<String>) {
+        self.say(message.into());
+    }
+
+    fn show_status(&mut self, status: impl Into<String>) {
+        self.say(status.into());
+    }
+
+    fn show_endgame(&mut self, board: &GameBoard, state: &EndgameType) -> EndgameChoice {
+        self.say_changes(board, narration::last_mover(board, state));
+        match self.narrated {
+            true => {
+                if let Some(line) = narration::describe_win(board) {
+                    self.say(line);
+                }
+            }
+            false => self.show_board(board),
+        }
+        let outcome = narration::describe_endgame(board, state, |p| self.describe_player(p));
+        self.say(outcome);
+
+        loop {
+            let Some(answer) = self.ask("Play again? [y]es, [n]o or [e]xport game:") else {
+                return EndgameChoice::Quit;
+            };
+            match answer.to_lowercase().as_str() {
+                "y" | "yes" => return EndgameChoice::PlayAgain,
+                "n" | "no" => return EndgameChoice::Quit,
+                "e" | "export" => return EndgameChoice::Export,
+                _ => self.say("Please answer y, n or e"),
+            }
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use crate::game::{
+    board::{MAX_COLS, MAX_PLAYERS, MAX_ROWS, MIN_PLAYERS, MIN_SIZE, MIN_WIN_LENGTH},
+    layout, FreeForAll, GameMode, Variant,
+};
+use crate::settings::{AiLevel, FirstPlayer, GlyphStyle, PieceColour, Settings};
+
+use std::fmt::Display;
+
+const MODES: [GameMode; 3] = [GameMode::Ai, GameMode::Local, GameMode::Online];
+const LEVELS: [AiLevel; 3] = [AiLevel::Easy, AiLevel::Medium, AiLevel::Hard];
+const GLYPH_STYLES: [GlyphStyle; 3] = [GlyphStyle::Auto, GlyphStyle::Unicode, GlyphStyle::Ascii];
+const THINK_TIMES_MS: [u64; 6] = [250, 500, 1000, 2000, 5000, 10000];
+const HUMAN: &str = "Human";
+const AI: &str = "AI";
+
+/// One editable line of the settings screen, shared by every [`ViewManager`](super::ViewManager).
+pub(crate) struct SettingField {
+    pub label: &'static str,
+    /// Values to choose between, or empty if any text is allowed.
+    pub options: Vec<String>,
+    /// Current value, as shown to the user.
+    pub get: fn(&Settings) -> String,
+    /// Updates the setting from a value as shown to the user; unrecognised values are ignored.
+    pub set: fn(&mut Settings, &str),
+}
+
+/// Every field on the settings screen, in display order.
+pub(crate) fn settings_fields() -> Vec<SettingField> {
+    vec![
+        SettingField {
+            label: "Default mode",
+            options: names(&MODES),
+            get: |s| s.game.mode.to_string(),
+            set: |s, v| set_from(&MODES, v, &mut s.game.mode),
+        },
+        SettingField {
+            label: "First move",
+            options: names(&FirstPlayer::ALL),
+            get: |s| s.game.first_player.to_string(),
+            set: |s, v| set_from(&FirstPlayer::ALL, v, &mut s.game.first_player),
+        },
+        SettingField {
+            label: "Board rows",
+            options: names(&(MIN_SIZE..=MAX_ROWS).collect::<Vec<_>>()),
+            get: |s| s.game.rows.to_string(),
+            set: |s, v| set_parsed(v, &mut s.game.rows),
+        },
+        SettingField {
+            label: "Board columns",
+            options: names(&(MIN_SIZE..=MAX_COLS).collect::<Vec<_>>()),
+            get: |s| s.game.cols.to_string(),
+            set: |s, v| set_parsed(v, &mut s.game.cols),
+        },
+        SettingField {
+            label: "Win length",
+            options: names(&(MIN_WIN_LENGTH..=MAX_ROWS.max(MAX_COLS)).collect::<Vec<_>>()),
+            get: |s| s.game.win_length.to_string(),
+            set: |s, v| set_parsed(v, &mut s.game.win_length),
+        },
+        SettingField {
+            label: "Rules",
+            options: names(&Variant::ALL),
+            get: |s| s.game.variant.to_string(),
+            set: |s, v| set_from(&Variant::ALL, v, &mut s.game.variant),
+        },
+        SettingField {
+            label: "Blocked spaces",
+            options: names(&(0..=layout::max_blockers(MAX_ROWS, MAX_COLS)).collect::<Vec<_>>()),
+            get: |s| s.game.blockers.to_string(),
+            set: |s, v| set_parsed(v, &mut s.game.blockers),
+        },
+        SettingField {
+            label: "Players",
+            options: names(&(MIN_PLAYERS..=MAX_PLAYERS).collect::<Vec<_>>()),
+            get: |s| s.game.players.to_string(),
+            set: |s, v| set_parsed(v, &mut s.game.players),
+        },
+        SettingField {
+            label: "Free-for-all",
+            options: names(&FreeForAll::ALL),
+            get: |s| s.game.free_for_all.to_string(),
+            set: |s, v| set_from(&FreeForAll::ALL, v, &mut s.game.free_for_all),
+        },
+        SettingField {
+            label: "AI difficulty",
+            options: names(&LEVELS),
+            get: |s| s.ai.level.to_string(),
+            set: |s, v| set_from(&LEVELS, v, &mut s.ai.level),
+        },
+        SettingField {
+            label: "AI thinking time",
+            options: THINK_TIMES_MS.iter().map(|ms| seconds(*ms)).collect(),
+            get: |s| seconds(s.ai.think_time_ms),
+            set: |s, v| {
+                if let Ok(secs) = v.trim_end_matches('s').parse::<f64>() {
+                    s.ai.think_time_ms = (secs * 1000.0).round().max(0.0) as u64;
+                }
+            },
+        },
+        SettingField {
+            label: "First against AI",
+            options: vec![HUMAN.to_string(), AI.to_string()],
+            get: |s| match s.ai.human_first {
+                true => HUMAN.to_string(),
+                false => AI.to_string(),
+            },
+            set: |s, v| match v {
+                HUMAN => s.ai.human_first = true,
+                AI => s.ai.human_first = false,
+                _ => (),
+            },
+        },
+        SettingField {
+            label: "Red player name",
+            options: Vec::new(),
+            get: |s| s.players.red.clone(),
+            set: |s, v| s.players.red = v.trim().to_string(),
+        },
+        SettingField {
+            label: "Yellow player name",
+            options: Vec::new(),
+            get: |s| s.players.yellow.clone(),
+            set: |s, v| s.players.yellow = v.trim().to_string(),
+        },
+        SettingField {
+            label: "Green player name",
+            options: Vec::new(),
+            get: |s| s.players.green.clone(),
+            set: |s, v| s.players.green = v.trim().to_string(),
+        },
+        SettingField {
+            label: "Blue player name",
+            options: Vec::new(),
+            get: |s| s.players.blue.clone(),
+            set: |s, v| s.players.blue = v.trim().to_string(),
+        },
+        SettingField {
+            label: "Board glyphs",
+            options: names(&GLYPH_STYLES),
+            get: |s| s.appearance.glyphs.to_string(),
+            set: |s, v| set_from(&GLYPH_STYLES, v, &mut s.appearance.glyphs),
+        },
+        SettingField {
+            label: "Red piece colour",
+            options: names(&PieceColour::ALL),
+            get: |s| s.appearance.red_colour.to_string(),
+            set: |s, v| set_from(&PieceColour::ALL, v, &mut s.appearance.red_colour),
+        },
+        SettingField {
+            label: "Yellow piece colour",
+            options: names(&PieceColour::ALL),
+            get: |s| s.appearance.yellow_colour.to_string(),
+            set: |s, v| set_from(&PieceColour::ALL, v, &mut s.appearance.yellow_colour),
+        },
+        SettingField {
+            label: "Green piece colour",
+            options: names(&PieceColour::ALL),
+            get: |s| s.appearance.green_colour.to_string(),
+            set: |s, v| set_from(&PieceColour::ALL, v, &mut s.appearance.green_colour),
+        },
+        SettingField {
+            label: "Blue piece colour",
+            options: names(&PieceColour::ALL),
+            get: |s| s.appearance.blue_colour.to_string(),
+            set: |s, v| set_from(&PieceColour::ALL, v, &mut s.appearance.blue_colour),
+        },
+    ]
+}
+
+fn names<T: Display>(values: &[T]) -> Vec<String> {
+    values.iter().map(|v| v.to_string()).collect()
+}
+
+/// Sets `target` to whichever of `values` is shown as `shown`, if any.
+fn set_from<T: Display + Copy>(values: &[T], shown: &str, target: &mut T) {
+    if let Some(value) = values.iter().find(|v| v.to_string() == shown) {
+        *target = *value;
+    }
+}
+
+fn set_parsed(shown: &str, target: &mut usize) {
+    if let Ok(value) = shown.parse() {
+        *target = value;
+    }
+}
+
+fn seconds(ms: u64) -> String {
+    format!("{}s", ms as f64 / 1000.0)
+}
@@ -1,18 +1,39 @@
+mod board_view;
+mod replay;
+
 use crate::game::{
-    board::{self, GameBoard, GamePiece},
-    state::EndgameType,
+    board::{GameBoard, GamePiece},
+    record::{self, GameRecord, HEADER_DATE, HEADER_RESULT},
+    state::{EndgameType, GameState, Move},
     GameMode,
 };
-use crate::view::ViewManager;
+use crate::settings::{AppearanceSettings, Settings};
+use crate::view::{
+    narration, settings_form::settings_fields, EndgameChoice, MenuChoice, TurnChoice, ViewManager,
+};
+use board_view::{new_pieces, BoardView, Placement};
+use replay::ReplayView;
 
 use cursive::{
     direction::Orientation,
+    event::Key,
     theme::Theme,
-    views::{Button, Dialog, LinearLayout, SelectView, TextView},
-    CursiveRunnable,
+    view::{Nameable, Resizable, Scrollable},
+    views::{Button, Dialog, EditView, LinearLayout, OnEventView, SelectView, TextView},
+    CbSink, Cursive, CursiveRunnable, View,
 };
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
 
 const TITLE: &str = "FourStack";
+const REPLAY_PATH_INPUT: &str = "replay_path";
+/// Prefix for the names of the inputs on the settings screen, which are numbered in display order.
+const SETTING_INPUT: &str = "setting_";
+/// Refresh rate while a replay is open, so that autoplay can advance without user input.
+const REPLAY_FPS: u32 = 10;
+/// Time between redraws while the board is animating.
+const ANIMATION_FRAME_TIME: Duration = Duration::from_millis(33);
 // Created with https://patorjk.com/software/taag
 const SPLASH: &str = r" ______               _____ _             _    
 |  ____|             / ____| |           | |   
@@ -22,118 +43,584 @@ const SPLASH: &str = r" ______               _____ _             _
 |_|  \___/ \__,_|_| |_____/ \__\__,_|\___|_|\_\
 ";
 
-pub struct TuiManager {
+/// Requests sent from the game to the user interface.
+#[derive(Clone)]
+enum ViewEvent {
+    MainMenu,
+    /// The replays screen, opening the given file if there is one.
+    Replays(Option<String>),
+    Settings,
+    Board(GameBoard),
+    ColumnSelection {
+        board: GameBoard,
+        player: GamePiece,
+        /// Moves the player is allowed to make.
+        moves: Vec<Move>,
+        /// Whether the rules let players pop their own pieces out as well as drop them in.
+        pops: bool,
+        played: Vec<Placement>,
+    },
+    Error(String),
+    Message(String),
+    Status(String),
+    Endgame {
+        board: GameBoard,
+        state: EndgameType,
+        played: Vec<Placement>,
+    },
+    Exit,
+}
+
+/// Responses sent from the user interface back to the game.
+enum UserIntent {
+    Menu(MenuChoice),
+    Turn(TurnChoice),
+    /// A screen that only needed acknowledging, such as a message, was closed.
+    Dismissed,
+    Endgame(EndgameChoice),
+    Settings(Option<Box<Settings>>),
+}
+
+type Intents = Sender<UserIntent>;
+
+/// Marks that the event loop was stopped by the game rather than the user.
+struct GameExited;
+
+/// Owns the terminal and runs the single cursive event loop.
+///
+/// The game itself runs elsewhere and drives the interface through a paired [`TuiManager`].
+pub struct TuiRuntime {
     runtime: CursiveRunnable,
 }
 
-impl Default for TuiManager {
-    fn default() -> Self {
-        let mut runtime = cursive::default();
-        // Application-wide TUI config
-        // Set theme to correspond with user's terminal
-        runtime.set_theme(Theme::terminal_default());
-        // Default base UI layer to prevent background flickering
-        runtime.add_layer(Dialog::default().title(TITLE));
-        Self { runtime }
+impl TuiRuntime {
+    /// Runs the user interface until the game exits or the user quits, blocking the current thread.
+    ///
+    /// Returns `true` if the game asked for the interface to close, or `false` if the user quit.
+    pub fn run(mut self) -> bool {
+        self.runtime.run();
+        self.runtime.take_user_data::<GameExited>().is_some()
+    }
+}
+
+/// Game-side handle to the terminal user interface.
+///
+/// Each [`ViewManager`] call is sent to the [`TuiRuntime`]'s event loop as an event, then waits for what the user chose.
+pub struct TuiManager {
+    events: CbSink,
+    intents_tx: Intents,
+    intents: Receiver<UserIntent>,
+    settings: Settings,
+    /// Board as the user last saw it, used to animate newly played pieces.
+    last_board: GameBoard,
+}
+
+/// Creates the terminal user interface, split into the event loop and the handle used by the game.
+pub fn create(settings: Settings) -> (TuiRuntime, TuiManager) {
+    let mut runtime = cursive::default();
+    // Application-wide TUI config
+    // Set theme to correspond with user's terminal
+    runtime.set_theme(Theme::terminal_default());
+    // Default base UI layer to prevent background flickering
+    runtime.add_layer(Dialog::default().title(TITLE));
+
+    let (intents_tx, intents) = mpsc::channel();
+    let manager = TuiManager {
+        events: runtime.cb_sink().clone(),
+        intents_tx,
+        intents,
+        settings,
+        last_board: GameBoard::default(),
+    };
+    (TuiRuntime { runtime }, manager)
+}
+
+impl TuiManager {
+    /// Sends an event to the user interface without waiting for a response.
+    fn send(&self, event: ViewEvent) {
+        let intents = self.intents_tx.clone();
+        let settings = self.settings.clone();
+        // If the interface has already shut down then the program is exiting, so there is no one to show anything to
+        let _ = self.events.send(Box::new(move |c| {
+            handle_event(c, event, intents, &settings)
+        }));
+    }
+
+    /// Sends an event to the user interface and waits for the user's response.
+    fn request(&self, event: ViewEvent) -> UserIntent {
+        self.send(event);
+        // If the user quits the interface this waits forever, as the game ends along with the process
+        self.intents
+            .recv()
+            .expect("Manager should hold a sender for its own intents")
+    }
+
+    /// Asks the user interface to shut down, ending the event loop.
+    pub fn exit(&self) {
+        self.send(ViewEvent::Exit);
+    }
+}
+
+impl Drop for TuiManager {
+    fn drop(&mut self) {
+        // Make sure the event loop never outlives the game, even if the game panics
+        self.exit();
     }
 }
+
 impl ViewManager for TuiManager {
-    fn main_menu(&mut self) -> GameMode {
-        let mut layout = LinearLayout::new(Orientation::Vertical);
-        layout.add_child(TextView::new(SPLASH));
-        layout.add_child(TextView::new("Select game mode:"));
-
-        let mut main_menu_selector = SelectView::<GameMode>::new();
-        main_menu_selector.add_item("1. AI", GameMode::Ai);
-        main_menu_selector.add_item("2. Local", GameMode::Local);
-        main_menu_selector.add_item("3. Online", GameMode::Online);
-
-        main_menu_selector.set_on_submit(|c, e| {
-            c.set_user_data(*e);
-            cleanup(c);
-        });
-        layout.add_child(main_menu_selector);
-
-        self.runtime.add_layer(Dialog::around(layout));
-        self.runtime.run();
-        // This is safe because `run` above will block the thread until `set_on_submit` is called
-        self.runtime
-            .take_user_data()
-            .expect("Cursive user data should be set to a GameMode!")
+    fn main_menu(&mut self) -> MenuChoice {
+        loop {
+            if let UserIntent::Menu(choice) = self.request(ViewEvent::MainMenu) {
+                self.last_board = GameBoard::default();
+                return choice;
+            }
+        }
+    }
+
+    fn show_replays(&mut self, file: Option<&str>) {
+        self.request(ViewEvent::Replays(file.map(str::to_string)));
+    }
+
+    fn show_settings(&mut self) -> Option<Settings> {
+        loop {
+            if let UserIntent::Settings(settings) = self.request(ViewEvent::Settings) {
+                let settings = *settings?;
+                self.settings = settings.clone();
+                return Some(settings);
+            }
+        }
     }
 
     fn show_board(&mut self, board: &GameBoard) {
-        let board_view = TextView::new(board.to_string());
-        self.runtime
-            .add_layer(Dialog::around(board_view).title(TITLE));
-        self.runtime.run()
-    }
-
-    fn get_column_selection(&mut self, board: &GameBoard, player: &GamePiece) -> usize {
-        let mut col_select_view = LinearLayout::new(Orientation::Horizontal);
-        for i in 1..=board::GRID_COLS {
-            let btn = Button::new_raw(format!(" [{}]", i), move |c| {
-                c.set_user_data(i);
-                cleanup(c);
-            });
-            col_select_view.add_child(btn);
+        self.last_board = *board;
+        self.send(ViewEvent::Board(*board));
+    }
+
+    fn get_turn_choice(&mut self, state: &GameState) -> TurnChoice {
+        let (board, player) = (state.get_board(), state.get_next_player());
+        let played = new_pieces(&self.last_board, board, Some(state.previous_player()));
+        let event = ViewEvent::ColumnSelection {
+            board: *board,
+            player: *player,
+            moves: state.legal_moves(),
+            pops: state.rules().can_pop(),
+            played,
+        };
+        loop {
+            if let UserIntent::Turn(choice) = self.request(event.clone()) {
+                self.last_board = *board;
+                // The interface shows the chosen move straight away, so it does not need animating again
+                let _ = match choice {
+                    TurnChoice::Drop(col) => self.last_board.insert_piece(*player, col),
+                    TurnChoice::Pop(col) => self.last_board.pop_piece(*player, col),
+                    TurnChoice::Undo | TurnChoice::Quit => Ok(()),
+                };
+                return choice;
+            }
         }
+    }
 
-        let mut layout = LinearLayout::new(Orientation::Vertical);
-        layout.add_child(TextView::new(board.to_string()));
-        layout.add_child(TextView::new(format!("It is {}'s turn", player)));
-        layout.add_child(col_select_view);
+    fn show_error(&mut self, error: impl Into<String>) {
+        self.request(ViewEvent::Error(error.into()));
+    }
 
-        self.runtime.add_layer(Dialog::around(layout).title(TITLE));
-        self.runtime.run();
+    fn show_message(&mut self, message: impl Into<String>) {
+        self.request(ViewEvent::Message(message.into()));
+    }
 
-        self.runtime
-            .take_user_data()
-            .expect("Cursive user data should contain a column selection!")
+    fn show_status(&mut self, status: impl Into<String>) {
+        self.send(ViewEvent::Status(status.into()));
     }
 
-    fn show_error(&mut self, error: impl Into<String>) {
-        self.runtime
-            .add_layer(Dialog::around(TextView::new(error)).button("OK", |c| {
-                cleanup(c);
+    fn show_endgame(&mut self, board: &GameBoard, state: &EndgameType) -> EndgameChoice {
+        let last_mover = narration::last_mover(board, state);
+        let played = new_pieces(&self.last_board, board, last_mover);
+        self.last_board = *board;
+        let mut event = ViewEvent::Endgame {
+            board: *board,
+            state: *state,
+            played,
+        };
+        loop {
+            if let UserIntent::Endgame(choice) = self.request(event) {
+                return choice;
+            }
+            // Don't replay the animation if the screen has to be shown again
+            event = ViewEvent::Endgame {
+                board: *board,
+                state: *state,
+                played: Vec::new(),
+            };
+        }
+    }
+}
+
+/* Event loop side */
+
+/// Builds the screen for an event from the game.
+fn handle_event(cursive: &mut Cursive, event: ViewEvent, intents: Intents, settings: &Settings) {
+    let appearance = settings.appearance;
+    match event {
+        ViewEvent::MainMenu => show_screen(cursive, main_menu(settings, intents), None),
+        ViewEvent::Replays(file) => {
+            let path = file.as_deref().unwrap_or(record::DEFAULT_RECORD_FILE);
+            show_screen(cursive, replays(path, appearance, intents.clone()), None);
+            if let Some(file) = file {
+                open_replay_file(cursive, &file, appearance, intents);
+            }
+        }
+        ViewEvent::Settings => show_screen(cursive, settings_form(settings, intents), None),
+        ViewEvent::Board(board) => show_screen(
+            cursive,
+            Dialog::around(BoardView::new(board).with_appearance(&appearance)).title(TITLE),
+            None,
+        ),
+        ViewEvent::ColumnSelection {
+            board,
+            player,
+            moves,
+            pops,
+            played,
+        } => {
+            let board_view = BoardView::new(board)
+                .with_appearance(&appearance)
+                .with_drop_animation(played);
+            let animation_time = board_view.animation_time();
+            let move_intents = intents.clone();
+            let name = settings.player_name(player);
+            let board_view = board_view.with_selection(player, moves, move |c, game_move| {
+                show_move_played(c, board, player, game_move, &appearance);
+                let choice = match game_move {
+                    Move::Drop(col) => TurnChoice::Drop(col),
+                    Move::Pop(col) => TurnChoice::Pop(col),
+                };
+                let _ = move_intents.send(UserIntent::Turn(choice));
+            });
+
+            let mut layout = LinearLayout::new(Orientation::Vertical);
+            layout.add_child(board_view);
+            layout.add_child(TextView::new(format!("It is {name}'s turn ({player})")));
+            layout.add_child(TextView::new(format!(
+                "<-/-> or h/l: move  Enter/Space: drop  {} or click: drop in column",
+                board_view::column_keys(board.cols())
+            )));
+            if pops {
+                layout.add_child(TextView::new("Down/p: pop your piece out of the bottom"));
+            }
+
+            let mut dialog = Dialog::around(layout).title(TITLE);
+            for (label, choice) in [("Undo", TurnChoice::Undo), ("Quit", TurnChoice::Quit)] {
+                let intents = intents.clone();
+                dialog.add_button(label, move |_| {
+                    let _ = intents.send(UserIntent::Turn(choice));
+                });
+            }
+            show_screen(cursive, dialog, None);
+            animate(cursive, animation_time);
+        }
+        ViewEvent::Error(error) => {
+            cursive.add_layer(Dialog::around(TextView::new(error)).button("OK", move |c| {
+                c.pop_layer();
+                let _ = intents.send(UserIntent::Dismissed);
             }));
-        self.runtime.run();
+        }
+        ViewEvent::Status(status) => show_screen(
+            cursive,
+            Dialog::around(TextView::new(status)).title(TITLE),
+            None,
+        ),
+        ViewEvent::Message(message) => {
+            cursive.add_layer(Dialog::around(TextView::new(message)).title(TITLE).button(
+                "OK",
+                move |c| {
+                    c.pop_layer();
+                    let _ = intents.send(UserIntent::Dismissed);
+                },
+            ));
+        }
+        ViewEvent::Endgame {
+            board,
+            state,
+            played,
+        } => {
+            let board_view = BoardView::new(board)
+                .with_appearance(&appearance)
+                .with_drop_animation(played)
+                .with_highlight(&board.get_winning_line().unwrap_or_default());
+            let animation_time = board_view.animation_time();
+            let banner = narration::describe_endgame(&board, &state, |p| settings.player_name(p));
+            show_screen(cursive, endgame(board_view, banner, intents), None);
+            animate(cursive, animation_time);
+        }
+        ViewEvent::Exit => {
+            cursive.set_user_data(GameExited);
+            cursive.quit();
+        }
     }
+}
 
-    fn show_endgame(&mut self, board: &GameBoard, state: &EndgameType) -> bool {
-        let mut layout = LinearLayout::new(Orientation::Vertical);
-        layout.add_child(TextView::new(board.to_string()));
+/// Replaces whatever screen is showing with a new one, optionally redrawing it regularly.
+fn show_screen(cursive: &mut Cursive, view: impl View, fps: Option<u32>) {
+    // Keep the base layer, which prevents background flickering
+    while cursive.screen().len() > 1 {
+        cursive.pop_layer();
+    }
+    cursive.set_fps(fps.unwrap_or(0));
+    cursive.add_layer(view);
+}
 
-        let mut banner = match state {
-            EndgameType::Win(p) => format!("{p} wins!"),
-            _ => "It's a draw!".to_string(),
-        };
-        banner.push_str(" Play again?");
-        layout.add_child(TextView::new(banner));
+/// Redraws the screen on a timer for the given time, so that an animation can play without user input.
+///
+/// The timer runs on its own thread and stops early if the event loop has shut down.
+fn animate(cursive: &Cursive, duration: Option<Duration>) {
+    let Some(duration) = duration else {
+        return;
+    };
+    let events = cursive.cb_sink().clone();
+    let started = Instant::now();
+    thread::spawn(move || {
+        // Every callback received by the event loop is followed by a redraw, so an empty one is enough
+        loop {
+            // Check before redrawing, so that the final frame is always drawn once the animation has finished
+            let finished = started.elapsed() > duration;
+            if events.send(Box::new(|_| ())).is_err() || finished {
+                break;
+            }
+            thread::sleep(ANIMATION_FRAME_TIME);
+        }
+    });
+}
 
-        let mut btn_row = LinearLayout::new(Orientation::Horizontal);
-        btn_row.add_child(Button::new("Yes", |c| {
-            c.set_user_data(true);
-            cleanup(c);
-        }));
-        btn_row.add_child(Button::new("No", |c| {
-            c.set_user_data(false);
-            cleanup(c);
+fn main_menu(settings: &Settings, intents: Intents) -> impl View {
+    let mut layout = LinearLayout::new(Orientation::Vertical);
+    layout.add_child(TextView::new(SPLASH));
+    layout.add_child(TextView::new("Select game mode:"));
+
+    let mut main_menu_selector = SelectView::<MenuChoice>::new();
+    main_menu_selector.add_item("1. AI", MenuChoice::Play(GameMode::Ai));
+    main_menu_selector.add_item("2. Local", MenuChoice::Play(GameMode::Local));
+    main_menu_selector.add_item("3. Online", MenuChoice::Play(GameMode::Online));
+    main_menu_selector.add_item("4. Replays", MenuChoice::Replays);
+    main_menu_selector.add_item("5. Settings", MenuChoice::Settings);
+    main_menu_selector.add_item("6. Quit", MenuChoice::Quit);
+    let default_choice = MenuChoice::Play(settings.game.mode);
+    let default_index = main_menu_selector
+        .iter()
+        .position(|(_, choice)| *choice == default_choice);
+    if let Some(index) = default_index {
+        main_menu_selector.set_selection(index);
+    }
+
+    main_menu_selector.set_on_submit(move |_, e| {
+        let _ = intents.send(UserIntent::Menu(*e));
+    });
+    layout.add_child(main_menu_selector);
+
+    Dialog::around(layout)
+}
+
+/// Shows the board with the user's chosen move while the game processes it.
+fn show_move_played(
+    cursive: &mut Cursive,
+    mut board: GameBoard,
+    player: GamePiece,
+    game_move: Move,
+    appearance: &AppearanceSettings,
+) {
+    let placed = board;
+    let played = match game_move {
+        Move::Drop(col) => board.insert_piece(player, col),
+        Move::Pop(col) => board.pop_piece(player, col),
+    };
+    if played.is_err() {
+        return;
+    }
+    let board_view = BoardView::new(board)
+        .with_appearance(appearance)
+        .with_drop_animation(new_pieces(&placed, &board, None));
+    let animation_time = board_view.animation_time();
+
+    let mut layout = LinearLayout::new(Orientation::Vertical);
+    layout.add_child(board_view);
+    let description = match game_move {
+        Move::Drop(col) => format!("{player} played column {col}"),
+        Move::Pop(col) => format!("{player} popped out of column {col}"),
+    };
+    layout.add_child(TextView::new(description));
+    show_screen(cursive, Dialog::around(layout).title(TITLE), None);
+    animate(cursive, animation_time);
+}
+
+fn endgame(board_view: BoardView, mut banner: String, intents: Intents) -> impl View {
+    let mut layout = LinearLayout::new(Orientation::Vertical);
+    layout.add_child(board_view);
+
+    banner.push_str(" Play again?");
+    layout.add_child(TextView::new(banner));
+
+    let mut btn_row = LinearLayout::new(Orientation::Horizontal);
+    let choices = [
+        ("Yes", EndgameChoice::PlayAgain),
+        ("No", EndgameChoice::Quit),
+        ("Export game", EndgameChoice::Export),
+    ];
+    for (label, choice) in choices {
+        let intents = intents.clone();
+        btn_row.add_child(Button::new(label, move |_| {
+            let _ = intents.send(UserIntent::Endgame(choice));
         }));
-        layout.add_child(btn_row);
+    }
+    layout.add_child(btn_row);
 
-        self.runtime.add_layer(Dialog::around(layout).title(TITLE));
-        self.runtime.run();
-        self.runtime.take_user_data().unwrap_or_default()
+    Dialog::around(layout).title(TITLE)
+}
+
+fn replays(path: &str, appearance: AppearanceSettings, intents: Intents) -> impl View {
+    let mut layout = LinearLayout::new(Orientation::Vertical);
+    layout.add_child(TextView::new("Game record file:"));
+    let submit_intents = intents.clone();
+    layout.add_child(
+        EditView::new()
+            .content(path)
+            .on_submit(move |c, path| open_replay_file(c, path, appearance, submit_intents.clone()))
+            .with_name(REPLAY_PATH_INPUT)
+            .min_width(32),
+    );
+
+    let open_intents = intents.clone();
+    Dialog::around(layout)
+        .title("Replays")
+        .button("Open", move |c| {
+            let path = c
+                .call_on_name(REPLAY_PATH_INPUT, |v: &mut EditView| v.get_content())
+                .expect("Replay path input should exist");
+            open_replay_file(c, &path, appearance, open_intents.clone());
+        })
+        .button("Back", move |_| {
+            let _ = intents.send(UserIntent::Dismissed);
+        })
+}
+
+/// Lists every setting with an input to change it, sending the new settings when saved.
+fn settings_form(settings: &Settings, intents: Intents) -> impl View {
+    let fields = settings_fields();
+    let label_width = fields.iter().map(|f| f.label.len()).max().unwrap_or(0) + 2;
+
+    let mut layout = LinearLayout::new(Orientation::Vertical);
+    for (i, field) in fields.iter().enumerate() {
+        let current = (field.get)(settings);
+        let name = format!("{SETTING_INPUT}{i}");
+        let mut row = LinearLayout::new(Orientation::Horizontal);
+        row.add_child(TextView::new(field.label).fixed_width(label_width));
+        match field.options.is_empty() {
+            true => row.add_child(
+                EditView::new()
+                    .content(current)
+                    .with_name(name)
+                    .min_width(20),
+            ),
+            false => {
+                let mut select = SelectView::<String>::new().popup();
+                select.add_all_str(&field.options);
+                // Keep values that were set by hand in the config file, even if they aren't one of the usual choices
+                if !field.options.contains(&current) {
+                    select.add_item_str(&current);
+                }
+                let index = select.iter().position(|(label, _)| label == current);
+                select.set_selection(index.unwrap_or(0));
+                row.add_child(select.with_name(name));
+            }
+        }
+        layout.add_child(row);
     }
+
+    let original = settings.clone();
+    let save_intents = intents.clone();
+    Dialog::around(layout.scrollable())
+        .title("Settings")
+        .button("Save", move |c| {
+            let mut settings = original.clone();
+            for (i, field) in settings_fields().iter().enumerate() {
+                let name = format!("{SETTING_INPUT}{i}");
+                let value = match field.options.is_empty() {
+                    true => c.call_on_name(&name, |v: &mut EditView| v.get_content()),
+                    false => c
+                        .call_on_name(&name, |v: &mut SelectView<String>| v.selection())
+                        .flatten(),
+                };
+                if let Some(value) = value {
+                    (field.set)(&mut settings, &value);
+                }
+            }
+            match settings.validate() {
+                Ok(_) => {
+                    let _ = save_intents.send(UserIntent::Settings(Some(Box::new(settings))));
+                }
+                Err(e) => c.add_layer(Dialog::info(e.to_string())),
+            }
+        })
+        .button("Cancel", move |_| {
+            let _ = intents.send(UserIntent::Settings(None));
+        })
 }
 
 /* Helper functions */
 
-/// Common tear-down steps when a cursive view is closed.
-fn cleanup(cursive: &mut cursive::Cursive) {
-    // Clear the foreground UI layer
-    cursive.pop_layer();
-    // Terminate the currently running UI event loop
-    cursive.quit();
+/// Loads the game records in a file and lists them so that one can be replayed.
+fn open_replay_file(
+    cursive: &mut Cursive,
+    path: &str,
+    appearance: AppearanceSettings,
+    intents: Intents,
+) {
+    let records = match record::load_records(path) {
+        Ok(records) if records.is_empty() => {
+            cursive.add_layer(Dialog::info("No games found in that file."));
+            return;
+        }
+        Ok(records) => records,
+        Err(e) => {
+            cursive.add_layer(Dialog::info(format!("Could not open {path}: {e}")));
+            return;
+        }
+    };
+
+    let mut game_selector = SelectView::<GameRecord>::new();
+    for (i, record) in records.into_iter().enumerate() {
+        let header = |key| record.get_header(key).unwrap_or(record::UNKNOWN_VALUE);
+        let label = format!(
+            "{}. {} ({}) - {}",
+            i + 1,
+            record.matchup(),
+            header(HEADER_DATE),
+            header(HEADER_RESULT)
+        );
+        game_selector.add_item(label, record);
+    }
+    game_selector.set_on_submit(move |c, record: &GameRecord| {
+        match ReplayView::new(record.clone(), &appearance) {
+            Ok(view) => {
+                let (close_intents, esc_intents) = (intents.clone(), intents.clone());
+                let replay = OnEventView::new(Dialog::around(view).title("Replay").button(
+                    "Close",
+                    move |_| {
+                        let _ = close_intents.send(UserIntent::Dismissed);
+                    },
+                ))
+                .on_event(Key::Esc, move |_| {
+                    let _ = esc_intents.send(UserIntent::Dismissed);
+                });
+                // Replace the file and game selection screens with the replay itself
+                show_screen(c, replay, Some(REPLAY_FPS));
+            }
+            Err(e) => c.add_layer(Dialog::info(format!("Cannot replay this game: {e}"))),
+        }
+    });
+
+    cursive.add_layer(
+        Dialog::around(game_selector)
+            .title("Select a game")
+            .dismiss_button("Back"),
+    );
 }
@@ -0,0 +1,627 @@
+use crate::game::{
+    board::{GameBoard, GamePiece},
+    state::Move,
+};
+use crate::settings::{AppearanceSettings, GlyphStyle, PieceColour};
+
+use cursive::{
+    direction::Direction,
+    event::{Event, EventResult, Key, MouseButton, MouseEvent},
+    theme::{BaseColor, Color, ColorStyle, Effect},
+    view::CannotFocus,
+    Cursive, Printer, Vec2, View,
+};
+use std::cmp::Reverse;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// Largest multiple of the minimum cell size that the board will grow to.
+const MAX_SCALE: usize = 3;
+/// Time taken for an animated piece to fall by one row.
+const ROW_DROP_TIME: Duration = Duration::from_millis(45);
+/// Time between the winning line being highlighted and un-highlighted.
+const HIGHLIGHT_FLASH_TIME: Duration = Duration::from_millis(400);
+/// Number of times the winning line flashes before it stays highlighted.
+const HIGHLIGHT_FLASHES: u32 = 3;
+
+/// Characters used to draw the board.
+struct Glyphs {
+    horizontal: &'static str,
+    vertical: &'static str,
+    // Corners and junctions, as [left, middle, right]
+    top: [&'static str; 3],
+    middle: [&'static str; 3],
+    bottom: [&'static str; 3],
+    /// Disc drawn in a single-line cell.
+    disc: fn(GamePiece) -> String,
+    /// Fill used for each line of a disc in a larger cell.
+    disc_fill: fn(GamePiece) -> String,
+    /// Preview of a disc about to be dropped, drawn in a single-line cell.
+    ghost: fn(GamePiece) -> String,
+    /// Fill used for each line of a preview disc in a larger cell.
+    ghost_fill: fn(GamePiece) -> String,
+    /// Fill used for every line of a blocked space.
+    blocked: &'static str,
+    /// Shown in place of the column number for columns that cannot be played.
+    full_column: &'static str,
+    coloured: bool,
+}
+
+const UNICODE_GLYPHS: Glyphs = Glyphs {
+    horizontal: "─",
+    vertical: "│",
+    top: ["┌", "┬", "┐"],
+    middle: ["├", "┼", "┤"],
+    bottom: ["└", "┴", "┘"],
+    disc: |_| "●".to_string(),
+    disc_fill: |_| "█".to_string(),
+    ghost: |_| "○".to_string(),
+    ghost_fill: |_| "░".to_string(),
+    blocked: "▒",
+    full_column: "×",
+    coloured: true,
+};
+
+const ASCII_GLYPHS: Glyphs = Glyphs {
+    horizontal: "-",
+    vertical: "|",
+    top: ["+", "+", "+"],
+    middle: ["+", "+", "+"],
+    bottom: ["+", "+", "+"],
+    disc: |p| p.to_string(),
+    disc_fill: |p| p.to_string(),
+    ghost: |p| p.to_string().to_lowercase(),
+    ghost_fill: |p| p.to_string().to_lowercase(),
+    blocked: "#",
+    full_column: "x",
+    coloured: false,
+};
+
+/// Called with the chosen move, with columns starting at 1, when a piece is dropped or popped out.
+type MoveCallback = Rc<dyn Fn(&mut Cursive, Move)>;
+
+/// State for choosing a column to drop a piece into.
+struct Selection {
+    player: GamePiece,
+    /// Currently highlighted column, starting at 0.
+    column: usize,
+    /// Moves the player is allowed to make.
+    moves: Vec<Move>,
+    on_move: MoveCallback,
+}
+
+/// A piece that has been added to the board, as a zero-based (row, column) counted from the top-left.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) struct Placement {
+    pub row: usize,
+    pub col: usize,
+    pub piece: GamePiece,
+}
+
+/// Pieces falling into place one after another.
+struct DropAnimation {
+    pieces: Vec<Placement>,
+    started: Instant,
+}
+
+/// Draws a [`GameBoard`] as a grid of coloured discs, growing the grid to fill the available space.
+///
+/// Falls back to plain ASCII when the terminal does not support colour.
+pub(super) struct BoardView {
+    board: GameBoard,
+    glyphs: &'static Glyphs,
+    appearance: AppearanceSettings,
+    scale: usize,
+    selection: Option<Selection>,
+    animation: Option<DropAnimation>,
+    highlight: Vec<(usize, usize)>,
+    created: Instant,
+}
+
+impl BoardView {
+    pub(super) fn new(board: GameBoard) -> Self {
+        Self {
+            board,
+            glyphs: match colour_supported() {
+                true => &UNICODE_GLYPHS,
+                false => &ASCII_GLYPHS,
+            },
+            appearance: AppearanceSettings::default(),
+            scale: 1,
+            selection: None,
+            animation: None,
+            highlight: Vec::new(),
+            created: Instant::now(),
+        }
+    }
+
+    /// Draws the board with the user's chosen glyphs and piece colours.
+    pub(super) fn with_appearance(mut self, appearance: &AppearanceSettings) -> Self {
+        self.glyphs = match appearance.glyphs {
+            GlyphStyle::Auto => self.glyphs,
+            GlyphStyle::Unicode => &UNICODE_GLYPHS,
+            GlyphStyle::Ascii => &ASCII_GLYPHS,
+        };
+        self.appearance = *appearance;
+        self
+    }
+
+    /// Animates the given pieces falling into place, in order, before they are shown as part of the board.
+    pub(super) fn with_drop_animation(mut self, pieces: Vec<Placement>) -> Self {
+        self.animation = (!pieces.is_empty()).then(|| DropAnimation {
+            pieces,
+            started: Instant::now(),
+        });
+        self
+    }
+
+    /// Flashes the given spaces, as zero-based (row, column) pairs, once any drop animation has finished, then leaves them highlighted.
+    pub(super) fn with_highlight(mut self, spaces: &[(usize, usize)]) -> Self {
+        self.highlight = spaces.to_vec();
+        self
+    }
+
+    /// Time from the view being created until it stops changing, or [`None`] if it never changes and so needs no redraws.
+    pub(super) fn animation_time(&self) -> Option<Duration> {
+        let flash_time = match self.highlight.is_empty() {
+            true => Duration::ZERO,
+            false => HIGHLIGHT_FLASH_TIME * (2 * HIGHLIGHT_FLASHES),
+        };
+        let total = self.drop_time() + flash_time;
+        (!total.is_zero()).then_some(total)
+    }
+
+    /// Time taken for every animated piece to land.
+    fn drop_time(&self) -> Duration {
+        self.animation.as_ref().map_or(Duration::ZERO, |animation| {
+            animation
+                .pieces
+                .iter()
+                .map(|placement| ROW_DROP_TIME * (placement.row as u32 + 1))
+                .sum()
+        })
+    }
+
+    /// Checks whether the highlighted spaces are currently shown highlighted, which alternates until the flashing finishes.
+    fn is_highlight_on(&self) -> bool {
+        let Some(flashing) = self.created.elapsed().checked_sub(self.drop_time()) else {
+            return false;
+        };
+        let phase = flashing.as_millis() / HIGHLIGHT_FLASH_TIME.as_millis();
+        phase >= 2 * HIGHLIGHT_FLASHES as u128 || phase.is_multiple_of(2)
+    }
+
+    /// Lets the user pick one of the given moves for a player with the keyboard or mouse, previewing the piece above the board.
+    pub(super) fn with_selection<F>(
+        mut self,
+        player: GamePiece,
+        moves: Vec<Move>,
+        on_move: F,
+    ) -> Self
+    where
+        F: Fn(&mut Cursive, Move) + 'static,
+    {
+        self.selection = Some(Selection {
+            player,
+            column: 0,
+            moves,
+            on_move: Rc::new(on_move),
+        });
+        // Start in the middle, or the closest playable column to it
+        let middle = self.board.cols() / 2;
+        let column = (0..self.board.cols())
+            .filter(|col| self.is_playable(*col))
+            .min_by_key(|col| col.abs_diff(middle))
+            .unwrap_or(middle);
+        if let Some(selection) = self.selection.as_mut() {
+            selection.column = column;
+        }
+        self
+    }
+
+    pub(super) fn set_board(&mut self, board: GameBoard) {
+        self.board = board;
+    }
+
+    /// Checks whether the selecting player can drop a piece into, or pop a piece out of, a column starting at 0.
+    fn is_playable(&self, col: usize) -> bool {
+        self.can_drop(col) || self.can_pop(col)
+    }
+
+    fn can_drop(&self, col: usize) -> bool {
+        self.is_legal(Move::Drop(col + 1))
+    }
+
+    fn can_pop(&self, col: usize) -> bool {
+        self.is_legal(Move::Pop(col + 1))
+    }
+
+    fn is_legal(&self, game_move: Move) -> bool {
+        self.selection
+            .as_ref()
+            .is_some_and(|s| s.moves.contains(&game_move))
+    }
+
+    /// Size of the inside of a single cell at a given scale, as (width, height).
+    fn cell_size(scale: usize) -> Vec2 {
+        Vec2::new(2 * scale + 1, scale)
+    }
+
+    /// Number of lines above the board reserved for previewing a piece.
+    fn preview_height(&self, scale: usize) -> usize {
+        match self.selection {
+            Some(_) => Self::cell_size(scale).y,
+            None => 0,
+        }
+    }
+
+    /// Width reserved at each side of a board that wraps for a ghost of the column on the other side.
+    fn ghost_width(&self, scale: usize) -> usize {
+        match self.board.wraps() {
+            true => Self::cell_size(scale).x + 1,
+            false => 0,
+        }
+    }
+
+    /// Size of the whole view at a given scale.
+    fn size_at(&self, scale: usize) -> Vec2 {
+        let cell = Self::cell_size(scale);
+        Vec2::new(
+            2 * self.ghost_width(scale) + self.board.cols() * (cell.x + 1) + 1,
+            self.preview_height(scale) + self.board.rows() * (cell.y + 1) + 1,
+        )
+    }
+
+    /// Largest scale at which the view fits within a given size, or the smallest scale if none do.
+    fn scale_for(&self, available: Vec2) -> usize {
+        (1..=MAX_SCALE)
+            .rev()
+            .find(|s| self.size_at(*s).fits_in(available))
+            .unwrap_or(1)
+    }
+
+    /// Builds a horizontal border line.
+    fn border_line(&self, corners: [&str; 3]) -> String {
+        let cell = self.glyphs.horizontal.repeat(Self::cell_size(self.scale).x);
+        format!(
+            "{}{}{}",
+            corners[0],
+            vec![cell; self.board.cols()].join(corners[1]),
+            corners[2]
+        )
+    }
+
+    /// Draws the top border, labelling each column with its number.
+    fn draw_header(&self, printer: &Printer) {
+        let cell = Self::cell_size(self.scale);
+        printer.print((0, 0), &self.border_line(self.glyphs.top));
+        for col in 0..self.board.cols() {
+            let x = col * (cell.x + 1) + 1;
+            match self.selection.is_some() && !self.is_playable(col) {
+                true => printer.with_effect(Effect::Dim, |p| {
+                    p.print((x + cell.x / 2, 0), self.glyphs.full_column)
+                }),
+                false => print_label(printer, x, cell.x, col),
+            }
+        }
+    }
+
+    /// Draws the last column again, dimmed, to the left of the board and the first column to the right, showing that lines wrap around.
+    fn draw_ghost_columns(&self, printer: &Printer, pending: &[Placement]) {
+        let cell = Self::cell_size(self.scale);
+        let cols = self.board.cols();
+        let right = self.ghost_width(self.scale) + cols * (cell.x + 1) + 2;
+        printer.with_effect(Effect::Dim, |printer| {
+            for (x, col) in [(0, cols - 1), (right, 0)] {
+                print_label(printer, x, cell.x, col);
+                for row in 0..self.board.rows() {
+                    let origin = Vec2::new(x, row * (cell.y + 1) + 1);
+                    let landed = !pending.iter().any(|p| p.row == row && p.col == col);
+                    if let Some(piece) = self.board.get_piece(row, col).filter(|_| landed) {
+                        self.draw_disc(printer, origin, piece, false);
+                    } else if self.board.is_blocked(row, col) {
+                        self.draw_blocked(printer, origin);
+                    }
+                }
+            }
+        });
+    }
+
+    /// Fills a blocked space, whose top-left corner is at the given origin.
+    fn draw_blocked(&self, printer: &Printer, origin: Vec2) {
+        let cell = Self::cell_size(self.scale);
+        for line in 0..cell.y {
+            printer.print(origin + (0, line), &self.glyphs.blocked.repeat(cell.x));
+        }
+    }
+
+    fn draw_disc(&self, printer: &Printer, origin: Vec2, piece: GamePiece, ghost: bool) {
+        let cell = Self::cell_size(self.scale);
+        let (disc, fill) = match ghost {
+            true => (self.glyphs.ghost, self.glyphs.ghost_fill),
+            false => (self.glyphs.disc, self.glyphs.disc_fill),
+        };
+        let draw = |p: &Printer| match self.scale {
+            1 => p.print(origin + (cell.x / 2, 0), &disc(piece)),
+            _ => {
+                for line in 0..cell.y {
+                    p.print(origin + (1, line), &fill(piece).repeat(cell.x - 2));
+                }
+            }
+        };
+        match self.glyphs.coloured {
+            true => printer.with_color(
+                ColorStyle::front(terminal_colour(self.appearance.colour(piece))),
+                draw,
+            ),
+            false => draw(printer),
+        }
+    }
+
+    /// Moves the highlighted column in the given direction, skipping full columns.
+    fn move_selection(&mut self, right: bool) -> EventResult {
+        let Some(current) = self.selection.as_ref().map(|s| s.column) else {
+            return EventResult::Ignored;
+        };
+        let next = match right {
+            true => (current + 1..self.board.cols()).find(|col| self.is_playable(*col)),
+            false => (0..current).rev().find(|col| self.is_playable(*col)),
+        };
+        if let (Some(col), Some(selection)) = (next, self.selection.as_mut()) {
+            selection.column = col;
+        }
+        EventResult::Consumed(None)
+    }
+
+    /// Drops a piece into the given column, starting at 0, if there is room.
+    fn drop_piece(&mut self, col: usize) -> EventResult {
+        match self.can_drop(col) {
+            true => self.play(col, Move::Drop(col + 1)),
+            false => EventResult::Consumed(None),
+        }
+    }
+
+    /// Pops the player's piece out of the bottom of the given column, starting at 0, if they are allowed to.
+    fn pop_piece(&mut self, col: usize) -> EventResult {
+        match self.can_pop(col) {
+            true => self.play(col, Move::Pop(col + 1)),
+            false => EventResult::Consumed(None),
+        }
+    }
+
+    /// Selects the column, starting at 0, that a move was played in and passes the move on.
+    fn play(&mut self, col: usize, game_move: Move) -> EventResult {
+        let Some(selection) = self.selection.as_mut() else {
+            return EventResult::Ignored;
+        };
+        selection.column = col;
+        let on_move = Rc::clone(&selection.on_move);
+        EventResult::with_cb(move |c| on_move(c, game_move))
+    }
+
+    /// Finds which animated piece is currently falling and the row it has reached.
+    ///
+    /// Returns [`None`] once every piece has landed.
+    fn falling_piece(&self) -> Option<(usize, usize)> {
+        let animation = self.animation.as_ref()?;
+        let mut elapsed = animation.started.elapsed();
+        for (i, placement) in animation.pieces.iter().enumerate() {
+            let duration = ROW_DROP_TIME * (placement.row as u32 + 1);
+            if elapsed < duration {
+                let row = (elapsed.as_millis() / ROW_DROP_TIME.as_millis()) as usize;
+                return Some((i, row));
+            }
+            elapsed -= duration;
+        }
+        None
+    }
+
+    /// Finds the column, starting at 0, under a position relative to this view.
+    ///
+    /// The ghost columns at the sides of a board that wraps stand for the columns they show.
+    fn column_at(&self, position: Vec2) -> Option<usize> {
+        let cell = Self::cell_size(self.scale);
+        let cols = self.board.cols();
+        if position.y >= self.size_at(self.scale).y {
+            return None;
+        }
+        let Some(x) = position.x.checked_sub(self.ghost_width(self.scale)) else {
+            return Some(cols - 1);
+        };
+        let col = x.saturating_sub(1) / (cell.x + 1);
+        match col < cols {
+            true => (x > 0).then_some(col),
+            false => self.board.wraps().then_some(0),
+        }
+    }
+}
+
+impl View for BoardView {
+    fn draw(&self, printer: &Printer) {
+        let cell = Self::cell_size(self.scale);
+        let glyphs = self.glyphs;
+        let ghost_width = self.ghost_width(self.scale);
+
+        if let Some(selection) = &self.selection {
+            let origin = Vec2::new(ghost_width + selection.column * (cell.x + 1) + 1, 0);
+            self.draw_disc(printer, origin, selection.player, true);
+        }
+
+        let printer = printer.offset((0, self.preview_height(self.scale)));
+        let falling = self.falling_piece();
+        // Pieces that have not yet landed are only drawn while falling
+        let pending: &[Placement] = match (falling, &self.animation) {
+            (Some((i, _)), Some(animation)) => &animation.pieces[i..],
+            _ => &[],
+        };
+        let flash_on = falling.is_none() && self.is_highlight_on();
+
+        if self.board.wraps() {
+            self.draw_ghost_columns(&printer, pending);
+        }
+        let printer = printer.offset((ghost_width, 0));
+        self.draw_header(&printer);
+        for row in 0..self.board.rows() {
+            let top = row * (cell.y + 1) + 1;
+            for line in 0..cell.y {
+                for col in 0..=self.board.cols() {
+                    printer.print((col * (cell.x + 1), top + line), glyphs.vertical);
+                }
+            }
+            for col in 0..self.board.cols() {
+                let origin = Vec2::new(col * (cell.x + 1) + 1, top);
+                if flash_on && self.highlight.contains(&(row, col)) {
+                    printer.with_effect(Effect::Reverse, |p| {
+                        for line in 0..cell.y {
+                            p.print_hline(origin + (0, line), cell.x, " ");
+                        }
+                        if let Some(piece) = self.board.get_piece(row, col) {
+                            self.draw_disc(p, origin, piece, false);
+                        }
+                    });
+                    continue;
+                }
+                if let Some(piece) = self.board.get_piece(row, col) {
+                    if !pending.iter().any(|p| p.row == row && p.col == col) {
+                        self.draw_disc(&printer, origin, piece, false);
+                    }
+                } else if self.board.is_blocked(row, col) {
+                    self.draw_blocked(&printer, origin);
+                }
+            }
+
+            let corners = match row {
+                r if r == self.board.rows() - 1 => glyphs.bottom,
+                _ => glyphs.middle,
+            };
+            printer.print((0, top + cell.y), &self.border_line(corners));
+        }
+
+        if let (Some(placement), Some((_, row))) = (pending.first(), falling) {
+            let origin = Vec2::new(placement.col * (cell.x + 1) + 1, row * (cell.y + 1) + 1);
+            self.draw_disc(&printer, origin, placement.piece, false);
+        }
+    }
+
+    fn layout(&mut self, size: Vec2) {
+        self.scale = self.scale_for(size);
+    }
+
+    fn required_size(&mut self, constraint: Vec2) -> Vec2 {
+        self.size_at(self.scale_for(constraint))
+    }
+
+    fn take_focus(&mut self, _source: Direction) -> Result<EventResult, CannotFocus> {
+        match self.selection {
+            Some(_) => Ok(EventResult::Consumed(None)),
+            None => Err(CannotFocus),
+        }
+    }
+
+    fn on_event(&mut self, event: Event) -> EventResult {
+        let Some(selected) = self.selection.as_ref().map(|s| s.column) else {
+            return EventResult::Ignored;
+        };
+        match event {
+            Event::Key(Key::Left) | Event::Char('h') => self.move_selection(false),
+            Event::Key(Key::Right) | Event::Char('l') => self.move_selection(true),
+            Event::Key(Key::Enter) | Event::Char(' ') => self.drop_piece(selected),
+            Event::Key(Key::Down) | Event::Char('p') => self.pop_piece(selected),
+            Event::Char(c) if c.is_ascii_digit() => match column_for_key(c) {
+                Some(col) if col < self.board.cols() => self.drop_piece(col),
+                _ => EventResult::Ignored,
+            },
+            Event::Mouse {
+                offset,
+                position,
+                event: MouseEvent::Press(MouseButton::Left),
+            } => match position.checked_sub(offset).and_then(|p| self.column_at(p)) {
+                Some(col) => self.drop_piece(col),
+                None => EventResult::Ignored,
+            },
+            _ => EventResult::Ignored,
+        }
+    }
+}
+
+/* Helper functions */
+
+/// Prints a column's number, counting from 1, centred in the width of a cell that starts at `x`.
+fn print_label(printer: &Printer, x: usize, width: usize, col: usize) {
+    let label = (col + 1).to_string();
+    printer.print((x + width.saturating_sub(label.len()) / 2, 0), &label);
+}
+
+/// Finds the column, starting at 0, that a number key drops into, with 0 standing for the tenth column.
+fn column_for_key(key: char) -> Option<usize> {
+    match key.to_digit(10)? {
+        0 => Some(9),
+        n => Some(n as usize - 1),
+    }
+}
+
+/// Describes the number keys that drop into a board's columns, for the help shown beneath it.
+pub(super) fn column_keys(cols: usize) -> String {
+    match cols {
+        10.. => "1-9, 0".to_string(),
+        _ => format!("1-{cols}"),
+    }
+}
+
+/// Finds the pieces on `new` that are not on `old`, in turn order so that any piece belonging to `last_mover` comes last.
+pub(super) fn new_pieces(
+    old: &GameBoard,
+    new: &GameBoard,
+    last_mover: Option<GamePiece>,
+) -> Vec<Placement> {
+    // Bottom rows first, so that pieces stacked in one column fall in order
+    let spaces = || {
+        (0..new.rows())
+            .rev()
+            .flat_map(|row| (0..new.cols()).map(move |col| (row, col)))
+    };
+
+    // A board that has lost pieces or changed size is a different game, so there is nothing to animate
+    if old.cleared() != new.cleared()
+        || spaces().any(|(row, col)| {
+            old.get_piece(row, col).is_some() && old.get_piece(row, col) != new.get_piece(row, col)
+        })
+    {
+        return Vec::new();
+    }
+
+    let mut pieces: Vec<Placement> = spaces()
+        .filter(|(row, col)| old.get_piece(*row, *col).is_none())
+        .filter_map(|(row, col)| {
+            let piece = new.get_piece(row, col)?;
+            Some(Placement { row, col, piece })
+        })
+        .collect();
+    if let Some(last_mover) = last_mover {
+        pieces.sort_by_key(|p| Reverse(p.piece.turns_before(last_mover)));
+    }
+    pieces
+}
+
+fn terminal_colour(colour: PieceColour) -> Color {
+    Color::Light(match colour {
+        PieceColour::Red => BaseColor::Red,
+        PieceColour::Yellow => BaseColor::Yellow,
+        PieceColour::Green => BaseColor::Green,
+        PieceColour::Blue => BaseColor::Blue,
+        PieceColour::Magenta => BaseColor::Magenta,
+        PieceColour::Cyan => BaseColor::Cyan,
+        PieceColour::White => BaseColor::White,
+    })
+}
+
+/// Guesses whether the terminal can display colours, honouring the `NO_COLOR` convention.
+fn colour_supported() -> bool {
+    if std::env::var_os("NO_COLOR").is_some() {
+        return false;
+    }
+    match std::env::var("TERM") {
+        Ok(term) => term != "dumb" && !term.starts_with("vt"),
+        Err(_) => cfg!(windows),
+    }
+}
@@ -0,0 +1,174 @@
+use super::board_view::BoardView;
+use crate::game::{
+    record::{GameRecord, RecordError},
+    state::GameState,
+};
+use crate::settings::AppearanceSettings;
+use crate::view::narration;
+
+use cursive::{
+    direction::Direction,
+    event::{Event, EventResult, Key},
+    view::CannotFocus,
+    Printer, Vec2, View, XY,
+};
+use std::time::{Duration, Instant};
+
+const MIN_AUTOPLAY_DELAY: Duration = Duration::from_millis(250);
+const MAX_AUTOPLAY_DELAY: Duration = Duration::from_secs(4);
+const DEFAULT_AUTOPLAY_DELAY: Duration = Duration::from_secs(1);
+const HELP: &str = "<-/-> step  Home/End jump  Space autoplay  +/- speed  Esc close";
+/// Number of status lines shown underneath the board.
+const STATUS_LINES: usize = 3;
+
+/// Steps through the positions of a recorded game.
+pub(super) struct ReplayView {
+    record: GameRecord,
+    positions: Vec<GameState>,
+    board_view: BoardView,
+    /// Index into `positions`; `0` is the position before any moves were made.
+    current: usize,
+    autoplay: bool,
+    autoplay_delay: Duration,
+    last_step: Instant,
+}
+
+impl ReplayView {
+    pub(super) fn new(
+        record: GameRecord,
+        appearance: &AppearanceSettings,
+    ) -> Result<Self, RecordError> {
+        let positions = record.positions()?;
+        let board_view = BoardView::new(*positions[0].get_board()).with_appearance(appearance);
+        Ok(Self {
+            record,
+            positions,
+            board_view,
+            current: 0,
+            autoplay: false,
+            autoplay_delay: DEFAULT_AUTOPLAY_DELAY,
+            last_step: Instant::now(),
+        })
+    }
+
+    fn last(&self) -> usize {
+        self.positions.len() - 1
+    }
+
+    fn go_to(&mut self, index: usize) {
+        self.current = index.min(self.last());
+        self.last_step = Instant::now();
+        self.board_view
+            .set_board(*self.positions[self.current].get_board());
+    }
+
+    /// Describes the move that led to the current position.
+    fn move_description(&self) -> String {
+        match self.current {
+            0 => format!(
+                "Start of game, {} to move",
+                self.positions[0].get_next_player()
+            ),
+            n => {
+                let mover = self.positions[n - 1].get_next_player();
+                let recorded = &self.record.get_moves()[n - 1];
+                let mut text = format!(
+                    "Move {n}/{}: {mover} {}",
+                    self.last(),
+                    narration::describe_move(recorded.game_move)
+                );
+                if let Some(comment) = &recorded.comment {
+                    text.push_str(&format!(" ({comment})"));
+                }
+                text
+            }
+        }
+    }
+
+    fn autoplay_description(&self) -> String {
+        match self.autoplay {
+            true => format!(
+                "Autoplay: on, {:.2}s per move",
+                self.autoplay_delay.as_secs_f32()
+            ),
+            false => "Autoplay: off".to_string(),
+        }
+    }
+
+    fn status_lines(&self) -> [String; STATUS_LINES] {
+        [
+            self.move_description(),
+            self.autoplay_description(),
+            HELP.to_string(),
+        ]
+    }
+
+    /// Space left for the board once the status lines are accounted for.
+    fn board_constraint(size: Vec2) -> Vec2 {
+        size.saturating_sub((0, STATUS_LINES))
+    }
+}
+
+impl View for ReplayView {
+    fn draw(&self, printer: &Printer) {
+        let board_size = Self::board_constraint(printer.size);
+        self.board_view.draw(&printer.cropped(board_size));
+
+        let board_height = printer.size.y.saturating_sub(STATUS_LINES);
+        for (i, line) in self.status_lines().iter().enumerate() {
+            printer.print((0, board_height + i), line);
+        }
+    }
+
+    fn layout(&mut self, size: Vec2) {
+        self.board_view.layout(Self::board_constraint(size));
+    }
+
+    fn required_size(&mut self, constraint: Vec2) -> Vec2 {
+        let board = self
+            .board_view
+            .required_size(Self::board_constraint(constraint));
+        let text_width = self
+            .status_lines()
+            .iter()
+            .map(|l| l.chars().count())
+            .max()
+            .unwrap_or(0);
+        XY::new(board.x.max(text_width), board.y + STATUS_LINES)
+    }
+
+    fn take_focus(&mut self, _source: Direction) -> Result<EventResult, CannotFocus> {
+        Ok(EventResult::Consumed(None))
+    }
+
+    fn on_event(&mut self, event: Event) -> EventResult {
+        match event {
+            Event::Key(Key::Left) => self.go_to(self.current.saturating_sub(1)),
+            Event::Key(Key::Right) => self.go_to(self.current + 1),
+            Event::Key(Key::Home) => self.go_to(0),
+            Event::Key(Key::End) => self.go_to(self.last()),
+            Event::Char(' ') => {
+                self.autoplay = !self.autoplay;
+                // Restart from the beginning when autoplay is started on the final position
+                if self.autoplay && self.current == self.last() {
+                    self.go_to(0);
+                }
+                self.last_step = Instant::now();
+            }
+            Event::Char('+') => {
+                self.autoplay_delay = (self.autoplay_delay / 2).max(MIN_AUTOPLAY_DELAY)
+            }
+            Event::Char('-') => {
+                self.autoplay_delay = (self.autoplay_delay * 2).min(MAX_AUTOPLAY_DELAY)
+            }
+            Event::Refresh => {
+                if self.autoplay && self.last_step.elapsed() >= self.autoplay_delay {
+                    self.go_to(self.current + 1);
+                    self.autoplay = self.current < self.last();
+                }
+            }
+            _ => return EventResult::Ignored,
+        }
+        EventResult::Consumed(None)
+    }
+}
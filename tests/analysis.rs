@@ -0,0 +1,109 @@
+//! End-to-end tests for the commands that solve positions and analyze recorded games without opening a view.
+#![cfg(all(feature = "cli", feature = "ai"))]
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+/// Runs the game with the given arguments, writing the input to it and waiting for it to exit.
+fn run(args: &[&str], input: &str) -> Output {
+    let mut game = Command::new(env!("CARGO_BIN_EXE_fourstack"))
+        .args(args)
+        .env(
+            "XDG_CONFIG_HOME",
+            std::env::temp_dir().join("fourstack-no-config"),
+        )
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Game should start");
+    game.stdin
+        .take()
+        .expect("Game input should be piped")
+        .write_all(input.as_bytes())
+        .expect("Game should read its input");
+    game.wait_with_output().expect("Game should finish")
+}
+
+fn stdout(output: &Output) -> String {
+    assert!(output.status.success());
+    String::from_utf8(output.stdout.clone()).expect("Game output should be text")
+}
+
+#[test]
+fn solve_prints_score_and_best_move() {
+    let output = stdout(&run(&["solve", "445566"], ""));
+    assert!(output.contains("Red to move"));
+    // Red wins with their fourth piece, at either end of their three in a row
+    assert!(output.contains("Score: 18 (Red wins)"));
+    assert!(output.contains("Best move: 3"));
+
+    let output = stdout(&run(&["solve", "44556"], ""));
+    assert!(output.contains("Yellow to move"));
+    assert!(output.contains("Score: -18 (Red wins)"));
+}
+
+#[test]
+fn solve_rejects_finished_games() {
+    let output = run(&["solve", "4455667"], "");
+    assert!(!output.status.success());
+    let error = String::from_utf8_lossy(&output.stderr);
+    assert!(error.contains("Error: The game is already over"));
+}
+
+#[test]
+fn solve_rejects_column_zero() {
+    let output = run(&["solve", "405"], "");
+    assert!(!output.status.success());
+    let error = String::from_utf8_lossy(&output.stderr);
+    assert!(error.contains("Error: Columns are numbered from 1"));
+}
+
+#[test]
+fn solve_batch_prints_json_lines() {
+    let output = stdout(&run(&["solve", "--batch"], "445566\n\n4455667\n44556\n"));
+    let results = output
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).expect("Lines should be JSON"))
+        .collect::<Vec<_>>();
+    assert_eq!(3, results.len());
+
+    assert_eq!("445566", results[0]["position"]);
+    assert_eq!("Red", results[0]["to_move"]);
+    assert_eq!(18, results[0]["score"]);
+    assert_eq!(3, results[0]["best_move"]);
+
+    // A position that can't be solved is reported without stopping the batch
+    assert_eq!("4455667", results[1]["position"]);
+    assert_eq!("The game is already over", results[1]["error"]);
+
+    assert_eq!("Yellow", results[2]["to_move"]);
+    assert_eq!(-18, results[2]["score"]);
+}
+
+#[test]
+fn analyze_scores_every_move() {
+    let path = std::env::temp_dir().join(format!("fourstack-analyze-{}.rec", std::process::id()));
+    // Three in a row on a small board, so that solving is quick
+    std::fs::write(
+        &path,
+        "[Red \"Alice\"]\n[Yellow \"Bob\"]\n[Date \"2024.01.02\"]\n[First \"R\"]\n\
+         [Size \"4x4\"]\n[WinLength \"3\"]\n\n1. 2 2 2. 3 3 3. 4 Red\n",
+    )
+    .expect("Record file should be written");
+    let output = stdout(&run(&["analyze", path.to_str().unwrap()], ""));
+    let _ = std::fs::remove_file(&path);
+
+    assert!(output.contains("Game 1: Alice vs Bob (2024.01.02)"));
+    let rows = output
+        .lines()
+        .skip_while(|line| !line.starts_with("Move"))
+        .skip(1)
+        .map(|line| line.split_whitespace().collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    assert_eq!(5, rows.len());
+    assert_eq!(vec!["1", "Red", "2"], rows[0][..3]);
+    assert_eq!(vec!["2", "Yellow", "2"], rows[1][..3]);
+    // Red's winning move scores as well as the best move, with six of their pieces left over
+    assert_eq!(vec!["5", "Red", "4", "6", "1", "6"], rows[4]);
+}
@@ -0,0 +1,91 @@
+//! End-to-end tests that drive the engine protocol the way another program would, by piping commands into it.
+#![cfg(all(feature = "cli", feature = "ai"))]
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Runs the engine with the given commands as input, returning the lines it printed.
+fn run_engine(commands: &str) -> Vec<String> {
+    let mut engine = Command::new(env!("CARGO_BIN_EXE_fourstack"))
+        .arg("engine")
+        .env(
+            "XDG_CONFIG_HOME",
+            std::env::temp_dir().join("fourstack-no-config"),
+        )
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Engine should start");
+    engine
+        .stdin
+        .take()
+        .expect("Engine input should be piped")
+        .write_all(commands.as_bytes())
+        .expect("Engine should read its input");
+    let output = engine.wait_with_output().expect("Engine should finish");
+    assert!(output.status.success());
+    String::from_utf8(output.stdout)
+        .expect("Engine output should be text")
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn identifies_itself_and_its_options() {
+    let output = run_engine("uci\nisready\nsetoption name Level value Easy\n");
+    assert!(output[0].starts_with("id name FourStack"));
+    assert!(output.contains(
+        &"option name Level type combo default Hard var Easy var Medium var Hard".to_string()
+    ));
+    assert_eq!(
+        vec!["uciok", "readyok"],
+        output[output.len() - 2..].to_vec()
+    );
+}
+
+#[test]
+fn plays_the_winning_move() {
+    let output = run_engine("position startpos moves 4 4 5 5 6 6\ngo depth 3\n");
+    // One line for each depth searched, then the move
+    assert_eq!(4, output.len());
+    assert!(output[0].starts_with("info depth 1 score win time "));
+    assert!(output[2].starts_with("info depth 3 score win time "));
+    assert!(["bestmove 3", "bestmove 7"].contains(&output[3].as_str()));
+}
+
+#[test]
+fn searches_within_limits_until_quit() {
+    let output = run_engine(
+        "setoption name Level value medium\nposition startpos\ngo movetime 100 depth 2\nquit\ngo\n",
+    );
+    let depths = output
+        .iter()
+        .filter(|line| line.starts_with("info depth"))
+        .count();
+    assert!((1..=2).contains(&depths));
+    assert!(output.last().unwrap().starts_with("bestmove "));
+    // Nothing is answered after quitting
+    assert_eq!(
+        1,
+        output
+            .iter()
+            .filter(|line| line.starts_with("bestmove"))
+            .count()
+    );
+}
+
+#[test]
+fn reports_invalid_commands() {
+    let output = run_engine(
+        "position startpos moves 4 4 5 5 6 6 7\ngo\n\
+         position startpos moves 9\nfly\nsetoption name Level value Expert\ngo depth\n",
+    );
+    // The game is over after Red's fourth piece, so there is no move to play
+    assert_eq!("bestmove none", output[0]);
+    assert!(output[1].starts_with("info string Move 1 is illegal"));
+    assert_eq!("info string Unknown command: fly", output[2]);
+    assert_eq!("info string Unknown level: Expert", output[3]);
+    assert!(output[4].starts_with("info string Expected go"));
+    assert_eq!(5, output.len());
+}
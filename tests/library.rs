@@ -0,0 +1,134 @@
+//! Tests that use the game as a library, the way bots and other tools built on it would.
+#![cfg(feature = "ai")]
+
+use fourstack::client::ai::solver::Solver;
+use fourstack::{
+    choose_move, notation, EndgameType, FreeForAll, GameBoard, GamePiece, GameState, Move, Variant,
+};
+use std::time::Duration;
+
+#[test]
+fn plays_a_game_through_the_public_api() {
+    let mut state = GameState::with_board(GameBoard::default(), GamePiece::Red);
+    for column in [4, 4, 5, 5, 6, 6] {
+        state.play_move(column).unwrap();
+    }
+    assert_eq!(EndgameType::None, state.check_endgame());
+    assert_eq!(
+        vec![3, 7],
+        state.get_board().winning_columns(GamePiece::Red)
+    );
+
+    // Both the AI and the solver find one of Red's winning moves
+    let ai_move = choose_move(&state, 2, Duration::from_secs(1)).unwrap();
+    assert!([Move::Drop(3), Move::Drop(7)].contains(&ai_move));
+    let (best, score) = Solver::new(state.get_board()).best_move(&state).unwrap();
+    assert_eq!((3, 18), (best, score));
+
+    state.play(ai_move).unwrap();
+    assert_eq!(Some(GamePiece::Red), state.get_board().is_winner());
+    assert!(!state.get_board().is_full());
+
+    let position = notation::to_notation(&state);
+    assert_eq!(state, notation::from_notation(&position).unwrap());
+}
+
+#[test]
+fn ai_plays_free_for_all_games() {
+    let mut state = GameState::with_board(GameBoard::with_size(7, 9, 4).unwrap(), GamePiece::Red)
+        .with_players(3, FreeForAll::FirstToConnect);
+    for column in [1, 2, 3, 1, 2, 3, 1] {
+        state.play_move(column).unwrap();
+    }
+    // Green would let Red win, so Yellow has to block even though the threat isn't aimed at them alone
+    assert_eq!(&GamePiece::Yellow, state.get_next_player());
+    let ai_move = choose_move(&state, 3, Duration::from_secs(1)).unwrap();
+    assert_eq!(Move::Drop(1), ai_move);
+
+    state.play(ai_move).unwrap();
+    assert_eq!(&GamePiece::Green, state.get_next_player());
+    let position = notation::to_notation(&state);
+    assert!(position.ends_with(" G 3p"));
+    assert_eq!(state, notation::from_notation(&position).unwrap());
+}
+
+#[test]
+fn mirrored_games_get_mirrored_moves() {
+    // Each opening leaves the player to move a single good move, as the AI picks at random between equally good ones
+    let openings: [&[usize]; 3] = [&[2, 5, 2, 5, 2], &[1, 7, 1, 7, 1, 6], &[2, 5, 3, 5, 4]];
+    for moves in openings {
+        let mut state = GameState::with_board(GameBoard::default(), GamePiece::Red);
+        let mut mirrored = state;
+        for column in moves {
+            state.play_move(*column).unwrap();
+            mirrored.play_move(8 - column).unwrap();
+        }
+        assert_eq!(mirrored, state.mirrored());
+
+        let ai_move = choose_move(&state, 4, Duration::from_secs(1)).unwrap();
+        let mirrored_move = choose_move(&mirrored, 4, Duration::from_secs(1)).unwrap();
+        assert_eq!(
+            ai_move.mirrored(state.get_board()),
+            mirrored_move,
+            "after {moves:?}"
+        );
+    }
+
+    // The solver breaks ties on its own, so mirrored positions with several good moves still get mirrored moves
+    let mut state = GameState::with_board(GameBoard::default(), GamePiece::Red);
+    for column in "7422341735647741166133573473242566".chars() {
+        state
+            .play_move(column.to_digit(10).unwrap() as usize)
+            .unwrap();
+    }
+    let mut solver = Solver::new(state.get_board());
+    let (best, score) = solver.best_move(&state).unwrap();
+    assert_eq!(
+        (8 - best, score),
+        solver.best_move(&state.mirrored()).unwrap()
+    );
+}
+
+#[test]
+fn cylinder_lines_wrap_around_the_edges() {
+    let mut state =
+        GameState::with_board(GameBoard::default(), GamePiece::Red).with_variant(Variant::Cylinder);
+    for column in [6, 3, 7, 3, 1] {
+        state.play_move(column).unwrap();
+    }
+    // Yellow can only block one end of the line, and loses whichever it picks
+    assert!(state
+        .get_board()
+        .winning_columns(GamePiece::Yellow)
+        .is_empty());
+    assert_eq!(
+        vec![2, 5],
+        state.get_board().winning_columns(GamePiece::Red)
+    );
+    state.play_move(2).unwrap();
+    let win = choose_move(&state, 2, Duration::from_secs(1)).unwrap();
+    state.play(win).unwrap();
+    assert_eq!(EndgameType::Win(GamePiece::Red), state.check_endgame());
+    assert!(state
+        .get_board()
+        .get_winning_line()
+        .unwrap()
+        .contains(&(5, 0)));
+}
+
+#[test]
+fn misere_ai_avoids_completing_lines() {
+    let mut state =
+        GameState::with_board(GameBoard::default(), GamePiece::Red).with_variant(Variant::Misere);
+    for column in [1, 1, 2, 2, 3, 3] {
+        state.play_move(column).unwrap();
+    }
+    assert_eq!(vec![4], state.get_board().winning_columns(GamePiece::Red));
+    for depth in 1..=3 {
+        let ai_move = choose_move(&state, depth, Duration::from_secs(1)).unwrap();
+        assert_ne!(Move::Drop(4), ai_move, "at depth {depth}");
+    }
+
+    state.play_move(4).unwrap();
+    assert_eq!(EndgameType::Win(GamePiece::Yellow), state.check_endgame());
+}
@@ -0,0 +1,462 @@
+//! End-to-end tests that play through the plain and narrated line-based interfaces by piping moves into the game.
+#![cfg(feature = "cli")]
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+
+/// Runs the game with the given lines as input and default settings, returning everything it printed.
+fn play(input: &str) -> String {
+    play_with_flag("--plain", input)
+}
+
+fn play_with_flag(flag: &str, input: &str) -> String {
+    // Nothing is saved here, so the game starts from the defaults each time
+    let config_dir = std::env::temp_dir().join("fourstack-no-config");
+    play_with_config(flag, input, &config_dir)
+}
+
+/// Creates an empty config directory for a test, so that it neither reads nor changes the user's settings.
+fn config_dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("fourstack-{test}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).expect("Config directory should be created");
+    dir
+}
+
+fn play_with_config(flag: &str, input: &str, config_dir: &Path) -> String {
+    play_with_args(&[flag], input, config_dir)
+}
+
+fn play_with_args(args: &[&str], input: &str, config_dir: &Path) -> String {
+    let mut game = start(args, config_dir);
+    send_input(&mut game, input);
+    finish(game, String::new())
+}
+
+fn start(args: &[&str], config_dir: &Path) -> Child {
+    Command::new(env!("CARGO_BIN_EXE_fourstack"))
+        .args(args)
+        .env("XDG_CONFIG_HOME", config_dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Game should start")
+}
+
+fn send_input(game: &mut Child, input: &str) {
+    game.stdin
+        .take()
+        .expect("Game input should be piped")
+        .write_all(input.as_bytes())
+        .expect("Game should read its input");
+}
+
+/// Waits for the game to exit, returning what it printed after anything already read.
+fn finish(game: Child, read: String) -> String {
+    let output = game.wait_with_output().expect("Game should finish");
+    assert!(output.status.success());
+    read + &String::from_utf8(output.stdout).expect("Game output should be text")
+}
+
+/// Counts the pieces on the last board printed.
+fn last_board_pieces(output: &str) -> usize {
+    let board_start = output
+        .rfind("+-1-+")
+        .expect("Output should include a board");
+    output[board_start..]
+        .lines()
+        .skip(1)
+        .take_while(|line| line.starts_with(['|', '+']))
+        .map(|line| line.matches(['R', 'Y', 'G', 'B']).count())
+        .sum()
+}
+
+/// Finds the player asked to move first.
+fn first_to_move(output: &str) -> &str {
+    output
+        .lines()
+        .find_map(|line| line.split_once(" to move."))
+        .map(|(player, _)| player)
+        .expect("Output should ask for a move")
+}
+
+#[test]
+fn local_game_to_a_win() {
+    let output = play("2\n4\n5\n4\n5\n4\n5\n4\nn\n");
+    assert!(output.contains(" wins!"));
+    assert!(output.contains("Play again?"));
+    assert_eq!(last_board_pieces(&output), 7);
+}
+
+#[test]
+fn undo_and_quit() {
+    let output = play("2\n4\n5\nundo\nquit\n");
+    assert_eq!(last_board_pieces(&output), 1);
+    assert!(!output.contains("Error"));
+
+    let output = play("2\n4\nundo\nundo\nquit\n");
+    assert!(output.contains("Error: There are no moves to undo"));
+}
+
+#[test]
+#[cfg(feature = "ai")]
+fn ai_replies_and_undo_takes_back_both_moves() {
+    let output = play("1\n4\n");
+    assert_eq!(last_board_pieces(&output), 2);
+
+    let output = play("1\n4\nundo\n");
+    assert_eq!(last_board_pieces(&output), 0);
+}
+
+#[test]
+fn invalid_input() {
+    let output = play("9\n2\n8\nfour\nquit\n");
+    assert!(output.contains("Please enter a number from 1 to 6"));
+    assert!(output.contains("Error: Invalid move"));
+    assert!(output.contains("Unknown command: four"));
+}
+
+#[test]
+fn closed_input_quits() {
+    let output = play("");
+    assert!(output.contains("Select game mode:"));
+
+    let output = play("2\n3\n");
+    assert_eq!(last_board_pieces(&output), 1);
+}
+
+#[test]
+fn narrated_game() {
+    let output = play_with_flag(
+        "--narrate",
+        "2\n4\n4\n3\nboard\ncolumn 4\n3\n5\n5\nundo\n5\n2\nn\n",
+    );
+    assert!(!output.contains("+-1-+"));
+    assert!(output.contains("dropped in column 4, landing on row 1."));
+    assert!(output.contains("dropped in column 4, landing on row 2."));
+    assert!(output.contains("Column 1 is empty."));
+    assert!(
+        output.contains("Column 4 has Yellow, Red from bottom.")
+            || output.contains("Column 4 has Red, Yellow from bottom.")
+    );
+    assert!(output.contains("threatens to win in column 2 or 6."));
+    assert!(output.contains("can win in column 2 or 6."));
+    assert!(output.contains("piece in column 5, row 2 was taken back."));
+    assert!(output.contains(
+        "has four in a row at column 2 row 1, column 3 row 1, column 4 row 1, column 5 row 1."
+    ));
+}
+
+#[test]
+#[cfg(feature = "ai")]
+fn settings_are_saved_and_used() {
+    let dir = config_dir("settings");
+    let output = play_with_config("--plain", "5\n4\n9\n13\nAlice\nsave\n2\n9\nquit\n", &dir);
+    assert!(output.contains("4. Board columns: 9"));
+    assert!(!output.contains("Error"));
+
+    let config = std::fs::read_to_string(dir.join("fourstack").join("config.toml"))
+        .expect("Settings should be saved");
+    assert!(config.contains("cols = 9"));
+    assert!(config.contains("red = \"Alice\""));
+
+    let output = play_with_config("--plain", "\n9\n", &dir);
+    assert!(output.contains("+-1-+-2-+-3-+-4-+-5-+-6-+-7-+-8-+-9-+"));
+    assert_eq!(last_board_pieces(&output), 2);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+#[cfg(feature = "ai")]
+fn play_command_skips_the_menu() {
+    let dir = std::env::temp_dir().join("fourstack-no-config");
+    let args = ["--plain", "play", "--mode", "local", "--first", "yellow"];
+    let output = play_with_args(&args, "4\nquit\n", &dir);
+    assert!(!output.contains("Select game mode:"));
+    assert_eq!(first_to_move(&output), "Yellow");
+    assert_eq!(last_board_pieces(&output), 1);
+
+    let args = [
+        "--plain", "play", "--mode", "ai", "--first", "ai", "--level", "easy",
+    ];
+    let output = play_with_args(&args, "quit\n", &dir);
+    assert_eq!(last_board_pieces(&output), 1);
+}
+
+#[test]
+fn popout_game_pops_and_draws_by_repetition() {
+    let dir = std::env::temp_dir().join("fourstack-no-config");
+    let args = [
+        "--narrate",
+        "play",
+        "--mode",
+        "local",
+        "--first",
+        "red",
+        "--variant",
+        "popout",
+    ];
+    let output = play_with_args(&args, "4\n4\np4\n1\n2\np1\np2\n1\n2\np1\np2\nn\n", &dir);
+    assert!(output.contains("\"p<column>\" to pop out"));
+    assert!(output.contains("Red popped a piece out of the bottom of column 4."));
+    assert!(!output.contains("Error"));
+    assert!(output.contains("It's a draw by repetition!"));
+
+    // Pieces can only be popped out when playing PopOut
+    let args = ["--plain", "play", "--mode", "local", "--first", "red"];
+    let output = play_with_args(&args, "4\np4\nquit\n", &dir);
+    assert!(
+        output.contains("Error: Invalid move: Pieces can only be popped out when playing PopOut.")
+    );
+}
+
+#[test]
+fn misere_game_is_lost_by_completing_a_line() {
+    let dir = std::env::temp_dir().join("fourstack-no-config");
+    let args = [
+        "--narrate",
+        "play",
+        "--mode",
+        "local",
+        "--first",
+        "red",
+        "--variant",
+        "misere",
+    ];
+    let output = play_with_args(&args, "4\n5\n4\n5\n4\n5\n4\nn\n", &dir);
+    assert!(output.contains("Red would lose by playing column 4."));
+    assert!(output.contains("Red made four in a row, so Yellow wins!"));
+}
+
+#[test]
+fn blocked_spaces_from_a_layout_or_at_random() {
+    let dir = config_dir("blocked");
+    let layout = dir.join("layout.txt");
+    std::fs::write(
+        &layout,
+        ".......\n.......\n.......\n.......\n.......\n...#...\n",
+    )
+    .expect("Layout file should be written");
+    let path = layout.to_str().expect("Temp path should be text");
+
+    let args = [
+        "--narrate",
+        "play",
+        "--mode",
+        "local",
+        "--first",
+        "red",
+        "--layout",
+        path,
+    ];
+    let output = play_with_args(&args, "4\ncolumn 4\nquit\n", &dir);
+    assert!(output.contains("Red dropped in column 4, landing on row 2."));
+    assert!(output.contains("Column 4 has a blocked space, Red from bottom."));
+
+    let blocked_board = |seed: &str| {
+        let args = [
+            "--plain",
+            "--seed",
+            seed,
+            "play",
+            "--mode",
+            "local",
+            "--blockers",
+            "6",
+        ];
+        let output = play_with_args(&args, "", &dir);
+        let start = output.find("+-1-+").expect("Output should include a board");
+        output[start..]
+            .lines()
+            .take_while(|line| line.starts_with(['|', '+']))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    let board = blocked_board("3");
+    assert_eq!(6, board.matches('#').count());
+    assert_eq!(board, blocked_board("3"));
+
+    let args = ["--plain", "play", "--mode", "local", "--blockers", "50"];
+    let output = play_with_args(&args, "", &dir);
+    assert!(output.contains(
+        "Error: Invalid blocked spaces: Can't block 50 spaces, this board has room for at most 10. Ignoring the game options given."
+    ));
+    assert!(!output.contains('#'));
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn three_player_hot_seat_game() {
+    let dir = config_dir("free-for-all");
+    std::fs::create_dir_all(dir.join("fourstack")).expect("Config directory should be created");
+    let config = "[game]\nfirst_player = \"red\"\nplayers = 3\n\n[players]\ngreen = \"Carol\"\n";
+    std::fs::write(dir.join("fourstack").join("config.toml"), config)
+        .expect("Config should be written");
+
+    let output = play_with_config("--plain", "2\n1\n2\n3\n1\n2\n3\n1\n2\n3\n1\nn\n", &dir);
+    assert!(output.contains("Carol (G) to move."));
+    assert!(output.contains("Red wins!"));
+    assert_eq!(last_board_pieces(&output), 10);
+
+    let args = ["--plain", "play", "--mode", "local", "--variant", "popout"];
+    let output = play_with_args(&args, "", &dir);
+    assert!(output.contains(
+        "Error: Can't play with 3 players, these rules allow 2 to 2. Ignoring the game options given."
+    ));
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn seed_repeats_random_choices() {
+    let dir = std::env::temp_dir().join("fourstack-no-config");
+    let first_players = |seed: &str| {
+        (0..5)
+            .map(|_| {
+                let args = ["--plain", "--seed", seed, "play", "--mode", "local"];
+                first_to_move(&play_with_args(&args, "", &dir)).to_string()
+            })
+            .collect::<Vec<_>>()
+    };
+    let players = first_players("7");
+    assert_eq!(players, first_players("7"));
+    assert!(players.iter().all(|p| p == "Red" || p == "Yellow"));
+}
+
+#[test]
+fn replay_command_opens_the_file() {
+    let dir = config_dir("replay");
+    let file = dir.join("games.rec");
+    let game = "[Red \"Alice\"]\n[Yellow \"Bob\"]\n[First \"R\"]\n[Result \"*\"]\n\n1. 4 4 *\n";
+    std::fs::write(&file, game).expect("Record file should be written");
+
+    let path = file.to_str().expect("Temp path should be text");
+    let output = play_with_args(&["--plain", "replay", path], "1\n", &dir);
+    assert!(!output.contains("Game record file"));
+    assert!(output.contains("Alice vs Bob"));
+    assert!(output.contains("Move 2/2: Y dropped in column 4"));
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+#[cfg(feature = "online")]
+fn online_game_between_host_and_guest() {
+    use std::io::{BufRead, BufReader, Read};
+    use std::net::TcpListener;
+
+    let dir = config_dir("online");
+    std::fs::create_dir_all(dir.join("fourstack")).expect("Config directory should be created");
+    let config = "[game]\nfirst_player = \"red\"\n\n[players]\nred = \"Alice\"\nyellow = \"Bob\"\n";
+    std::fs::write(dir.join("fourstack").join("config.toml"), config)
+        .expect("Config should be written");
+
+    // Find a free port for the host to listen on
+    let port = TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("A local port should be free")
+        .port()
+        .to_string();
+
+    let mut host = start(&["--plain", "host", "--port", &port], &dir);
+    let mut host_output = BufReader::new(host.stdout.take().expect("Host output should be piped"));
+    let mut waiting = String::new();
+    host_output
+        .read_line(&mut waiting)
+        .expect("Host should say it is waiting");
+    assert!(waiting.contains(&format!(
+        "Waiting for an opponent to connect on port {port}"
+    )));
+
+    let address = format!("127.0.0.1:{port}");
+    let mut guest = start(&["--plain", "connect", &address], &dir);
+    send_input(&mut guest, "5\n5\n5\nn\n");
+    send_input(&mut host, "4\n4\nundo\n4\n4\nn\n");
+
+    let guest_output = finish(guest, String::new());
+    let mut rest = String::new();
+    host_output
+        .read_to_string(&mut rest)
+        .expect("Host output should be text");
+    let host_output = finish(host, waiting + &rest);
+
+    assert!(host_output.contains("Error: Moves cannot be undone in online games"));
+    for output in [&host_output, &guest_output] {
+        assert!(output.contains("Alice (R) wins!"));
+        assert_eq!(last_board_pieces(output), 7);
+    }
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+/// Writes a script that identifies itself as an engine and runs `on_go` whenever it is asked for a move.
+#[cfg(unix)]
+fn stub_engine(dir: &Path, name: &str, on_go: &str) -> String {
+    use std::os::unix::fs::PermissionsExt;
+
+    let script = format!(
+        "#!/bin/sh\n\
+         while read -r command rest; do\n\
+         \x20   case \"$command\" in\n\
+         \x20       uci) echo \"id name Stub\"; echo \"uciok\" ;;\n\
+         \x20       go) {on_go} ;;\n\
+         \x20       quit) exit 0 ;;\n\
+         \x20   esac\n\
+         done\n"
+    );
+    let path = dir.join(name);
+    std::fs::write(&path, script).expect("Engine script should be written");
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+        .expect("Engine script should be executable");
+    path.to_str().expect("Temp path should be text").to_string()
+}
+
+#[test]
+#[cfg(unix)]
+fn external_engine_plays_and_forfeits() {
+    let dir = config_dir("engine");
+    std::fs::create_dir_all(dir.join("fourstack")).expect("Config directory should be created");
+    let config = "[game]\nfirst_player = \"red\"\n\n[ai]\nthink_time_ms = 100\n\n[players]\nred = \"Alice\"\n";
+    std::fs::write(dir.join("fourstack").join("config.toml"), config)
+        .expect("Config should be written");
+
+    // The engine always drops its piece in the first column, so Alice wins down the middle
+    let engine = stub_engine(
+        &dir,
+        "first-column",
+        "echo \"info depth 1\"; echo \"bestmove 1\"",
+    );
+    let args = ["--plain", "play", "--engine", &engine, "--first", "human"];
+    let output = play_with_args(&args, "4\n4\n4\n4\nn\n", &dir);
+    assert!(!output.contains("Error"));
+    assert!(output.contains("Alice (R) wins!"));
+    assert_eq!(last_board_pieces(&output), 7);
+
+    let engine = stub_engine(&dir, "crash", "exit 1");
+    let args = ["--plain", "play", "--engine", &engine, "--first", "human"];
+    let output = play_with_args(&args, "4\nn\n", &dir);
+    assert!(
+        output.contains("Error: Engine error: The engine stopped running, so it forfeits the game")
+    );
+    assert!(output.contains("Alice (R) wins!"));
+    assert_eq!(last_board_pieces(&output), 1);
+
+    let engine = stub_engine(&dir, "silent", ":");
+    let args = ["--plain", "play", "--engine", &engine, "--first", "human"];
+    let output = play_with_args(&args, "4\nn\n", &dir);
+    assert!(output.contains("The engine took too long to reply, so it forfeits the game"));
+    assert!(output.contains("Alice (R) wins!"));
+
+    // An engine that fails on the opening move forfeits like on any other move
+    let engine = stub_engine(&dir, "illegal", "echo \"bestmove 9\"");
+    let args = ["--plain", "play", "--engine", &engine, "--first", "ai"];
+    let output = play_with_args(&args, "n\n", &dir);
+    assert!(
+        output.contains("The engine played an illegal move in column 9, so it forfeits the game")
+    );
+    assert!(output.contains("wins!"));
+    assert_eq!(last_board_pieces(&output), 0);
+
+    let args = ["--plain", "play", "--engine", "/nonexistent/engine"];
+    let output = play_with_args(&args, "6\n", &dir);
+    assert!(output.contains("Error: Engine error: Could not start /nonexistent/engine"));
+    let _ = std::fs::remove_dir_all(dir);
+}